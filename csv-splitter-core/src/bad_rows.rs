@@ -0,0 +1,169 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use csv::{StringRecord, Writer, WriterBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::source::RecordSource;
+use crate::Error;
+
+/// 遇到格式错误的行（字段数与前面的行不一致、不是UTF-8编码）时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BadRowPolicy {
+    /// 报错并停止，错误中带有出错的行号和字节位置
+    #[default]
+    Strict,
+    /// 跳过出错的行，只计数
+    Skip,
+    /// 跳过出错的行，并连同出错原因写入 `<原文件名>_rejected.csv`
+    Quarantine,
+}
+
+/// 输出目录中被拒绝的行的文件名
+pub(crate) fn rejected_file_name(file_stem: &str) -> String {
+    format!("{}_rejected.csv", file_stem)
+}
+
+/// 按策略处理读取记录时的错误，隔离模式下把出错的行写入文件
+///
+/// 文件在第一次遇到出错的行时才创建，每行依次为行号、字节位置、错误码、原因和原有的字段；
+/// `headers` 为 `None` 时不写标题行（多线程分割时各线程的中间文件）。
+pub(crate) struct BadRows {
+    policy: BadRowPolicy,
+    path: PathBuf,
+    headers: Option<StringRecord>,
+    writer: Option<Writer<BufWriter<File>>>,
+    count: usize,
+}
+
+impl BadRows {
+    pub(crate) fn new(policy: BadRowPolicy, path: PathBuf, headers: Option<&StringRecord>) -> BadRows {
+        BadRows {
+            policy,
+            path,
+            headers: headers.map(|h| {
+                let mut record = StringRecord::from(vec!["row", "byte", "code", "reason"]);
+                record.extend(h.iter());
+                record
+            }),
+            writer: None,
+            count: 0,
+        }
+    }
+
+    /// 从断点继续：丢弃文件中 `end` 字节之后（断点之后写入）的内容，继续追加
+    pub(crate) fn resume(&mut self, end: u64, count: usize) -> Result<(), Error> {
+        self.count = count;
+        if end == 0 || self.policy != BadRowPolicy::Quarantine {
+            return Ok(());
+        }
+        let mut file = OpenOptions::new()
+            .write(true)
+            .open(&self.path)
+            .map_err(|e| Error::io(&self.path, e))?;
+        file.set_len(end)?;
+        file.seek(SeekFrom::End(0))?;
+        self.writer = Some(csv_writer(BufWriter::new(file)));
+        Ok(())
+    }
+
+    /// 处理读取记录时的错误：严格模式或无法跳过的错误原样返回，否则记录下来继续读取
+    pub(crate) fn handle(&mut self, error: Error, record: &StringRecord) -> Result<(), Error> {
+        if self.policy == BadRowPolicy::Strict || !error.is_row_error() {
+            return Err(error);
+        }
+        self.count += 1;
+        if self.policy == BadRowPolicy::Quarantine {
+            let position = [error.row(), error.byte()].map(|v| v.map(|v| v.to_string()).unwrap_or_default());
+            let mut row = StringRecord::from(vec![
+                position[0].as_str(),
+                position[1].as_str(),
+                error.code(),
+                &error.to_string(),
+            ]);
+            row.extend(record.iter());
+            self.writer()?.write_record(&row)?;
+        }
+        Ok(())
+    }
+
+    /// 跳过出错的行读取下一条记录，返回是否读到了记录
+    pub(crate) fn read_record(&mut self, source: &mut dyn RecordSource, record: &mut StringRecord) -> Result<bool, Error> {
+        loop {
+            match source.read_record(record) {
+                Err(e) => self.handle(e, record)?,
+                result => return result,
+            }
+        }
+    }
+
+    /// 把多线程分割时某个线程的中间文件追加到本文件，并删除中间文件
+    pub(crate) fn append(&mut self, part: BadRows) -> Result<(), Error> {
+        self.count += part.count;
+        let Some(mut part_writer) = part.writer else {
+            return Ok(());
+        };
+        part_writer.flush()?;
+        drop(part_writer);
+
+        // 中间文件已是CSV格式，直接复制字节
+        let writer = match self.writer.take() {
+            Some(writer) => writer,
+            None => self.create()?,
+        };
+        let mut inner = writer.into_inner().map_err(|e| Error::from(e.into_error()))?;
+        let mut file = File::open(&part.path).map_err(|e| Error::io(&part.path, e))?;
+        std::io::copy(&mut file, &mut inner)?;
+        self.writer = Some(csv_writer(inner));
+        drop(file);
+        let _ = std::fs::remove_file(&part.path);
+        Ok(())
+    }
+
+    /// 写入磁盘，返回文件当前的长度（用于断点）
+    pub(crate) fn flush(&mut self) -> Result<u64, Error> {
+        match self.writer {
+            Some(ref mut writer) => {
+                writer.flush()?;
+                let file = writer.get_ref().get_ref();
+                file.sync_all()?;
+                Ok(file.metadata()?.len())
+            }
+            None => Ok(0),
+        }
+    }
+
+    /// 跳过的行数
+    pub(crate) fn count(&self) -> usize {
+        self.count
+    }
+
+    /// 写入了出错的行的文件，没有时为 `None`
+    pub(crate) fn path(&self) -> Option<&Path> {
+        self.writer.as_ref().map(|_| self.path.as_path())
+    }
+
+    fn writer(&mut self) -> Result<&mut Writer<BufWriter<File>>, Error> {
+        if self.writer.is_none() {
+            self.writer = Some(self.create()?);
+        }
+        Ok(self.writer.as_mut().unwrap())
+    }
+
+    /// 创建文件并写入标题行
+    fn create(&self) -> Result<Writer<BufWriter<File>>, Error> {
+        let file = File::create(&self.path).map_err(|e| Error::io(&self.path, e))?;
+        let mut writer = csv_writer(BufWriter::new(file));
+        if let Some(ref headers) = self.headers {
+            writer.write_record(headers)?;
+        }
+        Ok(writer)
+    }
+}
+
+/// 出错的行字段数各不相同，写入时不检查字段数
+fn csv_writer(inner: BufWriter<File>) -> Writer<BufWriter<File>> {
+    WriterBuilder::new().flexible(true).from_writer(inner)
+}
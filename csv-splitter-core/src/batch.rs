@@ -0,0 +1,291 @@
+use std::collections::{HashMap, VecDeque};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::Serialize;
+
+use crate::i18n::Message;
+use crate::merge::natural_cmp;
+use crate::progress::ProgressListener;
+use crate::{split_single, Error, SplitParams, SplitResult};
+
+/// 未指定文件名模式时按目录批量处理的文件
+const DEFAULT_PATTERN: &str = "*.csv";
+
+/// 未指定并发数时同时处理的文件数上限
+const DEFAULT_PARALLEL_JOBS: usize = 2;
+
+/// 批量处理中单个输入文件的结果
+#[derive(Debug, Clone, Serialize)]
+pub struct InputResult {
+    pub input_path: String,
+    #[serde(flatten)]
+    pub result: SplitResult,
+}
+
+/// 展开批量任务的输入文件：显式列出的文件在前，目录中匹配的文件按自然顺序在后，重复的只保留一次
+pub(crate) fn collect_inputs(params: &SplitParams) -> Result<Vec<String>, Error> {
+    let mut inputs = params.input_paths.clone();
+
+    if let Some(ref input_dir) = params.input_dir {
+        let pattern = params.input_pattern.as_deref().unwrap_or(DEFAULT_PATTERN);
+        let mut matched: Vec<String> = std::fs::read_dir(input_dir)
+            .map_err(|e| Error::io(Path::new(input_dir), e))?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.is_file())
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|s| s.to_str())
+                    .is_some_and(|name| !name.starts_with('.') && wildcard_match(pattern, name))
+            })
+            .map(|path| path.display().to_string())
+            .collect();
+        matched.sort_by(|a, b| natural_cmp(a, b));
+        inputs.extend(matched);
+    }
+
+    let mut seen = Vec::new();
+    inputs.retain(|input| {
+        let key = Path::new(input).canonicalize().unwrap_or_else(|_| PathBuf::from(input));
+        if seen.contains(&key) {
+            false
+        } else {
+            seen.push(key);
+            true
+        }
+    });
+
+    if inputs.is_empty() {
+        return Err(Error::invalid_params(Message::new("batch.no_inputs")));
+    }
+    Ok(inputs)
+}
+
+/// 批量分割：多个文件通过有界的任务队列并发处理，单个文件失败不影响其他文件
+///
+/// 各文件的进度都转发给 `progress`，通过其中的 `input_path` 区分。
+/// 某个文件的处理过程中发生panic时只有该文件失败，工作线程继续处理队列中的其他文件。
+pub(crate) fn split_batch(params: &SplitParams, progress: &dyn ProgressListener) -> SplitResult {
+    let inputs = match collect_inputs(params) {
+        Ok(inputs) => inputs,
+        Err(e) => return SplitResult::failure(e),
+    };
+
+    // 输出文件和暂存目录按原文件名区分，同名的输入会互相覆盖
+    let mut stems: HashMap<String, &str> = HashMap::new();
+    let mut conflicts: HashMap<usize, Error> = HashMap::new();
+    for (i, input) in inputs.iter().enumerate() {
+        let stem = Path::new(input)
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("output")
+            .to_lowercase();
+        match stems.get(&stem) {
+            Some(first) => {
+                conflicts.insert(i, Error::DuplicateInput { path: first.to_string() });
+            }
+            None => {
+                stems.insert(stem, input);
+            }
+        }
+    }
+
+    let queue: Mutex<VecDeque<usize>> = Mutex::new((0..inputs.len()).collect());
+    let results: Mutex<Vec<Option<SplitResult>>> = Mutex::new((0..inputs.len()).map(|_| None).collect());
+    let jobs = match params.max_parallel_jobs {
+        0 => DEFAULT_PARALLEL_JOBS,
+        n => n,
+    }
+    .min(inputs.len());
+
+    std::thread::scope(|scope| {
+        for _ in 0..jobs {
+            scope.spawn(|| loop {
+                let Some(i) = queue.lock().unwrap().pop_front() else {
+                    break;
+                };
+                let result = match conflicts.get(&i) {
+                    Some(conflict) => SplitResult::failure(conflict.clone()),
+                    None => {
+                        let mut file_params = params.clone();
+                        file_params.input_path = inputs[i].clone();
+                        file_params.input_paths = Vec::new();
+                        file_params.input_dir = None;
+                        catch_unwind(AssertUnwindSafe(|| split_single(file_params, progress)))
+                            .unwrap_or_else(|_| Err(Message::new("split.worker_panicked").into()))
+                            .unwrap_or_else(SplitResult::failure)
+                    }
+                };
+                results.lock().unwrap()[i] = Some(result);
+            });
+        }
+    });
+
+    let inputs: Vec<InputResult> = inputs
+        .into_iter()
+        .zip(results.into_inner().unwrap())
+        .map(|(input_path, result)| InputResult {
+            input_path,
+            result: result.unwrap_or_else(|| SplitResult::failure(Message::new("batch.not_run"))),
+        })
+        .collect();
+
+    let failed = inputs.iter().filter(|r| !r.result.success).count();
+    SplitResult {
+        success: failed == 0,
+        file_count: inputs.iter().map(|r| r.result.file_count).sum(),
+        error: (failed > 0).then_some(Error::BatchFailed { failed, total: inputs.len() }),
+        verification: None,
+        manifest: None,
+        skipped_files: inputs.iter().flat_map(|r| r.result.skipped_files.iter().cloned()).collect(),
+        checkpoint_saved: inputs.iter().any(|r| r.result.checkpoint_saved),
+        rejected_rows: inputs.iter().map(|r| r.result.rejected_rows).sum(),
+        rejected_file: None,
+        adjusted_rows: inputs.iter().map(|r| r.result.adjusted_rows).sum(),
+        filtered_rows: inputs.iter().map(|r| r.result.filtered_rows).sum(),
+        duplicate_rows: inputs.iter().map(|r| r.result.duplicate_rows).sum(),
+        split_groups: inputs.iter().map(|r| r.result.split_groups).sum(),
+        inputs,
+    }
+}
+
+/// 文件名通配符匹配，支持 `*`（任意多个字符）和 `?`（单个字符），不区分大小写
+pub(crate) fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+
+    let (mut p, mut n) = (0, 0);
+    // 最近一个 `*` 的位置，以及它当前匹配到的名称位置
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            p = star_p + 1;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::Progress;
+
+    fn temp_dir(name: &str, files: &[&str]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("csv-splitter-batch-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("nested.csv")).unwrap();
+        for file in files {
+            std::fs::write(dir.join(file), "id\n1\n2\n").unwrap();
+        }
+        dir
+    }
+
+    fn params(dir: &Path, extra: serde_json::Value) -> SplitParams {
+        let mut value = serde_json::json!({
+            "input_path": "",
+            "output_dir": dir.join("out").display().to_string(),
+            "rows_per_file": 1,
+            "has_header": true,
+            "convert_to_excel": false,
+        });
+        for (key, field) in extra.as_object().unwrap() {
+            value[key] = field.clone();
+        }
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn names(inputs: &[String]) -> Vec<&str> {
+        inputs.iter().map(|input| Path::new(input).file_name().unwrap().to_str().unwrap()).collect()
+    }
+
+    #[test]
+    fn wildcards_match_case_insensitively() {
+        assert!(wildcard_match("*.csv", "Sales.CSV"));
+        assert!(wildcard_match("sales_??.csv", "sales_01.csv"));
+        assert!(!wildcard_match("sales_??.csv", "sales_1.csv"));
+        assert!(wildcard_match("*_*_*.csv", "a_b_c_d.csv"));
+        assert!(wildcard_match("a*b*c", "abbbc"));
+        assert!(!wildcard_match("a*b*c", "abbbcd"));
+        assert!(wildcard_match("**", ""));
+        assert!(!wildcard_match("?", ""));
+    }
+
+    #[test]
+    fn inputs_are_listed_then_matched_without_duplicates() {
+        let dir = temp_dir("collect", &["part_10.csv", "part_2.csv", ".hidden.csv", "notes.txt", "extra.csv"]);
+        let listed = dir.join("extra.csv").display().to_string();
+        let inputs = collect_inputs(&params(&dir, serde_json::json!({
+            "input_paths": [listed],
+            "input_dir": dir.display().to_string(),
+        })))
+        .unwrap();
+        // 显式列出的文件在前；目录中的文件按自然顺序，跳过隐藏文件、子目录和重复的文件
+        assert_eq!(names(&inputs), ["extra.csv", "part_2.csv", "part_10.csv"]);
+
+        let inputs = collect_inputs(&params(&dir, serde_json::json!({
+            "input_dir": dir.display().to_string(),
+            "input_pattern": "*.TXT",
+        })))
+        .unwrap();
+        assert_eq!(names(&inputs), ["notes.txt"]);
+
+        let error = collect_inputs(&params(&dir, serde_json::json!({
+            "input_dir": dir.display().to_string(),
+            "input_pattern": "*.json",
+        })))
+        .unwrap_err();
+        assert!(format!("{:?}", error).contains("batch.no_inputs"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn inputs_with_the_same_stem_fail_individually() {
+        let dir = temp_dir("stems", &["orders.csv", "other.csv"]);
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("sub").join("ORDERS.csv"), "id\n3\n").unwrap();
+        let first = dir.join("orders.csv").display().to_string();
+        let inputs = [first.clone(), dir.join("sub").join("ORDERS.csv").display().to_string(), dir.join("other.csv").display().to_string()];
+        let result = split_batch(&params(&dir, serde_json::json!({ "input_paths": inputs })), &crate::NoProgress);
+
+        assert_eq!(result.error, Some(Error::BatchFailed { failed: 1, total: 3 }));
+        let outcomes: Vec<_> = result.inputs.iter().map(|r| r.result.error.clone()).collect();
+        assert_eq!(outcomes, [None, Some(Error::DuplicateInput { path: first }), None]);
+        assert_eq!(result.file_count, 4);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// 处理指定文件时panic
+    struct PanicOn(&'static str);
+
+    impl ProgressListener for PanicOn {
+        fn on_progress(&self, progress: &Progress) {
+            if progress.input_path.ends_with(self.0) {
+                panic!("处理 {} 时出错", self.0);
+            }
+        }
+    }
+
+    #[test]
+    fn a_panicking_job_fails_only_its_input() {
+        let dir = temp_dir("panic", &["a.csv", "b.csv", "c.csv"]);
+        let params = params(&dir, serde_json::json!({ "input_dir": dir.display().to_string(), "max_parallel_jobs": 1 }));
+        let result = split_batch(&params, &PanicOn("b.csv"));
+
+        assert_eq!(result.error, Some(Error::BatchFailed { failed: 1, total: 3 }));
+        let codes: Vec<_> = result.inputs.iter().map(|r| r.result.error.as_ref().map(Error::code)).collect();
+        assert_eq!(codes, [None, Some("other"), None]);
+        assert!(format!("{:?}", result.inputs[1].result.error).contains("split.worker_panicked"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
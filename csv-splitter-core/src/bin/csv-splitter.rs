@@ -0,0 +1,5 @@
+//! 命令行版本，与界面使用同一套分割逻辑，适合在定时任务和CI中运行
+
+fn main() {
+    std::process::exit(csv_splitter_core::run_cli(std::env::args().skip(1).collect()))
+}
@@ -0,0 +1,264 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use csv::Position;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::i18n::Message;
+use crate::manifest::{file_digest, PieceManifest};
+use crate::output::staging_dir;
+use crate::{Error, PieceInfo, SplitParams};
+
+/// 计算输入指纹时读取的文件开头长度
+const FINGERPRINT_HEAD_SIZE: u64 = 1024 * 1024;
+
+/// 分割断点：每完成一个分块更新一次，保存在暂存目录中
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Checkpoint {
+    pub(crate) input: InputFingerprint,
+    pub(crate) params: SplitParams,
+    /// 已完成的分块（按顺序）
+    pub(crate) pieces: Vec<CheckpointPiece>,
+    /// 更新时间（Unix时间戳，秒）
+    pub(crate) updated_at: u64,
+}
+
+/// 输入文件指纹，用于确认续传时输入文件没有变化
+///
+/// 对几十GB的文件计算完整哈希代价太大，这里只比较大小、修改时间和文件开头的哈希。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct InputFingerprint {
+    pub(crate) byte_size: u64,
+    /// 修改时间（Unix时间戳，秒）
+    pub(crate) modified: u64,
+    /// 文件开头1MB的SHA-256
+    pub(crate) head_sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct CheckpointPiece {
+    #[serde(flatten)]
+    pub(crate) piece: PieceManifest,
+    /// 该分块最后一行之后的下一条记录在输入文件中的字节位置
+    pub(crate) end_offset: u64,
+    /// 下一条记录在输入文件中的行号和记录序号，续传后报告的行号从这里接着计数；
+    /// 旧版本的断点中没有，为0
+    #[serde(default)]
+    pub(crate) end_line: u64,
+    #[serde(default)]
+    pub(crate) end_record: u64,
+    /// 写完该分块时隔离文件的长度
+    #[serde(default)]
+    pub(crate) rejects_end: u64,
+    /// 写完该分块时累计跳过的行数
+    #[serde(default)]
+    pub(crate) rejected_rows: usize,
+    /// 写完该分块时累计不满足筛选条件的行数
+    #[serde(default)]
+    pub(crate) filtered_rows: usize,
+    /// 写完该分块时去重已判断过的行数
+    #[serde(default)]
+    pub(crate) dedupe_seen: usize,
+    /// 写完该分块时累计丢弃的重复行数
+    #[serde(default)]
+    pub(crate) duplicate_rows: usize,
+}
+
+impl InputFingerprint {
+    pub(crate) fn of(path: &Path) -> Result<InputFingerprint, Error> {
+        let metadata = std::fs::metadata(path).map_err(|e| Error::io(path, e))?;
+        let modified = metadata.modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        let file = File::open(path).map_err(|e| Error::io(path, e))?;
+        let mut head = Vec::new();
+        BufReader::new(file)
+            .take(FINGERPRINT_HEAD_SIZE)
+            .read_to_end(&mut head)
+            .map_err(|e| Error::io(path, e))?;
+
+        Ok(InputFingerprint {
+            byte_size: metadata.len(),
+            modified,
+            head_sha256: format!("{:x}", Sha256::digest(&head)),
+        })
+    }
+}
+
+impl Checkpoint {
+    pub(crate) fn new(params: &SplitParams) -> Result<Checkpoint, Error> {
+        Ok(Checkpoint {
+            input: InputFingerprint::of(Path::new(&params.input_path))?,
+            params: params.clone(),
+            pieces: Vec::new(),
+            updated_at: 0,
+        })
+    }
+
+    /// 续传时下一个分块的序号
+    pub(crate) fn next_index(&self) -> usize {
+        self.pieces.len() + 1
+    }
+
+    /// 已读过的数据行数（含跳过的行）
+    pub(crate) fn rows_done(&self) -> usize {
+        self.pieces.last().map_or(0, |p| p.piece.last_row)
+    }
+
+    /// 续传时隔离文件保留的长度和已跳过的行数
+    pub(crate) fn rejected(&self) -> (u64, usize) {
+        self.pieces.last().map_or((0, 0), |p| (p.rejects_end, p.rejected_rows))
+    }
+
+    /// 续传时已不满足筛选条件的行数
+    pub(crate) fn filtered_rows(&self) -> usize {
+        self.pieces.last().map_or(0, |p| p.filtered_rows)
+    }
+
+    /// 续传时去重已判断过的行数和已丢弃的重复行数
+    pub(crate) fn duplicates(&self) -> (usize, usize) {
+        self.pieces.last().map_or((0, 0), |p| (p.dedupe_seen, p.duplicate_rows))
+    }
+
+    /// 续传时开始读取的位置，没有已完成的分块时从头开始
+    pub(crate) fn resume_position(&self) -> Option<Position> {
+        self.pieces.last().map(|p| {
+            let mut position = Position::new();
+            position.set_byte(p.end_offset);
+            if p.end_line > 0 {
+                position.set_line(p.end_line).set_record(p.end_record);
+            }
+            position
+        })
+    }
+
+    /// 已完成的分块，用于继续分割
+    pub(crate) fn piece_infos(&self) -> Vec<PieceInfo> {
+        self.pieces
+            .iter()
+            .map(|p| PieceInfo {
+                path: PathBuf::from(&p.piece.path),
+                first_row: p.piece.first_row,
+                last_row: p.piece.last_row,
+                row_count: p.piece.row_count,
+                adjusted_rows: p.piece.adjusted_rows,
+            })
+            .collect()
+    }
+
+    /// 记录一个刚写完的分块并保存断点
+    pub(crate) fn record_piece(
+        &mut self,
+        piece: &PieceInfo,
+        end: &Position,
+        rejects_end: u64,
+        rejected_rows: usize,
+        filtered_rows: usize,
+        (dedupe_seen, duplicate_rows): (usize, usize),
+    ) -> Result<(), Error> {
+        let (byte_size, sha256) = file_digest(&piece.path)?;
+        self.pieces.push(CheckpointPiece {
+            piece: PieceManifest {
+                path: piece.path.display().to_string(),
+                first_row: piece.first_row,
+                last_row: piece.last_row,
+                row_count: piece.row_count,
+                adjusted_rows: piece.adjusted_rows,
+                byte_size,
+                sha256,
+            },
+            end_offset: end.byte(),
+            end_line: end.line(),
+            end_record: end.record(),
+            rejects_end,
+            rejected_rows,
+            filtered_rows,
+            dedupe_seen,
+            duplicate_rows,
+        });
+        self.save()
+    }
+
+    /// 先写临时文件再替换，进程在写入中途退出时旧的断点仍然可用
+    ///
+    /// 新任务在开始读取前保存一次，写完第一个分块之前中断也能继续。
+    pub(crate) fn save(&mut self) -> Result<(), Error> {
+        self.updated_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        let path = checkpoint_path(&self.params.input_path, &self.params.output_dir);
+        let temp_path = path.with_extension("json.tmp");
+        let file = File::create(&temp_path).map_err(|e| Error::io(&temp_path, e))?;
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer_pretty(&mut writer, self)
+            .map_err(|e| Error::Io { path: Some(temp_path.display().to_string()), reason: e.to_string() })?;
+        writer.flush().map_err(|e| Error::io(&temp_path, e))?;
+        writer.get_ref().sync_all().map_err(|e| Error::io(&temp_path, e))?;
+        drop(writer);
+        std::fs::rename(&temp_path, &path).map_err(|e| Error::io(&path, e))
+    }
+
+    /// 续传前的检查：输入文件未变化，已完成的分块完好
+    ///
+    /// 从第一个缺失或内容不一致的分块起丢弃后续记录，从该分块重新开始写。
+    pub(crate) fn validate(&mut self) -> Result<(), Error> {
+        let fingerprint = InputFingerprint::of(Path::new(&self.params.input_path))?;
+        if fingerprint != self.input {
+            return Err(Error::InputChanged);
+        }
+
+        let intact = self.pieces
+            .iter()
+            .take_while(|p| {
+                let path = Path::new(&p.piece.path);
+                path.is_file() && file_digest(path).is_ok_and(|(size, sha256)| {
+                    size == p.piece.byte_size && sha256 == p.piece.sha256
+                })
+            })
+            .count();
+        self.pieces.truncate(intact);
+        Ok(())
+    }
+}
+
+/// 断点文件位置：与分块一起放在暂存目录中，任务成功后随暂存目录一起删除
+fn checkpoint_path(input_path: &str, output_dir: &str) -> PathBuf {
+    let file_stem = Path::new(input_path)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("output");
+    staging_dir(Path::new(output_dir), file_stem).join("checkpoint.json")
+}
+
+/// 输出目录中是否保存了该输入文件的断点
+pub(crate) fn has_checkpoint(params: &SplitParams) -> bool {
+    checkpoint_path(&params.input_path, &params.output_dir).is_file()
+}
+
+/// 读取输出目录中未完成任务的断点
+pub(crate) fn read_checkpoint(input_path: &str, output_dir: &str) -> Result<Option<Checkpoint>, Error> {
+    let path = checkpoint_path(input_path, output_dir);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let file = File::open(&path).map_err(|e| Error::io(&path, e))?;
+    let checkpoint: Checkpoint = serde_json::from_reader(BufReader::new(file)).map_err(|e| Error::Parse {
+        row: None,
+        byte: None,
+        reason: Message::new("checkpoint.invalid").arg("path", path.display().to_string()).arg("reason", e.to_string()),
+    })?;
+    if checkpoint.params.input_path != input_path {
+        return Err(Error::invalid_params(
+            Message::new("checkpoint.other_input").arg("path", checkpoint.params.input_path.as_str()),
+        ));
+    }
+    Ok(Some(checkpoint))
+}
@@ -0,0 +1,838 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use crate::i18n::{lookup, Message};
+use crate::progress::{NoProgress, Progress, ProgressListener, Stage, Throttled};
+use crate::{Locale, Localized, MergeParams, ResumeParams, SplitParams, SplitResult, VerifyParams};
+
+const USAGE_ZH: &str = "\
+用法:
+  csv-splitter split [选项] <输入文件>... <输出目录>
+  csv-splitter resume [--json] [--quiet] <输入文件> <输出目录>
+  csv-splitter verify [选项] <输入文件> <输出目录>
+  csv-splitter merge [选项] <输入文件>... -o <输出文件>
+
+split 选项:
+  --rows <N>              每个文件的数据行数
+  --header / --no-header  第一行是否为标题行（默认没有标题行）
+  --xlsx                  转换为Excel格式
+  --format <格式>         输入格式：csv、json、fixed_width（默认按扩展名推断）
+  --layout <文件>         定长文本的列布局（JSON）
+  --name <模板>           输出文件命名模板，如 {stem}_{index:04}
+  --overwrite <策略>      fail、overwrite、skip、auto_rename、clean_prefix（默认 fail）
+  --verify                分割后校验分块能否还原为原文件
+  --checkpoint            记录断点，中断后可用 resume 继续
+  --bad-rows <策略>       格式错误的行：strict 报错、skip 跳过、quarantine 跳过并写入 <原文件名>_rejected.csv（默认 strict）
+  --flexible              按标题行的列数补齐或截断字段数不一致的行
+  --fill <值>             与 --flexible 一起使用，补齐缺失字段的值（默认为空）
+  --default <列名=值>     与 --flexible 一起使用，按列指定补齐值（可重复）
+  --long-rows <方式>      与 --flexible 一起使用，字段多出的行：truncate 截断、preserve 保留（默认 truncate）
+  --header-template <模板>  没有标题行时生成列名的模板，如 col_{index:03}（默认 column_{index}）
+  --header-row <列名,...>  替换整个标题行
+  --rename <列=新列名>    给列改名，列为列名或从1开始的列序号（可重复）
+  --compute <列名=表达式>  追加计算列，如 \"ym=format_date(created, '%Y%m')\"（可重复）
+  --filter <条件>         只保留满足条件的行，如 \"status = 'paid' and amount > 100\"
+  --columns <列,...>      只输出这些列并按此顺序排列，列名或从1开始的列序号，如 name,3,email
+  --drop <列,...>         不输出这些列
+  --dedupe                去除重复的行（默认比较输出的整行，保留第一次出现的行）
+  --dedupe-by <列,...>    按这些列判断重复，隐含 --dedupe
+  --keep <first|last>     重复的行中保留第一行还是最后一行，隐含 --dedupe
+  --sort <键,...>         分割前排序，每个键为 列[:asc|desc][:text|number|date]，如 created:desc:date,id:number
+  --sort-memory <MB>      排序可使用的内存，超过时借助临时文件（默认 256）
+  --group-by <列,...>     同一组的行不分到两个分块中，分块写满后延续到组结束；输入须已按这些列排序
+  --max-rows <N>          与 --group-by 一起使用，每个分块最多的行数，超过时切开该组（默认为 --rows 的两倍）
+  --input-dir <目录>      批量处理目录中的文件
+  --pattern <模式>        与 --input-dir 一起使用的文件名模式（默认 *.csv）
+  --jobs <N>              批量处理时同时分割的文件数
+  --preset <文件>         从JSON文件读取分割参数，命令行选项优先
+
+verify 选项:
+  --header / --no-header、--format、--layout、--bad-rows、--flexible、--compute、--filter、--columns、--dedupe、--sort 等  与分割时相同
+  --piece <文件>          按顺序指定分块（可重复），默认使用清单或按序号查找
+
+merge 选项:
+  -o, --output <文件>     输出文件
+  --header                输入文件带有标题行
+  --union                 按列名合并标题不一致的文件
+  --alias <旧列名=新列名>  列名别名（可重复）
+  --fill <值>             按列名合并时缺失列的填充值
+
+通用选项:
+  --json                  以JSON格式输出结果
+  --quiet                 不输出进度
+  --lang <语言>           输出信息的语言：zh-CN、en-US（默认跟随 LANG 环境变量）
+  -h, --help              显示帮助
+";
+
+const USAGE_EN: &str = "\
+Usage:
+  csv-splitter split [options] <input>... <output-dir>
+  csv-splitter resume [--json] [--quiet] <input> <output-dir>
+  csv-splitter verify [options] <input> <output-dir>
+  csv-splitter merge [options] <input>... -o <output>
+
+split options:
+  --rows <N>              Data rows per file
+  --header / --no-header  Whether the first line is a header (default: no header)
+  --xlsx                  Convert to Excel
+  --format <format>       Input format: csv, json, fixed_width (default: inferred from the extension)
+  --layout <file>         Column layout for fixed-width text (JSON)
+  --name <template>       Output file name template, e.g. {stem}_{index:04}
+  --overwrite <policy>    fail, overwrite, skip, auto_rename, clean_prefix (default: fail)
+  --verify                Check that the pieces reassemble into the input after splitting
+  --checkpoint            Save checkpoints so an interrupted split can continue with resume
+  --bad-rows <policy>     Malformed rows: strict fails, skip skips, quarantine skips and writes them to <stem>_rejected.csv (default: strict)
+  --flexible              Pad or truncate rows whose field count differs from the header
+  --fill <value>          With --flexible, the value for missing fields (default: empty)
+  --default <col=value>   With --flexible, the value for a missing column (repeatable)
+  --long-rows <mode>      With --flexible, rows with extra fields: truncate or preserve (default: truncate)
+  --header-template <template>  Template for generated column names without a header, e.g. col_{index:03} (default: column_{index})
+  --header-row <name,...>  Replace the whole header row
+  --rename <col=name>     Rename a column given by name or 1-based position (repeatable)
+  --compute <name=expr>   Append a computed column, e.g. \"ym=format_date(created, '%Y%m')\" (repeatable)
+  --filter <condition>    Keep only matching rows, e.g. \"status = 'paid' and amount > 100\"
+  --columns <col,...>     Output only these columns in this order, by name or 1-based position, e.g. name,3,email
+  --drop <col,...>        Do not output these columns
+  --dedupe                Remove duplicate rows (default: compare the whole output row, keep the first)
+  --dedupe-by <col,...>   Compare these columns for duplicates, implies --dedupe
+  --keep <first|last>     Keep the first or the last of duplicate rows, implies --dedupe
+  --sort <key,...>        Sort before splitting, each key is col[:asc|desc][:text|number|date], e.g. created:desc:date,id:number
+  --sort-memory <MB>      Memory for sorting before spilling to temporary files (default: 256)
+  --group-by <col,...>    Keep rows of the same group in one piece, extending a full piece to the end of the group; the input must be sorted by these columns
+  --max-rows <N>          With --group-by, the most rows in a piece before a group is cut (default: twice --rows)
+  --input-dir <dir>       Split the files in a directory
+  --pattern <pattern>     File name pattern for --input-dir (default: *.csv)
+  --jobs <N>              Files split at the same time in a batch
+  --preset <file>         Read split parameters from a JSON file; command-line options take precedence
+
+verify options:
+  --header / --no-header, --format, --layout, --bad-rows, --flexible, --compute, --filter, --columns, --dedupe, --sort, ...  Same as for split
+  --piece <file>          Pieces in order (repeatable); default: the manifest or pieces found by index
+
+merge options:
+  -o, --output <file>     Output file
+  --header                The inputs have a header row
+  --union                 Merge files with different headers by column name
+  --alias <old=new>       Column name alias (repeatable)
+  --fill <value>          Value for missing columns in a union merge
+
+Common options:
+  --json                  Print the result as JSON
+  --quiet                 Do not print progress
+  --lang <language>       Language of the output: zh-CN, en-US (default: follows the LANG environment variable)
+  -h, --help              Show this help
+";
+
+/// 命令行入口，返回进程退出码：0 成功，1 处理失败，2 参数错误
+pub(crate) fn run(args: Vec<String>) -> i32 {
+    let Some((command, rest)) = args.split_first() else {
+        eprint!("{}", usage(Locale::system()));
+        return 2;
+    };
+    if command == "-h" || command == "--help" || command == "help" {
+        print!("{}", usage(Locale::system()));
+        return 0;
+    }
+
+    let parsed = match ParsedArgs::parse(rest) {
+        Ok(parsed) => parsed,
+        Err(e) => return usage_error(e, Locale::system()),
+    };
+    let locale = match parsed.locale() {
+        Ok(locale) => locale,
+        Err(e) => return usage_error(e, Locale::system()),
+    };
+    if parsed.has("help") {
+        print!("{}", usage(locale));
+        return 0;
+    }
+
+    let outcome = match command.as_str() {
+        "split" => run_split(&parsed, locale),
+        "resume" => run_resume(&parsed, locale),
+        "verify" => run_verify(&parsed, locale),
+        "merge" => run_merge(&parsed, locale),
+        other => Err(Message::new("cli.unknown_command").arg("command", other)),
+    };
+
+    match outcome {
+        Ok(success) => {
+            if success { 0 } else { 1 }
+        }
+        Err(e) => usage_error(e, locale),
+    }
+}
+
+fn usage(locale: Locale) -> &'static str {
+    match locale {
+        Locale::ZhCn => USAGE_ZH,
+        Locale::EnUs => USAGE_EN,
+    }
+}
+
+fn usage_error(message: Message, locale: Locale) -> i32 {
+    eprintln!("{}", Message::new("cli.error").arg("reason", message).render(locale));
+    eprintln!("{}", lookup("cli.see_help", locale));
+    2
+}
+
+/// 解析后的命令行参数：选项（可重复）和位置参数
+struct ParsedArgs {
+    options: HashMap<String, Vec<String>>,
+    positional: Vec<String>,
+}
+
+/// 需要取值的选项
+const VALUE_OPTIONS: &[&str] = &[
+    "rows", "format", "layout", "name", "overwrite", "input-dir", "pattern", "jobs",
+    "preset", "piece", "output", "alias", "fill", "lang", "bad-rows",
+    "default", "long-rows", "columns", "drop", "header-template", "header-row", "rename",
+    "filter", "compute", "dedupe-by", "keep", "sort", "sort-memory", "group-by", "max-rows",
+];
+
+impl ParsedArgs {
+    fn parse(args: &[String]) -> Result<ParsedArgs, Message> {
+        let mut options: HashMap<String, Vec<String>> = HashMap::new();
+        let mut positional = Vec::new();
+        let mut iter = args.iter();
+
+        while let Some(arg) = iter.next() {
+            let name = match arg.as_str() {
+                "-o" => "output".to_string(),
+                "-h" => "help".to_string(),
+                "--" => {
+                    positional.extend(iter.by_ref().cloned());
+                    break;
+                }
+                _ => match arg.strip_prefix("--") {
+                    Some(name) => name.to_string(),
+                    None => {
+                        positional.push(arg.clone());
+                        continue;
+                    }
+                },
+            };
+
+            // 支持 --name=value 写法
+            let (name, inline_value) = match name.split_once('=') {
+                Some((name, value)) => (name.to_string(), Some(value.to_string())),
+                None => (name, None),
+            };
+            let value = if VALUE_OPTIONS.contains(&name.as_str()) {
+                match inline_value {
+                    Some(value) => value,
+                    None => iter.next().cloned().ok_or_else(|| Message::new("cli.option_needs_value").arg("name", name.as_str()))?,
+                }
+            } else if inline_value.is_some() {
+                return Err(Message::new("cli.option_takes_no_value").arg("name", name));
+            } else {
+                String::new()
+            };
+            options.entry(name).or_default().push(value);
+        }
+
+        Ok(ParsedArgs { options, positional })
+    }
+
+    fn has(&self, name: &str) -> bool {
+        self.options.contains_key(name)
+    }
+
+    fn value(&self, name: &str) -> Option<&str> {
+        self.options.get(name).and_then(|values| values.last()).map(|s| s.as_str())
+    }
+
+    fn values(&self, name: &str) -> &[String] {
+        self.options.get(name).map(|values| values.as_slice()).unwrap_or(&[])
+    }
+
+    /// 检查是否有当前命令不支持的选项
+    fn expect_only(&self, allowed: &[&str]) -> Result<(), Message> {
+        for name in self.options.keys() {
+            if !allowed.contains(&name.as_str()) && !["json", "quiet", "lang"].contains(&name.as_str()) {
+                return Err(Message::new("cli.unsupported_option").arg("name", name.as_str()));
+            }
+        }
+        Ok(())
+    }
+
+    /// `--lang` 指定的输出信息语言，未指定时为系统语言
+    fn locale(&self) -> Result<Locale, Message> {
+        match self.value("lang") {
+            Some(lang) => Locale::parse(lang).ok_or_else(|| Message::new("cli.unsupported_lang").arg("lang", lang)),
+            None => Ok(Locale::system()),
+        }
+    }
+
+    fn parse_number(&self, name: &str) -> Result<Option<usize>, Message> {
+        self.value(name)
+            .map(|value| {
+                value
+                    .parse::<usize>()
+                    .map_err(|_| Message::new("cli.not_a_number").arg("name", name).arg("value", value))
+            })
+            .transpose()
+    }
+}
+
+/// 按命令行选项构造分割参数，通过serde反序列化复用参数校验和默认值
+///
+/// 校验时不关心分块行数，`require_rows` 为 `false` 时允许不指定 `--rows`。
+fn split_params(
+    args: &ParsedArgs,
+    input_path: &str,
+    output_dir: &str,
+    require_rows: bool,
+) -> Result<SplitParams, Message> {
+    let mut params: Map<String, Value> = match args.value("preset") {
+        Some(preset) => {
+            let file = File::open(preset)
+                .map_err(|e| Message::new("cli.open_preset").arg("path", preset).arg("reason", e.to_string()))?;
+            serde_json::from_reader(BufReader::new(file))
+                .map_err(|e| Message::new("cli.invalid_preset").arg("path", preset).arg("reason", e.to_string()))?
+        }
+        None => Map::new(),
+    };
+
+    params.insert("input_path".into(), json!(input_path));
+    params.insert("output_dir".into(), json!(output_dir));
+    if let Some(rows) = args.parse_number("rows")? {
+        params.insert("rows_per_file".into(), json!(rows));
+    }
+    if !params.contains_key("rows_per_file") {
+        if require_rows {
+            return Err(Message::new("cli.rows_required"));
+        }
+        params.insert("rows_per_file".into(), json!(1));
+    }
+    if args.has("header") && args.has("no-header") {
+        return Err(Message::new("cli.header_conflict"));
+    }
+    let has_header = args.has("header")
+        || (!args.has("no-header") && params.get("has_header").and_then(Value::as_bool).unwrap_or(false));
+    params.insert("has_header".into(), json!(has_header));
+    let convert_to_excel = args.has("xlsx") || params.get("convert_to_excel").and_then(Value::as_bool).unwrap_or(false);
+    params.insert("convert_to_excel".into(), json!(convert_to_excel));
+
+    for (option, key) in [("verify", "verify"), ("checkpoint", "checkpoint"), ("flexible", "flexible")] {
+        if args.has(option) {
+            params.insert(key.into(), json!(true));
+        }
+    }
+    for (option, key) in [
+        ("format", "input_format"),
+        ("name", "file_name_template"),
+        ("overwrite", "overwrite_policy"),
+        ("bad-rows", "bad_row_policy"),
+        ("fill", "fill_value"),
+        ("long-rows", "long_rows"),
+        ("header-template", "header_template"),
+        ("filter", "filter"),
+        ("input-dir", "input_dir"),
+        ("pattern", "input_pattern"),
+    ] {
+        if let Some(value) = args.value(option) {
+            params.insert(key.into(), json!(value));
+        }
+    }
+    if !args.values("default").is_empty() {
+        let mut defaults = params.get("column_defaults").filter(|v| v.is_object()).cloned().unwrap_or_else(|| json!({}));
+        for default in args.values("default") {
+            let (column, value) = default
+                .split_once('=')
+                .ok_or_else(|| Message::new("cli.invalid_default").arg("value", default.as_str()))?;
+            defaults[column] = json!(value);
+        }
+        params.insert("column_defaults".into(), defaults);
+    }
+    if let Some(value) = args.value("header-row") {
+        params.insert("header_row".into(), json!(value.split(',').map(str::trim).collect::<Vec<_>>()));
+    }
+    if !args.values("rename").is_empty() {
+        let mut renames = match params.remove("rename_columns") {
+            Some(Value::Array(renames)) => renames,
+            _ => Vec::new(),
+        };
+        for rename in args.values("rename") {
+            let (column, name) = rename
+                .split_once('=')
+                .ok_or_else(|| Message::new("cli.invalid_rename").arg("value", rename.as_str()))?;
+            renames.push(json!({ "column": column_ref(column.trim()), "name": name }));
+        }
+        params.insert("rename_columns".into(), Value::Array(renames));
+    }
+    if !args.values("compute").is_empty() {
+        let mut computed = match params.remove("computed_columns") {
+            Some(Value::Array(computed)) => computed,
+            _ => Vec::new(),
+        };
+        for compute in args.values("compute") {
+            let (name, expression) = compute
+                .split_once('=')
+                .ok_or_else(|| Message::new("cli.invalid_compute").arg("value", compute.as_str()))?;
+            computed.push(json!({ "name": name.trim(), "expression": expression }));
+        }
+        params.insert("computed_columns".into(), Value::Array(computed));
+    }
+    for (option, key) in [("columns", "columns"), ("drop", "exclude_columns"), ("group-by", "group_by")] {
+        if let Some(value) = args.value(option) {
+            params.insert(key.into(), column_list(value));
+        }
+    }
+    if args.has("dedupe") || args.has("dedupe-by") || args.has("keep") {
+        let mut dedupe = params.remove("dedupe").filter(Value::is_object).unwrap_or_else(|| json!({}));
+        if let Some(value) = args.value("dedupe-by") {
+            dedupe["columns"] = column_list(value);
+        }
+        if let Some(value) = args.value("keep") {
+            dedupe["keep"] = json!(value);
+        }
+        params.insert("dedupe".into(), dedupe);
+    }
+    if let Some(value) = args.value("sort") {
+        params.insert("sort".into(), sort_keys(value)?);
+    }
+    if let Some(memory) = args.parse_number("sort-memory")? {
+        params.insert("sort_memory_mb".into(), json!(memory));
+    }
+    if let Some(max_rows) = args.parse_number("max-rows")? {
+        params.insert("max_rows_per_file".into(), json!(max_rows));
+    }
+    if let Some(jobs) = args.parse_number("jobs")? {
+        params.insert("max_parallel_jobs".into(), json!(jobs));
+    }
+    if let Some(layout) = args.value("layout") {
+        let file = File::open(layout)
+            .map_err(|e| Message::new("cli.open_layout").arg("path", layout).arg("reason", e.to_string()))?;
+        let layout: Value = serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| Message::new("cli.invalid_layout").arg("reason", e.to_string()))?;
+        params.insert("fixed_width_layout".into(), layout);
+    }
+
+    serde_json::from_value(Value::Object(params)).map_err(|e| Message::new("cli.invalid_params").arg("reason", e.to_string()))
+}
+
+/// 逗号分隔的列
+fn column_list(value: &str) -> Value {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|column| !column.is_empty())
+        .map(column_ref)
+        .collect()
+}
+
+/// 逗号分隔的排序键，每个键为 `列[:asc|desc][:text|number|date]`
+fn sort_keys(value: &str) -> Result<Value, Message> {
+    let mut keys = Vec::new();
+    for key in value.split(',').map(str::trim).filter(|key| !key.is_empty()) {
+        let mut parts = key.split(':');
+        let column = parts.next().unwrap_or_default().trim();
+        let mut sort_key = json!({ "column": column_ref(column) });
+        for part in parts {
+            match part.trim() {
+                "asc" => sort_key["descending"] = json!(false),
+                "desc" => sort_key["descending"] = json!(true),
+                compare @ ("text" | "number" | "date") => sort_key["compare"] = json!(compare),
+                other => return Err(Message::new("cli.invalid_sort_key").arg("key", key).arg("part", other)),
+            }
+        }
+        keys.push(sort_key);
+    }
+    Ok(Value::Array(keys))
+}
+
+/// 纯数字为列序号，其余为列名
+fn column_ref(column: &str) -> Value {
+    match column.parse::<usize>() {
+        Ok(index) => json!(index),
+        Err(_) => json!(column),
+    }
+}
+
+fn run_split(args: &ParsedArgs, locale: Locale) -> Result<bool, Message> {
+    args.expect_only(&[
+        "rows", "header", "no-header", "xlsx", "format", "layout", "name", "overwrite", "verify",
+        "checkpoint", "input-dir", "pattern", "jobs", "preset", "bad-rows", "flexible", "fill", "default",
+        "long-rows", "header-template", "header-row", "rename", "compute", "filter", "columns",
+        "drop", "dedupe", "dedupe-by", "keep", "sort", "sort-memory", "group-by", "max-rows",
+    ])?;
+
+    // 最后一个位置参数是输出目录，其余为输入文件
+    let Some((output_dir, inputs)) = args.positional.split_last() else {
+        return Err(Message::new("cli.paths_required"));
+    };
+    if inputs.is_empty() && !args.has("input-dir") {
+        return Err(Message::new("cli.input_required"));
+    }
+
+    let single_input = if inputs.len() == 1 && !args.has("input-dir") { inputs[0].as_str() } else { "" };
+    let mut params = split_params(args, single_input, output_dir, true)?;
+
+    let progress = StderrProgress::new(args.has("quiet"), locale);
+    if params.input_path.is_empty() {
+        params.input_paths = inputs.to_vec();
+    }
+    let result = crate::split(params, &progress);
+    progress.finish();
+
+    report_split(args, locale, &result)
+}
+
+fn run_resume(args: &ParsedArgs, locale: Locale) -> Result<bool, Message> {
+    args.expect_only(&[])?;
+    let [input_path, output_dir] = args.positional.as_slice() else {
+        return Err(Message::new("cli.paths_required"));
+    };
+
+    let progress = StderrProgress::new(args.has("quiet"), locale);
+    let params = ResumeParams {
+        input_path: input_path.clone(),
+        output_dir: output_dir.clone(),
+    };
+    let result = crate::resume(&params, &progress);
+    progress.finish();
+
+    report_split(args, locale, &result)
+}
+
+fn run_verify(args: &ParsedArgs, locale: Locale) -> Result<bool, Message> {
+    args.expect_only(&[
+        "header", "no-header", "format", "layout", "piece", "preset", "bad-rows", "flexible", "fill", "default",
+        "long-rows", "header-template", "header-row", "rename", "compute", "filter", "columns",
+        "drop", "dedupe", "dedupe-by", "keep", "sort", "sort-memory", "group-by", "max-rows",
+    ])?;
+    let [input_path, output_dir] = args.positional.as_slice() else {
+        return Err(Message::new("cli.paths_required"));
+    };
+
+    let params = VerifyParams {
+        split: split_params(args, input_path, output_dir, false)?,
+        pieces: args.values("piece").to_vec(),
+    };
+
+    match crate::verify(&params) {
+        Ok(report) => {
+            if args.has("json") {
+                print_json(&report, locale);
+            } else if report.matched {
+                let message = Message::new("cli.verify_matched")
+                    .arg("pieces", report.piece_count.to_string())
+                    .arg("rows", report.piece_rows.to_string())
+                    .arg("hash", report.input_hash.as_str());
+                println!("{}", message.render(locale));
+            } else {
+                eprintln!("{}", report.summary().render(locale));
+            }
+            Ok(report.matched)
+        }
+        Err(e) => {
+            if args.has("json") {
+                print_json(&json!({ "matched": false, "error": Localized::new(e, locale) }), locale);
+            } else {
+                eprintln!("{}", Message::new("cli.verify_failed").arg("reason", e.message(locale)).render(locale));
+            }
+            Ok(false)
+        }
+    }
+}
+
+fn run_merge(args: &ParsedArgs, locale: Locale) -> Result<bool, Message> {
+    args.expect_only(&["output", "header", "union", "alias", "fill"])?;
+    let Some(output_path) = args.value("output") else {
+        return Err(Message::new("cli.output_required"));
+    };
+    if args.positional.is_empty() {
+        return Err(Message::new("cli.merge_inputs_required"));
+    }
+
+    let mut column_aliases = HashMap::new();
+    for alias in args.values("alias") {
+        let (from, to) = alias
+            .split_once('=')
+            .ok_or_else(|| Message::new("cli.invalid_alias").arg("value", alias.as_str()))?;
+        column_aliases.insert(from.to_string(), to.to_string());
+    }
+
+    let params: MergeParams = serde_json::from_value(json!({
+        "input_paths": args.positional,
+        "output_path": output_path,
+        "has_header": args.has("header"),
+        "header_mode": if args.has("union") { "union" } else { "strict" },
+        "column_aliases": column_aliases,
+        "fill_value": args.value("fill").unwrap_or(""),
+    }))
+    .map_err(|e| Message::new("cli.invalid_params").arg("reason", e.to_string()))?;
+
+    let result = crate::merge(&params);
+
+    if args.has("json") {
+        print_json(&result, locale);
+    } else if result.success {
+        let message = Message::new("cli.merge_done")
+            .arg("files", result.files.len().to_string())
+            .arg("rows", result.row_count.to_string())
+            .arg("path", output_path);
+        println!("{}", message.render(locale));
+    } else {
+        let reason = result.error.as_ref().map_or_else(|| lookup("cli.unknown_error", locale).to_string(), |e| e.message(locale));
+        eprintln!("{}", Message::new("cli.merge_failed").arg("reason", reason).render(locale));
+    }
+    Ok(result.success)
+}
+
+/// 输出分割结果，返回是否成功
+fn report_split(args: &ParsedArgs, locale: Locale, result: &SplitResult) -> Result<bool, Message> {
+    if args.has("json") {
+        print_json(result, locale);
+        return Ok(result.success);
+    }
+
+    let say = |message: Message| message.render(locale);
+    for input in &result.inputs {
+        match input.result.error {
+            None => println!(
+                "{}",
+                say(Message::new("cli.input_done")
+                    .arg("path", input.input_path.as_str())
+                    .arg("files", input.result.file_count.to_string()))
+            ),
+            Some(ref e) => eprintln!(
+                "{}",
+                say(Message::new("cli.input_failed").arg("path", input.input_path.as_str()).arg("reason", e.message(locale)))
+            ),
+        }
+    }
+    if let Some(report) = result.verification.as_ref().filter(|r| r.matched) {
+        println!("{}", say(Message::new("cli.verify_rows").arg("rows", report.input_rows.to_string())));
+    }
+    for skipped in &result.skipped_files {
+        println!("{}", say(Message::new("cli.skipped_file").arg("path", skipped.as_str())));
+    }
+    for (count, key) in [
+        (result.adjusted_rows, "cli.adjusted_rows"),
+        (result.filtered_rows, "cli.filtered_rows"),
+        (result.duplicate_rows, "cli.duplicate_rows"),
+        (result.split_groups, "cli.split_groups"),
+    ] {
+        if count > 0 {
+            println!("{}", say(Message::new(key).arg("count", count.to_string())));
+        }
+    }
+    if result.rejected_rows > 0 {
+        let message = match result.rejected_file {
+            Some(ref file) => Message::new("cli.rejected_rows_file").arg("path", file.as_str()),
+            None => Message::new("cli.rejected_rows"),
+        };
+        eprintln!("{}", say(message.arg("count", result.rejected_rows.to_string())));
+    }
+    match result.error {
+        None => println!("{}", say(Message::new("cli.split_done").arg("files", result.file_count.to_string()))),
+        Some(ref e) => eprintln!("{}", say(Message::new("cli.split_failed").arg("reason", e.message(locale)))),
+    }
+    if result.checkpoint_saved {
+        eprintln!("{}", lookup("cli.checkpoint_saved", locale));
+    }
+    Ok(result.success)
+}
+
+fn print_json<T: Serialize>(value: &T, locale: Locale) {
+    match serde_json::to_string_pretty(&Localized::new(value, locale)) {
+        Ok(text) => println!("{}", text),
+        Err(e) => eprintln!("{}", Message::new("cli.print_json_failed").arg("reason", e.to_string()).render(locale)),
+    }
+}
+
+/// 把进度输出到标准错误：终端中在同一行刷新，重定向到文件时每隔几秒输出一行
+struct StderrProgress {
+    inner: Option<Throttled<StderrLine>>,
+}
+
+struct StderrLine {
+    is_terminal: bool,
+    locale: Locale,
+    /// 终端中是否留有未换行的进度行
+    pending_line: AtomicBool,
+}
+
+impl StderrProgress {
+    fn new(quiet: bool, locale: Locale) -> StderrProgress {
+        let is_terminal = std::io::stderr().is_terminal();
+        let interval = if is_terminal { Duration::from_millis(200) } else { Duration::from_secs(5) };
+        StderrProgress {
+            inner: (!quiet).then(|| Throttled::new(
+                StderrLine { is_terminal, locale, pending_line: AtomicBool::new(false) },
+                interval,
+            )),
+        }
+    }
+
+    /// 结束进度行，避免后续输出接在进度后面
+    fn finish(&self) {
+        if let Some(ref inner) = self.inner
+            && inner.inner().pending_line.swap(false, Ordering::Relaxed)
+        {
+            eprintln!();
+        }
+    }
+}
+
+impl ProgressListener for StderrProgress {
+    fn on_progress(&self, progress: &Progress) {
+        match self.inner {
+            Some(ref inner) => inner.on_progress(progress),
+            None => NoProgress.on_progress(progress),
+        }
+    }
+}
+
+impl ProgressListener for StderrLine {
+    fn on_progress(&self, progress: &Progress) {
+        let stage = match progress.stage {
+            Stage::Deduplicating => "cli.stage.deduplicating",
+            Stage::Sorting => "cli.stage.sorting",
+            Stage::Splitting => "cli.stage.splitting",
+            Stage::Verifying => "cli.stage.verifying",
+            Stage::Converting => "cli.stage.converting",
+            Stage::Finishing => "cli.stage.finishing",
+        };
+        let percent = progress.percent().map(|p| format!(" {:5.1}%", p)).unwrap_or_default();
+        let line = Message::new("cli.progress")
+            .arg("stage", Message::new(stage))
+            .arg("percent", percent)
+            .arg("rows", progress.rows.to_string())
+            .arg("pieces", progress.pieces.to_string())
+            .render(self.locale);
+
+        let mut stderr = std::io::stderr().lock();
+        if self.is_terminal {
+            let _ = write!(stderr, "\r\x1b[2K{}", line);
+            let _ = stderr.flush();
+            self.pending_line.store(true, Ordering::Relaxed);
+        } else {
+            let _ = writeln!(stderr, "{}", line);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<ParsedArgs, Message> {
+        ParsedArgs::parse(&args.iter().map(|s| s.to_string()).collect::<Vec<_>>())
+    }
+
+    fn error_key<T>(result: Result<T, Message>) -> &'static str {
+        match result {
+            Err(Message::Key { key, .. }) => key,
+            _ => panic!("expected an error"),
+        }
+    }
+
+    #[test]
+    fn options_and_positional_arguments() {
+        let args = parse(&[
+            "a.csv", "--rows", "10", "--header", "--compute=x=1", "-o", "out.csv", "--compute", "y=2", "b.csv", "--", "--c.csv",
+        ])
+        .unwrap();
+        assert_eq!(args.positional, ["a.csv", "b.csv", "--c.csv"]);
+        assert_eq!(args.value("rows"), Some("10"));
+        assert!(args.has("header") && !args.has("no-header"));
+        // 可重复的选项保留每个值，--name=value 只在第一个等号处切开
+        assert_eq!(args.values("compute"), ["x=1", "y=2"]);
+        assert_eq!(args.value("compute"), Some("y=2"));
+        assert_eq!(args.value("output"), Some("out.csv"));
+        assert_eq!(args.parse_number("rows").unwrap(), Some(10));
+        assert_eq!(args.parse_number("jobs").unwrap(), None);
+
+        assert_eq!(error_key(parse(&["a.csv", "--rows"])), "cli.option_needs_value");
+        assert_eq!(error_key(parse(&["--header=yes"])), "cli.option_takes_no_value");
+        assert_eq!(error_key(parse(&["--rows", "-1"]).unwrap().parse_number("rows")), "cli.not_a_number");
+        assert_eq!(error_key(parse(&["--lang", "fr"]).unwrap().locale()), "cli.unsupported_lang");
+        assert_eq!(parse(&["--lang", "en-US"]).unwrap().locale().unwrap(), Locale::EnUs);
+        assert_eq!(error_key(parse(&["--rows", "1", "--bogus"]).unwrap().expect_only(&["rows"])), "cli.unsupported_option");
+        assert!(parse(&["--rows", "1", "--json", "--quiet"]).unwrap().expect_only(&["rows"]).is_ok());
+    }
+
+    #[test]
+    fn usage_errors_exit_with_two() {
+        let run = |args: &[&str]| run(args.iter().map(|s| s.to_string()).collect());
+        assert_eq!(run(&[]), 2);
+        assert_eq!(run(&["frobnicate", "a.csv"]), 2);
+        assert_eq!(run(&["split", "a.csv", "out", "--bogus"]), 2);
+        assert_eq!(run(&["split", "a.csv", "out", "--rows"]), 2);
+        assert_eq!(run(&["split", "a.csv", "out", "--rows", "ten"]), 2);
+        assert_eq!(run(&["split", "out", "--rows", "10"]), 2);
+        assert_eq!(run(&["split", "a.csv", "out", "--rows", "10", "--header", "--no-header"]), 2);
+        assert_eq!(run(&["resume", "a.csv", "out", "--rows", "10"]), 2);
+        assert_eq!(run(&["merge", "a.csv", "b.csv"]), 2);
+        assert_eq!(run(&["split", "--lang", "fr", "a.csv", "out"]), 2);
+        assert_eq!(run(&["split", "--help"]), 0);
+    }
+
+    #[test]
+    fn command_line_options_take_precedence_over_the_preset() {
+        let dir = std::env::temp_dir().join(format!("csv-splitter-cli-{}-preset", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let preset = dir.join("preset.json");
+        std::fs::write(
+            &preset,
+            r#"{"rows_per_file": 5, "has_header": true, "fill_value": "-", "flexible": true,
+                "rename_columns": [{"column": "a", "name": "b"}], "dedupe": {"columns": ["id"]}}"#,
+        )
+        .unwrap();
+        let preset = preset.display().to_string();
+
+        let args = parse(&["--preset", &preset, "--rows", "7", "--no-header", "--rename", "2=c", "--keep", "last"]).unwrap();
+        let params = serde_json::to_value(split_params(&args, "in.csv", "out", true).unwrap()).unwrap();
+        assert_eq!(params["input_path"], "in.csv");
+        assert_eq!(params["rows_per_file"], 7);
+        assert_eq!(params["has_header"], false);
+        assert_eq!(params["fill_value"], "-");
+        assert_eq!(params["flexible"], true);
+        // 可重复的选项追加在预设之后，嵌套的对象只替换指定的字段
+        assert_eq!(params["rename_columns"], json!([{ "column": "a", "name": "b" }, { "column": 2, "name": "c" }]));
+        assert_eq!(params["dedupe"]["columns"], json!(["id"]));
+        assert_eq!(params["dedupe"]["keep"], "last");
+
+        let args = parse(&["--preset", &preset]).unwrap();
+        let params = serde_json::to_value(split_params(&args, "in.csv", "out", true).unwrap()).unwrap();
+        assert_eq!((&params["rows_per_file"], &params["has_header"]), (&json!(5), &json!(true)));
+
+        assert_eq!(error_key(split_params(&parse(&[]).unwrap(), "in.csv", "out", true)), "cli.rows_required");
+        assert!(split_params(&parse(&[]).unwrap(), "in.csv", "out", false).is_ok());
+        let missing = dir.join("missing.json").display().to_string();
+        assert_eq!(error_key(split_params(&parse(&["--preset", &missing]).unwrap(), "in.csv", "out", true)), "cli.open_preset");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn sort_keys_and_column_references() {
+        assert_eq!(column_ref("3"), json!(3));
+        assert_eq!(column_ref("col3"), json!("col3"));
+        assert_eq!(column_list("name, 3,,email"), json!(["name", 3, "email"]));
+
+        assert_eq!(
+            sort_keys("created:desc:date, 2:number,name:asc").unwrap(),
+            json!([
+                { "column": "created", "descending": true, "compare": "date" },
+                { "column": 2, "compare": "number" },
+                { "column": "name", "descending": false },
+            ])
+        );
+        assert_eq!(error_key(sort_keys("id:down")), "cli.invalid_sort_key");
+
+        let args = parse(&["--rows", "1", "--sort", "created:desc:date,1"]).unwrap();
+        let params = split_params(&args, "in.csv", "out", true).unwrap();
+        assert_eq!(params.sort.len(), 2);
+        assert!(params.sort[0].descending);
+        assert_eq!(params.sort[1].column, crate::ColumnRef::Index(1));
+    }
+}
@@ -0,0 +1,64 @@
+use csv::StringRecord;
+use serde::{Deserialize, Serialize};
+
+use crate::expr::{parse_value, source_file_name, Expr, Row};
+use crate::i18n::Message;
+use crate::{Error, SplitParams};
+
+/// 按表达式计算、追加在每行末尾的列
+///
+/// 表达式可以引用原有的列和排在前面的计算列，如：
+/// - 拼接和截取：`concat(region, '-', substr(code, 1, 3))`、`upper(trim(name))`
+/// - 日期：`year(created)`、`format_date(created, '%Y%m')`、`date(created, '%d/%m/%Y')`
+/// - 算术：`number(price) * qty`、`amount / 100`（不是数字的值视为空值）
+/// - 常量：`'CN'`、`1`
+/// - 来源：`row_number()` 原文件中的数据行号、`file_name()` 原文件名
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComputedColumn {
+    /// 列名，不能与已有的列同名
+    pub name: String,
+    pub expression: String,
+}
+
+/// 解析好的计算列
+pub(crate) struct ComputedColumns {
+    exprs: Vec<Expr>,
+    file_name: String,
+    /// 原有的列数，计算列从这里开始
+    width: usize,
+}
+
+impl ComputedColumns {
+    /// 没有计算列时返回 `None`；计算列的列名追加到 `headers` 末尾
+    pub(crate) fn new(params: &SplitParams, headers: &mut StringRecord) -> Result<Option<ComputedColumns>, Error> {
+        if params.computed_columns.is_empty() {
+            return Ok(None);
+        }
+        let width = headers.len();
+        let mut exprs = Vec::with_capacity(params.computed_columns.len());
+        for column in &params.computed_columns {
+            if headers.iter().any(|h| h == column.name) {
+                return Err(Error::invalid_params(Message::new("computed.duplicate_name").arg("name", column.name.as_str())));
+            }
+            let invalid = |reason| Error::InvalidExpression { column: column.name.clone(), reason };
+            exprs.push(parse_value(&column.expression, headers, &invalid)?);
+            headers.push_field(&column.name);
+        }
+        Ok(Some(ComputedColumns { exprs, file_name: source_file_name(params), width }))
+    }
+
+    /// 依次计算并写在原有的列之后，空值写为空字段
+    ///
+    /// 灵活模式下保留的多余字段移到计算列之后，计算列的值始终在标题行中对应的位置。
+    pub(crate) fn apply(&self, record: &mut StringRecord, row_number: usize) {
+        let extra: Vec<String> = record.iter().skip(self.width).map(str::to_string).collect();
+        record.truncate(self.width);
+        for expr in &self.exprs {
+            let value = expr.eval(&Row { record, row_number, file_name: &self.file_name }).to_text();
+            record.push_field(&value);
+        }
+        for field in &extra {
+            record.push_field(field);
+        }
+    }
+}
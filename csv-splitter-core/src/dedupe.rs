@@ -0,0 +1,374 @@
+use std::cmp::Reverse;
+use std::collections::hash_map::Entry;
+use std::collections::{BinaryHeap, HashMap};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+
+use csv::StringRecord;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::pipeline::{PreparedRecords, RowPipeline};
+use crate::progress::{Progress, ProgressListener, Stage, REPORT_EVERY_ROWS};
+use crate::projection::{ColumnRef, Projection};
+use crate::source::open_source;
+use crate::{Error, SplitParams};
+
+/// 内存中最多暂存的行的键哈希数，超过后按哈希分桶写入磁盘
+const MEMORY_ENTRIES: usize = 2 * 1024 * 1024;
+/// 写入磁盘时的分桶数，之后每个桶单独载入内存查找重复
+const BUCKETS: usize = 256;
+
+/// 去除重复行
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Dedupe {
+    /// 判断重复的列（列名或从1开始的列序号），为空时比较输出的整行
+    #[serde(default)]
+    pub columns: Vec<ColumnRef>,
+    /// 重复的行中保留哪一行
+    #[serde(default)]
+    pub keep: DedupeKeep,
+}
+
+/// 重复的行中保留的行
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DedupeKeep {
+    /// 保留第一次出现的行
+    #[default]
+    First,
+    /// 保留最后一次出现的行，写入位置也是最后一次出现的位置
+    Last,
+}
+
+/// 要丢弃的重复行，分割前先完整扫描一遍输入得到
+///
+/// 扫描时只保存每行的键哈希（128位）和序号，超过内存上限时按哈希分桶写入磁盘，
+/// 相同的键一定落在同一个桶中，逐个桶找出重复的行后再按序号归并，
+/// 因此可以处理比内存大的文件。序号为经过筛选后的第几行，分割和校验时按同样的顺序逐行判断。
+///
+/// 只比较哈希而不保存键本身：两个不同的键哈希相同时，后出现的行会被当作重复行丢弃，
+/// 还原校验按同样的方式去重，也发现不了。n 行中出现这种情况的概率约为 n²/2¹²⁹，
+/// 一百亿行时约为 10⁻¹⁹，远低于磁盘出错的概率，因此不再回读原文比较键的内容。
+pub(crate) struct Duplicates {
+    runs: Vec<DroppedRun>,
+    /// (下一个要丢弃的序号, 所在的段)
+    heap: BinaryHeap<Reverse<(u64, usize)>>,
+    /// 已判断过的行数
+    seen: u64,
+    count: usize,
+    dir: Option<PathBuf>,
+}
+
+impl Duplicates {
+    /// 没有开启去重时返回 `None`；临时文件写在 `work_dir` 下，随返回值一起删除
+    pub(crate) fn scan(
+        params: &SplitParams,
+        pipeline: &RowPipeline,
+        work_dir: &Path,
+        progress: &dyn ProgressListener,
+    ) -> Result<Option<Duplicates>, Error> {
+        Duplicates::scan_with_limit(params, pipeline, work_dir, progress, MEMORY_ENTRIES)
+    }
+
+    /// `memory_entries` 为内存中最多暂存的键哈希数
+    fn scan_with_limit(
+        params: &SplitParams,
+        pipeline: &RowPipeline,
+        work_dir: &Path,
+        progress: &dyn ProgressListener,
+        memory_entries: usize,
+    ) -> Result<Option<Duplicates>, Error> {
+        let Some(ref dedupe) = params.dedupe else {
+            return Ok(None);
+        };
+        // 按列去重时从处理后的行中取键，整行去重时比较实际输出的列
+        let key_columns = if dedupe.columns.is_empty() {
+            Projection::new(params, pipeline.headers())?.map(|p| p.positions().to_vec())
+        } else {
+            Some(dedupe.columns
+                .iter()
+                .map(|column| column.position(pipeline.headers()))
+                .collect::<Result<Vec<_>, _>>()?)
+        };
+
+        let mut duplicates = Duplicates {
+            runs: Vec::new(),
+            heap: BinaryHeap::new(),
+            seen: 0,
+            count: 0,
+            dir: None,
+        };
+        let mut records = PreparedRecords::new(params, open_source(params)?, pipeline);
+        let total_bytes = std::fs::metadata(&params.input_path).map_or(0, |m| m.len());
+        let mut current_progress = Progress::new(&params.input_path, Stage::Deduplicating, total_bytes);
+        let mut entries: Vec<(u128, u64)> = Vec::new();
+        let mut buckets: Vec<BufWriter<File>> = Vec::new();
+        let mut record = StringRecord::new();
+        let mut index = 0;
+
+        while records.next(&mut record)? {
+            entries.push((key_hash(&record, key_columns.as_deref()), index));
+            index += 1;
+            if index % REPORT_EVERY_ROWS as u64 == 0 {
+                current_progress.rows = records.rows_read();
+                current_progress.bytes_read = records.bytes_read();
+                progress.on_progress(&current_progress);
+                if progress.is_cancelled() {
+                    return Err(Error::Cancelled);
+                }
+            }
+            if entries.len() >= memory_entries {
+                if buckets.is_empty() {
+                    buckets = duplicates.create_buckets(work_dir)?;
+                }
+                spill(&mut entries, &mut buckets)?;
+            }
+        }
+
+        if buckets.is_empty() {
+            let dropped = find_dropped(&entries, dedupe.keep);
+            duplicates.add_run(DroppedRun::Memory(dropped.into_iter()))?;
+        } else {
+            spill(&mut entries, &mut buckets)?;
+            drop(entries);
+            let dir = duplicates.dir.clone().unwrap_or_default();
+            for (n, bucket) in buckets.into_iter().enumerate() {
+                bucket.into_inner().map_err(|e| Error::from(e.into_error()))?;
+                let bucket_path = dir.join(format!("bucket_{}.bin", n));
+                let entries = read_entries(&bucket_path)?;
+                std::fs::remove_file(&bucket_path).map_err(|e| Error::io(&bucket_path, e))?;
+                let dropped = find_dropped(&entries, dedupe.keep);
+                if dropped.is_empty() {
+                    continue;
+                }
+                let run_path = dir.join(format!("dropped_{}.bin", n));
+                let file = File::create(&run_path).map_err(|e| Error::io(&run_path, e))?;
+                let mut writer = BufWriter::new(file);
+                for index in dropped {
+                    writer.write_all(&index.to_le_bytes())?;
+                }
+                writer.flush()?;
+                let file = File::open(&run_path).map_err(|e| Error::io(&run_path, e))?;
+                duplicates.add_run(DroppedRun::File(BufReader::new(file)))?;
+            }
+        }
+        Ok(Some(duplicates))
+    }
+
+    /// 下一条经过筛选的行是否为要丢弃的重复行，须按顺序对每一行调用一次
+    pub(crate) fn is_duplicate(&mut self) -> Result<bool, Error> {
+        let index = self.seen;
+        self.seen += 1;
+        match self.heap.peek() {
+            Some(&Reverse((next, run))) if next == index => {
+                self.heap.pop();
+                if let Some(next) = self.runs[run].next()? {
+                    self.heap.push(Reverse((next, run)));
+                }
+                self.count += 1;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// 丢弃的重复行数
+    pub(crate) fn count(&self) -> usize {
+        self.count
+    }
+
+    /// (已判断过的行数, 丢弃的重复行数)，用于记录断点
+    pub(crate) fn state(&self) -> (usize, usize) {
+        (self.seen as usize, self.count)
+    }
+
+    /// 从断点继续：跳过断点之前已判断过的行
+    pub(crate) fn resume(&mut self, seen: usize, count: usize) -> Result<(), Error> {
+        while (self.seen as usize) < seen {
+            self.is_duplicate()?;
+        }
+        self.count = count;
+        Ok(())
+    }
+
+    fn create_buckets(&mut self, work_dir: &Path) -> Result<Vec<BufWriter<File>>, Error> {
+        let dir = work_dir.join(format!("dedupe-{}", std::process::id()));
+        if dir.exists() {
+            std::fs::remove_dir_all(&dir).map_err(|e| Error::io(&dir, e))?;
+        }
+        std::fs::create_dir_all(&dir).map_err(|e| Error::io(&dir, e))?;
+        self.dir = Some(dir.clone());
+        (0..BUCKETS)
+            .map(|n| {
+                let path = dir.join(format!("bucket_{}.bin", n));
+                let file = File::create(&path).map_err(|e| Error::io(&path, e))?;
+                Ok(BufWriter::with_capacity(64 * 1024, file))
+            })
+            .collect()
+    }
+
+    fn add_run(&mut self, mut run: DroppedRun) -> Result<(), Error> {
+        if let Some(first) = run.next()? {
+            self.heap.push(Reverse((first, self.runs.len())));
+            self.runs.push(run);
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Duplicates {
+    fn drop(&mut self) {
+        if let Some(ref dir) = self.dir {
+            let _ = std::fs::remove_dir_all(dir);
+        }
+    }
+}
+
+/// 一段按序号递增的要丢弃的行
+enum DroppedRun {
+    Memory(std::vec::IntoIter<u64>),
+    File(BufReader<File>),
+}
+
+impl DroppedRun {
+    fn next(&mut self) -> Result<Option<u64>, Error> {
+        match self {
+            DroppedRun::Memory(indexes) => Ok(indexes.next()),
+            DroppedRun::File(reader) => {
+                let mut bytes = [0; 8];
+                match reader.read_exact(&mut bytes) {
+                    Ok(()) => Ok(Some(u64::from_le_bytes(bytes))),
+                    Err(e) if e.kind() == ErrorKind::UnexpectedEof => Ok(None),
+                    Err(e) => Err(e.into()),
+                }
+            }
+        }
+    }
+}
+
+/// 键的哈希：取SHA-256的前128位，字段前写入长度，避免不同的字段拼接后相同
+fn key_hash(record: &StringRecord, columns: Option<&[usize]>) -> u128 {
+    let mut hasher = Sha256::new();
+    let mut update = |field: &str| {
+        hasher.update((field.len() as u64).to_le_bytes());
+        hasher.update(field.as_bytes());
+    };
+    match columns {
+        Some(columns) => columns.iter().for_each(|&c| update(record.get(c).unwrap_or(""))),
+        None => record.iter().for_each(update),
+    }
+    let digest = hasher.finalize();
+    u128::from_le_bytes(digest[..16].try_into().unwrap_or_default())
+}
+
+/// 把暂存的键哈希按高8位写入对应的桶，同一个桶中的序号保持递增
+fn spill(entries: &mut Vec<(u128, u64)>, buckets: &mut [BufWriter<File>]) -> Result<(), Error> {
+    for &(hash, index) in entries.iter() {
+        let bucket = &mut buckets[(hash >> 120) as usize % BUCKETS];
+        bucket.write_all(&hash.to_le_bytes())?;
+        bucket.write_all(&index.to_le_bytes())?;
+    }
+    entries.clear();
+    Ok(())
+}
+
+fn read_entries(path: &Path) -> Result<Vec<(u128, u64)>, Error> {
+    let bytes = std::fs::read(path).map_err(|e| Error::io(path, e))?;
+    Ok(bytes
+        .chunks_exact(24)
+        .map(|chunk| {
+            let hash = u128::from_le_bytes(chunk[..16].try_into().unwrap_or_default());
+            let index = u64::from_le_bytes(chunk[16..].try_into().unwrap_or_default());
+            (hash, index)
+        })
+        .collect())
+}
+
+/// 在按序号递增的键哈希中找出要丢弃的行，返回递增的序号
+fn find_dropped(entries: &[(u128, u64)], keep: DedupeKeep) -> Vec<u64> {
+    let mut kept: HashMap<u128, u64> = HashMap::with_capacity(entries.len());
+    let mut dropped = Vec::new();
+    for &(hash, index) in entries {
+        match keep {
+            DedupeKeep::First => match kept.entry(hash) {
+                Entry::Occupied(_) => dropped.push(index),
+                Entry::Vacant(entry) => {
+                    entry.insert(index);
+                }
+            },
+            DedupeKeep::Last => {
+                if let Some(previous) = kept.insert(hash, index) {
+                    dropped.push(previous);
+                }
+            }
+        }
+    }
+    dropped.sort_unstable();
+    dropped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header::resolve_headers;
+    use crate::progress::NoProgress;
+
+    /// 按参数扫描输入，返回每一行是否被当作重复行丢弃
+    fn dropped(name: &str, content: &str, dedupe: serde_json::Value, memory_entries: usize) -> (Vec<bool>, usize) {
+        let dir = std::env::temp_dir().join(format!("csv-splitter-dedupe-{}-{}", std::process::id(), name));
+        std::fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("input.csv");
+        std::fs::write(&input, content).unwrap();
+        let params: SplitParams = serde_json::from_value(serde_json::json!({
+            "input_path": input.display().to_string(),
+            "output_dir": dir.display().to_string(),
+            "rows_per_file": 10,
+            "has_header": true,
+            "convert_to_excel": false,
+            "dedupe": dedupe,
+        }))
+        .unwrap();
+        let headers = resolve_headers(&params, open_source(&params).unwrap().headers()).unwrap();
+        let pipeline = RowPipeline::new(&params, &headers).unwrap();
+        let mut duplicates = Duplicates::scan_with_limit(&params, &pipeline, &dir, &NoProgress, memory_entries)
+            .unwrap()
+            .unwrap();
+        let rows = content.lines().count() - 1;
+        let flags = (0..rows).map(|_| duplicates.is_duplicate().unwrap()).collect();
+        let count = duplicates.count();
+        drop(duplicates);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1, "临时文件应随去重结果一起删除");
+        std::fs::remove_dir_all(&dir).unwrap();
+        (flags, count)
+    }
+
+    const INPUT: &str = "key,value\na,1\nb,2\na,3\nc,4\nb,5\na,6\nd,7\n";
+
+    #[test]
+    fn keeps_first_or_last_occurrence() {
+        let (flags, count) = dropped("first", INPUT, serde_json::json!({ "columns": ["key"] }), MEMORY_ENTRIES);
+        assert_eq!(flags, [false, false, true, false, true, true, false]);
+        assert_eq!(count, 3);
+        let (flags, _) = dropped("last", INPUT, serde_json::json!({ "columns": ["key"], "keep": "last" }), MEMORY_ENTRIES);
+        assert_eq!(flags, [true, true, true, false, false, false, false]);
+    }
+
+    #[test]
+    fn whole_row_keys_separate_fields() {
+        let input = "a,b\nx,yz\nxy,z\nx,yz\n";
+        let (flags, _) = dropped("whole", input, serde_json::json!({}), MEMORY_ENTRIES);
+        assert_eq!(flags, [false, false, true]);
+    }
+
+    #[test]
+    fn bucketed_scan_matches_the_in_memory_result() {
+        for keep in ["first", "last"] {
+            let dedupe = serde_json::json!({ "columns": [1], "keep": keep });
+            let in_memory = dropped(&format!("memory-{}", keep), INPUT, dedupe.clone(), MEMORY_ENTRIES);
+            let bucketed = dropped(&format!("buckets-{}", keep), INPUT, dedupe, 2);
+            assert_eq!(in_memory, bucketed, "{}", keep);
+        }
+    }
+}
@@ -0,0 +1,392 @@
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+use serde::{Serialize, Serializer};
+
+use crate::i18n::{self, Locale, Message};
+use crate::source::InputFormat;
+
+/// 分割引擎返回给调用方的错误
+///
+/// 序列化为 `{ "code": ..., "message": ..., ... }`：`code` 是稳定的错误码，调用方据此区分错误类型；
+/// `message` 是给用户看的说明，语言见 [`Localized`](crate::Localized)；`path`、`row`、`column`、`byte` 为出错位置，没有时省略。
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// 读写文件失败
+    Io { path: Option<String>, reason: String },
+    /// 没有读取或写入权限
+    PermissionDenied { path: String },
+    /// 文件或目录不存在
+    NotFound { path: String },
+    /// 输入文件的扩展名与输入格式不符
+    UnsupportedFile { format: InputFormat },
+    /// 输入文件为空
+    EmptyInput,
+    /// 输入文件没有数据行
+    NoDataRows,
+    /// 每个文件的行数为0
+    ZeroRowsPerFile,
+    /// 输入格式不支持断点续传
+    CheckpointUnsupported,
+    /// 定长文本输入没有提供列布局
+    LayoutRequired,
+    /// 列选择中的列名或列序号在标题行中不存在
+    ColumnNotFound { column: String },
+    /// 列选择后没有剩下任何列
+    NoColumnsSelected,
+    /// 替换的标题行与输入文件的列数不一致
+    HeaderMismatch { expected: usize, found: usize },
+    /// 分割参数无效
+    InvalidParams { reason: Message },
+    /// 行筛选条件无法解析
+    InvalidFilter { reason: Message },
+    /// 计算列的表达式无法解析
+    InvalidExpression { column: String, reason: Message },
+    /// 输入内容无法解析，`row` 为文件中的行号（JSON输入为记录序号），从1开始
+    Parse { row: Option<u64>, byte: Option<u64>, reason: Message },
+    /// 某一行的字段数与前面的行不一致
+    FieldCount { row: u64, byte: u64, expected: u64, found: u64 },
+    /// 同一条记录中有两个字段对应同一列，如JSON中嵌套对象展开后与带点的键同名，`row` 为记录序号
+    DuplicateField { row: u64, column: String },
+    /// JSON输入中的某条记录无法解析或不是对象，前后的记录仍可读取，`row` 为记录序号
+    InvalidRecord { row: u64, reason: Message },
+    /// 输入不是UTF-8编码，`column` 为出错字段的序号（从1开始）
+    Encoding { row: Option<u64>, column: Option<u64>, byte: Option<u64> },
+    /// 超出输出格式的限制，`what` 为信息目录中的键
+    LimitExceeded { what: &'static str, limit: u64 },
+    /// 调用方取消了任务
+    Cancelled,
+    /// 输出目录中已有同名文件
+    OutputExists { path: String },
+    /// 没有找到可以继续的断点
+    CheckpointNotFound,
+    /// 输入文件在中断后被修改，无法从断点继续
+    InputChanged,
+    /// 批量任务中有文件分割失败
+    BatchFailed { failed: usize, total: usize },
+    /// 批量任务中与 `path` 的文件名相同，输出会互相覆盖
+    DuplicateInput { path: String },
+    /// 分块与原文件不一致
+    VerifyFailed { reason: Message },
+    /// 其他错误
+    Other { reason: Message },
+}
+
+impl Error {
+    /// 稳定的错误码，新增错误类型时只增不改
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::Io { .. } => "io",
+            Error::PermissionDenied { .. } => "permission_denied",
+            Error::NotFound { .. } => "not_found",
+            Error::UnsupportedFile { .. } => "unsupported_file",
+            Error::EmptyInput => "empty_input",
+            Error::NoDataRows => "no_data_rows",
+            Error::ZeroRowsPerFile => "zero_rows_per_file",
+            Error::CheckpointUnsupported => "checkpoint_unsupported",
+            Error::LayoutRequired => "layout_required",
+            Error::ColumnNotFound { .. } => "column_not_found",
+            Error::NoColumnsSelected => "no_columns_selected",
+            Error::HeaderMismatch { .. } => "header_mismatch",
+            Error::InvalidParams { .. } => "invalid_params",
+            Error::InvalidFilter { .. } => "invalid_filter",
+            Error::InvalidExpression { .. } => "invalid_expression",
+            Error::Parse { .. } => "parse",
+            Error::FieldCount { .. } => "field_count",
+            Error::DuplicateField { .. } => "duplicate_field",
+            Error::InvalidRecord { .. } => "invalid_record",
+            Error::Encoding { .. } => "encoding",
+            Error::LimitExceeded { .. } => "limit_exceeded",
+            Error::Cancelled => "cancelled",
+            Error::OutputExists { .. } => "output_exists",
+            Error::CheckpointNotFound => "checkpoint_not_found",
+            Error::InputChanged => "input_changed",
+            Error::BatchFailed { .. } => "batch_failed",
+            Error::DuplicateInput { .. } => "duplicate_input",
+            Error::VerifyFailed { .. } => "verify_failed",
+            Error::Other { .. } => "other",
+        }
+    }
+
+    /// 按指定语言生成给用户看的说明
+    ///
+    /// 来自底层的原因（如系统的IO错误、CSV解析错误）保持原文。
+    pub fn message(&self, locale: Locale) -> String {
+        let key = match self {
+            Error::Io { path: None, .. } => "io.no_path",
+            Error::UnsupportedFile { format } => match format {
+                InputFormat::Csv => "unsupported_file.csv",
+                InputFormat::Json => "unsupported_file.json",
+                InputFormat::FixedWidth => "unsupported_file.fixed_width",
+            },
+            Error::Parse { row: None, .. } => "parse.no_row",
+            Error::Encoding { row: Some(_), column: Some(_), .. } => "encoding.row_column",
+            Error::Encoding { row: Some(_), column: None, .. } => "encoding.row",
+            other => other.code(),
+        };
+
+        let mut args: Vec<(&str, String)> = Vec::new();
+        if let Some(path) = self.path() {
+            args.push(("path", path.to_string()));
+        }
+        if let Some(row) = self.row() {
+            args.push(("row", row.to_string()));
+        }
+        if let Some(column) = self.column() {
+            args.push(("column", column.to_string()));
+        }
+        match self {
+            Error::Io { reason, .. } => args.push(("reason", reason.clone())),
+            Error::InvalidParams { reason }
+            | Error::InvalidFilter { reason }
+            | Error::Parse { reason, .. }
+            | Error::InvalidRecord { reason, .. }
+            | Error::VerifyFailed { reason }
+            | Error::Other { reason } => args.push(("reason", reason.render(locale))),
+            Error::FieldCount { expected, found, .. } => {
+                args.push(("expected", expected.to_string()));
+                args.push(("found", found.to_string()));
+            }
+            Error::LimitExceeded { what, limit } => {
+                args.push(("what", i18n::lookup(what, locale).to_string()));
+                args.push(("limit", limit.to_string()));
+            }
+            Error::HeaderMismatch { expected, found } => {
+                args.push(("expected", expected.to_string()));
+                args.push(("found", found.to_string()));
+            }
+            Error::ColumnNotFound { column } | Error::DuplicateField { column, .. } => args.push(("name", column.clone())),
+            Error::InvalidExpression { column, reason } => {
+                args.push(("name", column.clone()));
+                args.push(("reason", reason.render(locale)));
+            }
+            Error::BatchFailed { failed, total } => {
+                args.push(("failed", failed.to_string()));
+                args.push(("total", total.to_string()));
+            }
+            _ => {}
+        }
+        i18n::render(i18n::lookup(key, locale), &args)
+    }
+
+    /// 按IO错误的类型区分不存在、没有权限和其他读写失败
+    pub(crate) fn io(path: &Path, error: io::Error) -> Error {
+        let path = path.display().to_string();
+        match error.kind() {
+            io::ErrorKind::NotFound => Error::NotFound { path },
+            io::ErrorKind::PermissionDenied => Error::PermissionDenied { path },
+            _ => Error::Io { path: Some(path), reason: error.to_string() },
+        }
+    }
+
+    pub(crate) fn invalid_params(reason: impl Into<Message>) -> Error {
+        Error::InvalidParams { reason: reason.into() }
+    }
+
+    /// 把相对某段数据开头的出错位置换算为整个文件中的位置，`lines` 和 `bytes` 为这段数据之前的行数和字节数
+    pub(crate) fn shifted(self, lines: u64, bytes: u64) -> Error {
+        match self {
+            Error::Parse { row, byte, reason } => Error::Parse {
+                row: row.map(|r| r + lines),
+                byte: byte.map(|b| b + bytes),
+                reason,
+            },
+            Error::FieldCount { row, byte, expected, found } => Error::FieldCount {
+                row: row + lines,
+                byte: byte + bytes,
+                expected,
+                found,
+            },
+            Error::Encoding { row, column, byte } => Error::Encoding {
+                row: row.map(|r| r + lines),
+                column,
+                byte: byte.map(|b| b + bytes),
+            },
+            other => other,
+        }
+    }
+
+    /// 是否为某一行的格式错误（字段数不一致、字段同名、记录无效、编码错误），跳过该行后可以继续读取
+    pub(crate) fn is_row_error(&self) -> bool {
+        matches!(
+            self,
+            Error::FieldCount { .. }
+                | Error::DuplicateField { .. }
+                | Error::InvalidRecord { .. }
+                | Error::Encoding { row: Some(_), .. }
+        )
+    }
+
+    fn path(&self) -> Option<&str> {
+        match self {
+            Error::Io { path, .. } => path.as_deref(),
+            Error::PermissionDenied { path }
+            | Error::NotFound { path }
+            | Error::OutputExists { path }
+            | Error::DuplicateInput { path } => Some(path),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn row(&self) -> Option<u64> {
+        match *self {
+            Error::Parse { row, .. } | Error::Encoding { row, .. } => row,
+            Error::FieldCount { row, .. } | Error::DuplicateField { row, .. } | Error::InvalidRecord { row, .. } => Some(row),
+            _ => None,
+        }
+    }
+
+    fn column(&self) -> Option<u64> {
+        match *self {
+            Error::Encoding { column, .. } => column,
+            _ => None,
+        }
+    }
+
+    pub(crate) fn byte(&self) -> Option<u64> {
+        match *self {
+            Error::Parse { byte, .. } | Error::Encoding { byte, .. } => byte,
+            Error::FieldCount { byte, .. } => Some(byte),
+            _ => None,
+        }
+    }
+}
+
+/// 按系统语言显示，见 [`Locale::system`]
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message(Locale::system()))
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Serialize for Error {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Body<'a> {
+            code: &'static str,
+            message: String,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            path: Option<&'a str>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            row: Option<u64>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            column: Option<u64>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            byte: Option<u64>,
+        }
+
+        Body {
+            code: self.code(),
+            message: self.message(i18n::serialize_locale()),
+            path: self.path(),
+            row: self.row(),
+            column: self.column(),
+            byte: self.byte(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl From<csv::Error> for Error {
+    fn from(error: csv::Error) -> Error {
+        let row = error.position().map(|p| p.line());
+        let byte = error.position().map(|p| p.byte());
+        let reason = error.to_string();
+        match error.into_kind() {
+            csv::ErrorKind::Io(e) => Error::Io { path: None, reason: e.to_string() },
+            csv::ErrorKind::Utf8 { err, .. } => Error::Encoding {
+                row,
+                column: Some(err.field() as u64 + 1),
+                byte,
+            },
+            csv::ErrorKind::UnequalLengths { pos, expected_len, len } => Error::FieldCount {
+                row: pos.as_ref().map_or(0, |p| p.line()),
+                byte: pos.as_ref().map_or(0, |p| p.byte()),
+                expected: expected_len,
+                found: len,
+            },
+            _ => Error::Parse { row, byte, reason: reason.into() },
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Error {
+        Error::Io { path: None, reason: error.to_string() }
+    }
+}
+
+impl From<String> for Error {
+    fn from(reason: String) -> Error {
+        Error::Other { reason: reason.into() }
+    }
+}
+
+impl From<Message> for Error {
+    fn from(reason: Message) -> Error {
+        Error::Other { reason }
+    }
+}
+
+impl From<&str> for Error {
+    fn from(reason: &str) -> Error {
+        Error::Other { reason: reason.into() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::Localized;
+
+    fn serialize(error: Error, locale: Locale) -> serde_json::Value {
+        serde_json::to_value(Localized::new(error, locale)).unwrap()
+    }
+
+    #[test]
+    fn serializes_code_message_and_location() {
+        assert_eq!(
+            serialize(Error::NotFound { path: "in.csv".into() }, Locale::EnUs),
+            json!({ "code": "not_found", "message": "File not found: in.csv", "path": "in.csv" })
+        );
+        assert_eq!(
+            serialize(Error::FieldCount { row: 12, byte: 340, expected: 3, found: 4 }, Locale::EnUs),
+            json!({
+                "code": "field_count",
+                "message": "Line 12 has 4 fields, but previous lines have 3",
+                "row": 12,
+                "byte": 340,
+            })
+        );
+        assert_eq!(
+            serialize(Error::Encoding { row: Some(7), column: Some(2), byte: Some(99) }, Locale::EnUs),
+            json!({
+                "code": "encoding",
+                "message": "The input file is not UTF-8 encoded (line 7, column 2)",
+                "row": 7,
+                "column": 2,
+                "byte": 99,
+            })
+        );
+        assert_eq!(
+            serialize(Error::Io { path: None, reason: "disk full".into() }, Locale::EnUs),
+            json!({ "code": "io", "message": "Failed to read or write file: disk full" })
+        );
+        assert_eq!(
+            serialize(Error::EmptyInput, Locale::ZhCn),
+            json!({ "code": "empty_input", "message": "输入文件为空" })
+        );
+        // 嵌套的说明按同一语言翻译
+        let error = Error::InvalidRecord { row: 3, reason: Message::new("column_not_found").arg("name", "id") };
+        assert_eq!(
+            serialize(error, Locale::EnUs),
+            json!({ "code": "invalid_record", "message": "Record 3 is invalid: Column not found: id", "row": 3 })
+        );
+    }
+}
@@ -0,0 +1,823 @@
+use std::cmp::Ordering;
+use std::iter::Peekable;
+use std::path::Path;
+use std::str::CharIndices;
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime};
+use csv::StringRecord;
+use regex::Regex;
+
+use crate::i18n::Message;
+use crate::projection::ColumnRef;
+use crate::{Error, SplitParams};
+
+/// 不指定格式时 `date()` 和日期比较尝试的格式
+const DATE_FORMATS: &[&str] = &["%Y-%m-%d", "%Y/%m/%d", "%Y%m%d"];
+const DATETIME_FORMATS: &[&str] = &["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y/%m/%d %H:%M:%S"];
+
+/// 表达式求值时的一行数据
+pub(crate) struct Row<'a> {
+    pub(crate) record: &'a StringRecord,
+    /// 在原文件中的数据行号（从1开始，不含标题行）
+    pub(crate) row_number: usize,
+    /// 原文件名（含扩展名）
+    pub(crate) file_name: &'a str,
+}
+
+/// 原文件名，供 `file_name()` 使用
+pub(crate) fn source_file_name(params: &SplitParams) -> String {
+    Path::new(&params.input_path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// 表达式是否用到了 `row_number()`；原文件行号只有按顺序读取时才知道，用到时不能按多线程分块
+pub(crate) fn uses_row_number(expression: &str) -> bool {
+    let Ok(tokens) = tokenize(expression, &Error::invalid_params) else {
+        return false;
+    };
+    tokens.windows(2).any(|pair| {
+        matches!(&pair[0].1, Token::Ident(name) if name.eq_ignore_ascii_case("row_number")) && pair[1].1 == Token::LParen
+    })
+}
+
+/// 解析条件表达式（筛选条件），`invalid` 把语法错误的原因转换为对应的错误
+pub(crate) fn parse_condition(
+    expression: &str,
+    headers: &StringRecord,
+    invalid: &dyn Fn(Message) -> Error,
+) -> Result<Condition, Error> {
+    let mut parser = Parser { tokens: tokenize(expression, invalid)?, next: 0, headers, invalid };
+    let condition = parser.or()?;
+    parser.finish()?;
+    Ok(condition)
+}
+
+/// 解析取值表达式（计算列）
+pub(crate) fn parse_value(
+    expression: &str,
+    headers: &StringRecord,
+    invalid: &dyn Fn(Message) -> Error,
+) -> Result<Expr, Error> {
+    let mut parser = Parser { tokens: tokenize(expression, invalid)?, next: 0, headers, invalid };
+    let value = parser.value()?;
+    parser.finish()?;
+    Ok(value)
+}
+
+/// 条件表达式
+pub(crate) enum Condition {
+    Or(Box<Condition>, Box<Condition>),
+    And(Box<Condition>, Box<Condition>),
+    Not(Box<Condition>),
+    Compare(Expr, CompareOp, Expr),
+    Matches(Expr, Regex),
+    In(Expr, Vec<Expr>),
+    IsNull(Expr),
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// 取值表达式
+pub(crate) enum Expr {
+    Column(usize),
+    Text(String),
+    Number(f64),
+    Negate(Box<Expr>),
+    Arithmetic(ArithmeticOp, Box<Expr>, Box<Expr>),
+    Call(Function, Vec<Expr>),
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum ArithmeticOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum Function {
+    Number,
+    Text,
+    Date,
+    Concat,
+    Substr,
+    Upper,
+    Lower,
+    Trim,
+    Year,
+    Month,
+    Day,
+    FormatDate,
+    RowNumber,
+    FileName,
+}
+
+impl Function {
+    /// 函数名和允许的参数个数范围
+    fn lookup(name: &str) -> Option<(Function, usize, usize)> {
+        let function = match name.to_ascii_lowercase().as_str() {
+            "number" => (Function::Number, 1, 1),
+            "text" => (Function::Text, 1, 1),
+            "date" => (Function::Date, 1, 2),
+            "concat" => (Function::Concat, 1, usize::MAX),
+            "substr" => (Function::Substr, 2, 3),
+            "upper" => (Function::Upper, 1, 1),
+            "lower" => (Function::Lower, 1, 1),
+            "trim" => (Function::Trim, 1, 1),
+            "year" => (Function::Year, 1, 1),
+            "month" => (Function::Month, 1, 1),
+            "day" => (Function::Day, 1, 1),
+            "format_date" => (Function::FormatDate, 2, 2),
+            "row_number" => (Function::RowNumber, 0, 0),
+            "file_name" => (Function::FileName, 0, 0),
+            _ => return None,
+        };
+        Some(function)
+    }
+}
+
+/// 求值时的值，文本借用自记录或表达式中的字面量
+pub(crate) enum Value<'a> {
+    Null,
+    Text(&'a str),
+    OwnedText(String),
+    Number(f64),
+    Date(NaiveDateTime),
+}
+
+impl Condition {
+    pub(crate) fn eval(&self, row: &Row) -> bool {
+        match self {
+            Condition::Or(left, right) => left.eval(row) || right.eval(row),
+            Condition::And(left, right) => left.eval(row) && right.eval(row),
+            Condition::Not(inner) => !inner.eval(row),
+            Condition::Compare(left, op, right) => {
+                let ordering = compare(&left.eval(row), &right.eval(row));
+                match (op, ordering) {
+                    (_, None) => false,
+                    (CompareOp::Eq, Some(o)) => o == Ordering::Equal,
+                    (CompareOp::Ne, Some(o)) => o != Ordering::Equal,
+                    (CompareOp::Lt, Some(o)) => o == Ordering::Less,
+                    (CompareOp::Le, Some(o)) => o != Ordering::Greater,
+                    (CompareOp::Gt, Some(o)) => o == Ordering::Greater,
+                    (CompareOp::Ge, Some(o)) => o != Ordering::Less,
+                }
+            }
+            Condition::Matches(expr, regex) => match expr.eval(row) {
+                Value::Null => false,
+                value => regex.is_match(&value.to_text()),
+            },
+            Condition::In(expr, list) => {
+                let value = expr.eval(row);
+                list.iter().any(|item| compare(&value, &item.eval(row)) == Some(Ordering::Equal))
+            }
+            Condition::IsNull(expr) => matches!(expr.eval(row), Value::Null),
+        }
+    }
+}
+
+impl Expr {
+    pub(crate) fn eval<'a>(&'a self, row: &Row<'a>) -> Value<'a> {
+        match self {
+            Expr::Column(position) => match row.record.get(*position) {
+                None | Some("") => Value::Null,
+                Some(text) => Value::Text(text),
+            },
+            Expr::Text(text) => Value::Text(text),
+            Expr::Number(number) => Value::Number(*number),
+            Expr::Negate(inner) => inner.eval(row).to_number().map_or(Value::Null, |n| Value::Number(-n)),
+            Expr::Arithmetic(op, left, right) => {
+                let (Some(left), Some(right)) = (left.eval(row).to_number(), right.eval(row).to_number()) else {
+                    return Value::Null;
+                };
+                let result = match op {
+                    ArithmeticOp::Add => left + right,
+                    ArithmeticOp::Subtract => left - right,
+                    ArithmeticOp::Multiply => left * right,
+                    ArithmeticOp::Divide => left / right,
+                    ArithmeticOp::Remainder => left % right,
+                };
+                // 除以0等得到的非有限值按空值处理
+                if result.is_finite() { Value::Number(result) } else { Value::Null }
+            }
+            Expr::Call(function, args) => call(*function, args, row),
+        }
+    }
+}
+
+fn call<'a>(function: Function, args: &'a [Expr], row: &Row<'a>) -> Value<'a> {
+    let arg = |i: usize| args.get(i).map_or(Value::Null, |a| a.eval(row));
+    match function {
+        Function::Number => arg(0).to_number().map_or(Value::Null, Value::Number),
+        Function::Text => text_value(arg(0).to_text()),
+        Function::Date => {
+            let format = args.get(1).map(|_| arg(1).to_text());
+            arg(0).to_date(format.as_deref()).map_or(Value::Null, Value::Date)
+        }
+        Function::Concat => text_value(args.iter().map(|a| a.eval(row).to_text()).collect()),
+        Function::Substr => {
+            let text = arg(0).to_text();
+            let (Some(start), length) = (arg(1).to_number(), args.get(2).map(|_| arg(2).to_number())) else {
+                return Value::Null;
+            };
+            // 起始位置从1开始，按字符计
+            let skip = (start.max(1.0) as usize) - 1;
+            let chars = text.chars().skip(skip);
+            text_value(match length {
+                None => chars.collect(),
+                Some(Some(length)) => chars.take(length.max(0.0) as usize).collect(),
+                Some(None) => return Value::Null,
+            })
+        }
+        Function::Upper => text_value(arg(0).to_text().to_uppercase()),
+        Function::Lower => text_value(arg(0).to_text().to_lowercase()),
+        Function::Trim => text_value(arg(0).to_text().trim().to_string()),
+        Function::Year | Function::Month | Function::Day => match arg(0).to_date(None) {
+            Some(date) => Value::Number(match function {
+                Function::Year => date.year() as f64,
+                Function::Month => date.month() as f64,
+                _ => date.day() as f64,
+            }),
+            None => Value::Null,
+        },
+        Function::FormatDate => {
+            let format = arg(1).to_text();
+            match arg(0).to_date(None) {
+                Some(date) if StrftimeItems::new(&format).all(|item| !matches!(item, Item::Error)) => {
+                    text_value(date.format(&format).to_string())
+                }
+                _ => Value::Null,
+            }
+        }
+        Function::RowNumber => Value::Number(row.row_number as f64),
+        Function::FileName => Value::Text(row.file_name),
+    }
+}
+
+/// 空文本按空值处理，与空字段一致
+fn text_value<'a>(text: String) -> Value<'a> {
+    if text.is_empty() { Value::Null } else { Value::OwnedText(text) }
+}
+
+impl Value<'_> {
+    pub(crate) fn to_text(&self) -> String {
+        match self {
+            Value::Null => String::new(),
+            Value::Text(text) => text.to_string(),
+            Value::OwnedText(text) => text.clone(),
+            Value::Number(number) => number.to_string(),
+            Value::Date(date) if date.time() == NaiveTime::MIN => date.format("%Y-%m-%d").to_string(),
+            Value::Date(date) => date.format("%Y-%m-%d %H:%M:%S").to_string(),
+        }
+    }
+
+    fn as_text(&self) -> Option<&str> {
+        match self {
+            Value::Text(text) => Some(text),
+            Value::OwnedText(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    fn to_number(&self) -> Option<f64> {
+        match self {
+            Value::Number(number) => Some(*number),
+            _ => self.as_text().and_then(parse_number),
+        }
+    }
+
+    fn to_date(&self, format: Option<&str>) -> Option<NaiveDateTime> {
+        if let Value::Date(date) = self {
+            return Some(*date);
+        }
+        parse_date(self.as_text()?, format)
+    }
+}
+
+/// 把文本转换为数字，与表达式中的 `number()` 相同
+pub(crate) fn parse_number(text: &str) -> Option<f64> {
+    text.trim().parse::<f64>().ok().filter(|n| n.is_finite())
+}
+
+/// 把文本转换为日期时间，与表达式中的 `date()` 相同；没有指定格式时依次尝试常见的日期时间和日期格式
+pub(crate) fn parse_date(text: &str, format: Option<&str>) -> Option<NaiveDateTime> {
+    let text = text.trim();
+    let (datetime_formats, date_formats) = match format {
+        Some(ref format) => (std::slice::from_ref(format), std::slice::from_ref(format)),
+        None => (DATETIME_FORMATS, DATE_FORMATS),
+    };
+    datetime_formats
+        .iter()
+        .find_map(|f| NaiveDateTime::parse_from_str(text, f).ok())
+        .or_else(|| {
+            date_formats
+                .iter()
+                .find_map(|f| NaiveDate::parse_from_str(text, f).ok())
+                .map(|date| date.and_time(NaiveTime::MIN))
+        })
+}
+
+/// 比较两个值，有一方为空值或无法转换为对方的类型时返回 `None`
+fn compare(left: &Value, right: &Value) -> Option<Ordering> {
+    match (left, right) {
+        (Value::Null, _) | (_, Value::Null) => None,
+        (Value::Number(_), _) | (_, Value::Number(_)) => left.to_number()?.partial_cmp(&right.to_number()?),
+        (Value::Date(_), _) | (_, Value::Date(_)) => Some(left.to_date(None)?.cmp(&right.to_date(None)?)),
+        _ => Some(left.as_text()?.cmp(right.as_text()?)),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// 列名、函数名或关键字
+    Ident(String),
+    /// 反引号括起来的列名
+    Quoted(String),
+    Text(String),
+    Number(f64),
+    Op(&'static str),
+    LParen,
+    RParen,
+    Comma,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Ident(name) => write!(f, "{}", name),
+            Token::Quoted(name) => write!(f, "`{}`", name),
+            Token::Text(text) => write!(f, "'{}'", text),
+            Token::Number(number) => write!(f, "{}", number),
+            Token::Op(op) => f.write_str(op),
+            Token::LParen => f.write_str("("),
+            Token::RParen => f.write_str(")"),
+            Token::Comma => f.write_str(","),
+        }
+    }
+}
+
+const COMPARE_OPS: &[&str] = &["=", "==", "!=", "<>", "<", "<=", ">", ">="];
+
+/// 拆分为 (字符位置, 记号)
+fn tokenize(expression: &str, invalid: &dyn Fn(Message) -> Error) -> Result<Vec<(usize, Token)>, Error> {
+    let invalid_at = |position: usize, reason: Message| invalid(at(position, reason));
+    let mut tokens = Vec::new();
+    let mut chars = expression.char_indices().peekable();
+
+    while let Some(&(start, c)) = chars.peek() {
+        let position = expression[..start].chars().count();
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let token = match c {
+            '(' | ')' | ',' => {
+                chars.next();
+                match c {
+                    '(' => Token::LParen,
+                    ')' => Token::RParen,
+                    _ => Token::Comma,
+                }
+            }
+            '\'' | '"' | '`' => {
+                chars.next();
+                let text = quoted(&mut chars, c).ok_or_else(|| invalid_at(position, Message::new("expr.unclosed_quote").arg("quote", c.to_string())))?;
+                if c == '`' { Token::Quoted(text) } else { Token::Text(text) }
+            }
+            '+' | '-' | '*' | '/' | '%' => {
+                chars.next();
+                Token::Op(match c {
+                    '+' => "+",
+                    '-' => "-",
+                    '*' => "*",
+                    '/' => "/",
+                    _ => "%",
+                })
+            }
+            '=' | '!' | '<' | '>' => {
+                chars.next();
+                let next = chars.peek().map(|&(_, c)| c);
+                let op = match (c, next) {
+                    ('=', Some('=')) => "==",
+                    ('!', Some('=')) => "!=",
+                    ('<', Some('=')) => "<=",
+                    ('<', Some('>')) => "<>",
+                    ('>', Some('=')) => ">=",
+                    ('=', _) => "=",
+                    ('<', _) => "<",
+                    ('>', _) => ">",
+                    _ => return Err(invalid_at(position, Message::new("expr.bang_without_eq"))),
+                };
+                if op.len() == 2 {
+                    chars.next();
+                }
+                Token::Op(op)
+            }
+            c if c.is_ascii_digit() || c == '.' => {
+                let mut text = String::new();
+                while let Some(&(_, c)) = chars.peek() {
+                    // 指数部分的正负号属于数字，其他位置的是运算符
+                    let exponent_sign = matches!(c, '+' | '-') && text.ends_with(['e', 'E']);
+                    if !(c.is_ascii_alphanumeric() || c == '.' || exponent_sign) {
+                        break;
+                    }
+                    text.push(c);
+                    chars.next();
+                }
+                let number = text.parse::<f64>().map_err(|_| invalid_at(position, Message::new("expr.invalid_number").arg("value", text.as_str())))?;
+                Token::Number(number)
+            }
+            c if c.is_alphanumeric() || c == '_' => Token::Ident(take_while(&mut chars, |c| c.is_alphanumeric() || c == '_')),
+            c => return Err(invalid_at(position, Message::new("expr.unknown_char").arg("value", c.to_string()))),
+        };
+        tokens.push((position, token));
+    }
+    Ok(tokens)
+}
+
+/// 读取到结束引号为止的文本，连续两个引号表示引号本身
+fn quoted(chars: &mut Peekable<CharIndices>, quote: char) -> Option<String> {
+    let mut text = String::new();
+    while let Some((_, c)) = chars.next() {
+        if c == quote {
+            if chars.peek().map(|&(_, c)| c) != Some(quote) {
+                return Some(text);
+            }
+            chars.next();
+        }
+        text.push(c);
+    }
+    None
+}
+
+fn take_while(chars: &mut Peekable<CharIndices>, accept: impl Fn(char) -> bool) -> String {
+    let mut text = String::new();
+    while let Some(&(_, c)) = chars.peek() {
+        if !accept(c) {
+            break;
+        }
+        text.push(c);
+        chars.next();
+    }
+    text
+}
+
+/// 递归下降解析
+///
+/// 条件的优先级从低到高为 `or`、`and`、`not`、比较；取值的优先级从低到高为 `+ -`、`* / %`、负号。
+struct Parser<'a> {
+    tokens: Vec<(usize, Token)>,
+    /// 下一个记号的下标，括号有歧义时回退重新解析
+    next: usize,
+    headers: &'a StringRecord,
+    invalid: &'a dyn Fn(Message) -> Error,
+}
+
+impl Parser<'_> {
+    fn or(&mut self) -> Result<Condition, Error> {
+        let mut left = self.and()?;
+        while self.keyword("or") {
+            left = Condition::Or(Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Condition, Error> {
+        let mut left = self.not()?;
+        while self.keyword("and") {
+            left = Condition::And(Box::new(left), Box::new(self.not()?));
+        }
+        Ok(left)
+    }
+
+    fn not(&mut self) -> Result<Condition, Error> {
+        if self.keyword("not") {
+            return Ok(Condition::Not(Box::new(self.not()?)));
+        }
+        self.predicate()
+    }
+
+    fn predicate(&mut self) -> Result<Condition, Error> {
+        // 括号内可能是完整的条件，也可能是取值（如 `(a + b) > 1`）：先按条件解析，后面还有运算符时回退按取值解析
+        if self.peek() == Some(&Token::LParen) {
+            let start = self.next;
+            self.next += 1;
+            if let Ok(inner) = self.or().and_then(|inner| self.expect(Token::RParen).map(|_| inner)) {
+                let continues = match self.peek() {
+                    Some(Token::Op(_)) => true,
+                    Some(Token::Ident(name)) => ["is", "in", "not", "matches"].iter().any(|k| name.eq_ignore_ascii_case(k)),
+                    _ => false,
+                };
+                if !continues {
+                    return Ok(inner);
+                }
+            }
+            self.next = start;
+        }
+
+        let left = self.value()?;
+        if self.keyword("is") {
+            let negated = self.keyword("not");
+            if !self.keyword("null") {
+                return Err(self.unexpected(Message::new("expr.expected_null")));
+            }
+            let condition = Condition::IsNull(left);
+            return Ok(if negated { Condition::Not(Box::new(condition)) } else { condition });
+        }
+        if self.keyword("matches") {
+            let (position, pattern) = match self.tokens.get(self.next) {
+                Some((position, Token::Text(pattern))) => (*position, pattern.clone()),
+                _ => return Err(self.unexpected(Message::new("expr.expected_regex"))),
+            };
+            self.next += 1;
+            let regex = Regex::new(&pattern).map_err(|e| self.error_at(position, Message::new("expr.invalid_regex").arg("reason", e.to_string())))?;
+            return Ok(Condition::Matches(left, regex));
+        }
+        let negated = self.keyword("not");
+        if self.keyword("in") {
+            self.expect(Token::LParen)?;
+            let list = self.arguments()?;
+            let condition = Condition::In(left, list);
+            return Ok(if negated { Condition::Not(Box::new(condition)) } else { condition });
+        }
+        if negated {
+            return Err(self.unexpected(Message::new("expr.expected_in")));
+        }
+
+        let op = match self.peek() {
+            Some(Token::Op(op)) if COMPARE_OPS.contains(op) => match *op {
+                "=" | "==" => CompareOp::Eq,
+                "!=" | "<>" => CompareOp::Ne,
+                "<" => CompareOp::Lt,
+                "<=" => CompareOp::Le,
+                ">" => CompareOp::Gt,
+                _ => CompareOp::Ge,
+            },
+            _ => return Err(self.unexpected(Message::new("expr.expected_operator"))),
+        };
+        self.next += 1;
+        Ok(Condition::Compare(left, op, self.value()?))
+    }
+
+    fn value(&mut self) -> Result<Expr, Error> {
+        let mut left = self.term()?;
+        while let Some(op) = self.arithmetic_op(&["+", "-"]) {
+            left = Expr::Arithmetic(op, Box::new(left), Box::new(self.term()?));
+        }
+        Ok(left)
+    }
+
+    fn term(&mut self) -> Result<Expr, Error> {
+        let mut left = self.unary()?;
+        while let Some(op) = self.arithmetic_op(&["*", "/", "%"]) {
+            left = Expr::Arithmetic(op, Box::new(left), Box::new(self.unary()?));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, Error> {
+        if self.peek() == Some(&Token::Op("-")) {
+            self.next += 1;
+            return Ok(match self.unary()? {
+                Expr::Number(number) => Expr::Number(-number),
+                inner => Expr::Negate(Box::new(inner)),
+            });
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, Error> {
+        let Some((position, token)) = self.tokens.get(self.next).cloned() else {
+            return Err(self.unexpected(Message::new("expr.expected_value")));
+        };
+        self.next += 1;
+        match token {
+            Token::Text(text) => Ok(Expr::Text(text)),
+            Token::Number(number) => Ok(Expr::Number(number)),
+            Token::Quoted(name) => self.column(name),
+            Token::LParen => {
+                let inner = self.value()?;
+                self.expect(Token::RParen)?;
+                Ok(inner)
+            }
+            Token::Ident(name) => {
+                if self.peek() != Some(&Token::LParen) {
+                    return self.column(name);
+                }
+                self.next += 1;
+                let (function, min, max) =
+                    Function::lookup(&name).ok_or_else(|| self.error_at(position, Message::new("expr.unknown_function").arg("name", name.as_str())))?;
+                let args = if self.peek() == Some(&Token::RParen) {
+                    self.next += 1;
+                    Vec::new()
+                } else {
+                    self.arguments()?
+                };
+                if args.len() < min || args.len() > max {
+                    return Err(self.error_at(position, Message::new("expr.argument_count").arg("name", name.as_str())));
+                }
+                Ok(Expr::Call(function, args))
+            }
+            _ => {
+                self.next -= 1;
+                Err(self.unexpected(Message::new("expr.expected_value")))
+            }
+        }
+    }
+
+    /// 逗号分隔的取值，读到右括号为止
+    fn arguments(&mut self) -> Result<Vec<Expr>, Error> {
+        let mut list = vec![self.value()?];
+        while self.peek() == Some(&Token::Comma) {
+            self.next += 1;
+            list.push(self.value()?);
+        }
+        self.expect(Token::RParen)?;
+        Ok(list)
+    }
+
+    fn arithmetic_op(&mut self, ops: &[&str]) -> Option<ArithmeticOp> {
+        let op = match self.peek() {
+            Some(Token::Op(op)) if ops.contains(op) => *op,
+            _ => return None,
+        };
+        self.next += 1;
+        Some(match op {
+            "+" => ArithmeticOp::Add,
+            "-" => ArithmeticOp::Subtract,
+            "*" => ArithmeticOp::Multiply,
+            "/" => ArithmeticOp::Divide,
+            _ => ArithmeticOp::Remainder,
+        })
+    }
+
+    fn column(&self, name: String) -> Result<Expr, Error> {
+        Ok(Expr::Column(ColumnRef::Name(name).position(self.headers)?))
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next).map(|(_, token)| token)
+    }
+
+    /// 下一个记号是指定的关键字时读取它
+    fn keyword(&mut self, keyword: &str) -> bool {
+        let matched = matches!(self.peek(), Some(Token::Ident(name)) if name.eq_ignore_ascii_case(keyword));
+        if matched {
+            self.next += 1;
+        }
+        matched
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), Error> {
+        if self.peek() == Some(&expected) {
+            self.next += 1;
+            Ok(())
+        } else {
+            Err(self.unexpected(Message::new("expr.expected_token").arg("token", expected.to_string())))
+        }
+    }
+
+    /// 表达式应当已经结束
+    fn finish(&self) -> Result<(), Error> {
+        match self.tokens.get(self.next) {
+            Some((position, token)) => Err(self.error_at(*position, Message::new("expr.extra_token").arg("token", token.to_string()))),
+            None => Ok(()),
+        }
+    }
+
+    fn error_at(&self, position: usize, reason: Message) -> Error {
+        (self.invalid)(at(position, reason))
+    }
+
+    /// 下一个记号不是期望的内容，`expected` 说明期望的是什么
+    fn unexpected(&self, expected: Message) -> Error {
+        match self.tokens.get(self.next) {
+            Some((position, token)) => self.error_at(
+                *position,
+                Message::new("expr.found").arg("expected", expected).arg("token", token.to_string()),
+            ),
+            None => (self.invalid)(Message::new("expr.incomplete").arg("expected", expected)),
+        }
+    }
+}
+
+/// 语法错误的原因加上出错的位置（从1开始的字符位置）
+fn at(position: usize, reason: Message) -> Message {
+    Message::new("expr.at").arg("position", (position + 1).to_string()).arg("reason", reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Locale;
+
+    const HEADERS: &[&str] = &["name", "amount", "qty", "created", "status"];
+    const ROW: &[&str] = &["A12", "50", "3", "2024-01-05", "paid"];
+
+    fn row_eval<T>(record: &[&str], eval: impl FnOnce(&Row) -> T) -> T {
+        let record = StringRecord::from(record.to_vec());
+        eval(&Row { record: &record, row_number: 7, file_name: "orders.csv" })
+    }
+
+    fn cond_on(expression: &str, record: &[&str]) -> bool {
+        let condition = parse_condition(expression, &StringRecord::from(HEADERS.to_vec()), &|reason| Error::InvalidFilter { reason })
+            .unwrap_or_else(|e| panic!("{}: {}", expression, e));
+        row_eval(record, |row| condition.eval(row))
+    }
+
+    fn cond(expression: &str) -> bool {
+        cond_on(expression, ROW)
+    }
+
+    fn value(expression: &str) -> String {
+        let expr = parse_value(expression, &StringRecord::from(HEADERS.to_vec()), &|reason| Error::InvalidFilter { reason })
+            .unwrap_or_else(|e| panic!("{}: {}", expression, e));
+        row_eval(ROW, |row| expr.eval(row).to_text())
+    }
+
+    fn error(expression: &str) -> String {
+        match parse_condition(expression, &StringRecord::from(HEADERS.to_vec()), &|reason| Error::InvalidFilter { reason }) {
+            Ok(_) => panic!("{} 应当解析失败", expression),
+            Err(e) => e.message(Locale::EnUs),
+        }
+    }
+
+    #[test]
+    fn arithmetic_and_logic_follow_precedence() {
+        assert_eq!(value("1 + 2 * 3"), "7");
+        assert_eq!(value("(1 + 2) * 3"), "9");
+        assert_eq!(value("-2 * 3 + 10 % 4"), "-4");
+        assert_eq!(value("amount - qty - 1"), "46");
+        assert_eq!(value("amount / 0"), "");
+        assert!(!cond("status = 'x' or status = 'paid' and amount > 100"));
+        assert!(cond("status = 'paid' or status = 'x' and amount > 100"));
+        assert!(cond("not status = 'x' and amount = 50"));
+        assert!(cond("(amount + qty) * 2 > 100"));
+        assert!(cond("(status = 'x' or qty = 3) and amount >= 50"));
+    }
+
+    #[test]
+    fn comparisons_convert_between_types() {
+        assert!(cond("amount > 9"));
+        assert!(!cond("amount > '9'"));
+        assert!(cond("created > '2024-01-01'"));
+        assert!(cond("date(created) >= date('2024-01-01')"));
+        assert!(!cond("number(qty) != 'three'"));
+        assert!(!cond_on("amount < 100", &["A12", "", "3", "", "paid"]));
+        assert!(cond_on("amount is null and created is not null", &["A12", "", "3", "2024-01-05", "paid"]));
+    }
+
+    #[test]
+    fn regex_and_in_lists() {
+        assert!(cond("name matches '^A\\d+$'"));
+        assert!(!cond_on("name matches '^A\\d+$'", &["A1x", "50", "3", "", ""]));
+        assert!(!cond_on("name matches '.*'", &["", "50", "3", "", ""]));
+        assert!(cond("status in ('new', 'paid')"));
+        assert!(cond("status not in ('new', 'void')"));
+        assert!(cond("amount in (49, 50.0)"));
+        assert!(cond("qty in ('a', 1 + 2)"));
+    }
+
+    #[test]
+    fn functions_and_casts() {
+        assert_eq!(value("number(' 12.50 ')"), "12.5");
+        assert_eq!(value("text(amount) + 1"), "51");
+        assert_eq!(value("date('05/01/2024', '%d/%m/%Y')"), "2024-01-05");
+        assert_eq!(value("date('2024-01-05 08:30:00')"), "2024-01-05 08:30:00");
+        assert_eq!(value("year(created) * 100 + month(created)"), "202401");
+        assert_eq!(value("format_date(created, '%Y%m')"), "202401");
+        assert_eq!(value("concat(upper(substr(status, 1, 1)), substr(status, 2))"), "Paid");
+        assert_eq!(value("trim('  x ')"), "x");
+        assert_eq!(value("concat(file_name(), ':', row_number())"), "orders.csv:7");
+        assert!(uses_row_number("ROW_NUMBER() > 1"));
+        assert!(!uses_row_number("row_number > 1"));
+    }
+
+    #[test]
+    fn syntax_errors_report_the_position() {
+        assert!(error("amount >").ends_with("expected a column name or value, but the expression ended"), "{}", error("amount >"));
+        assert!(error("amount > > 1").contains("Near character 10: expected a column name or value, found >"));
+        assert!(error("name = 'abc").contains("Near character 8: unclosed '"));
+        assert!(error("foo(1) = 1").contains("Near character 1: unknown function foo"));
+        assert!(error("amount ! 1").contains("Near character 8: ! must be followed by ="));
+        assert!(error("name matches '['").contains("Near character 14: invalid regular expression"));
+        assert!(error("amount = 1 )").contains("Near character 12: unexpected )"));
+        assert!(error("substr(name) = 'a'").contains("Near character 1: wrong number of arguments for substr"));
+        assert!(error("status not 'a'").contains("Near character 12: expected in after not, found 'a'"));
+        let error = parse_condition("missing = 1", &StringRecord::from(HEADERS.to_vec()), &|reason| Error::InvalidFilter { reason });
+        assert_eq!(error.err().map(|e| e.code()), Some("column_not_found"));
+    }
+}
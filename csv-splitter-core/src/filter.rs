@@ -0,0 +1,37 @@
+use csv::StringRecord;
+
+use crate::expr::{parse_condition, source_file_name, Condition, Row};
+use crate::{Error, SplitParams};
+
+/// 行筛选条件，只有满足条件的行写入分块
+///
+/// 语法示例：`status = 'paid' and amount > 100 and date(created) >= date('2024-01-01')`
+/// - 列名直接书写，含空格、运算符或与关键字同名的列名用反引号括起来，如 `` `order id` ``
+/// - 字面量：`'文本'` 或 `"文本"`、数字
+/// - 比较：`=`、`!=`（`<>`）、`<`、`<=`、`>`、`>=`；文本与数字或日期比较时先把文本转换过去，转换失败视为不满足
+/// - 正则匹配：`列 matches '^A\d+$'`；列表：`列 in ('a', 'b')`、`列 not in (...)`
+/// - 空值：`列 is null`、`列 is not null`，空字段为空值，空值与任何值比较都不满足
+/// - 类型转换：`number(列)`、`text(列)`、`date(列)`、`date(列, '%d/%m/%Y')`
+/// - 逻辑：`and`、`or`、`not` 和括号，关键字不区分大小写
+///
+/// 比较的两边也可以是与计算列相同的取值表达式，如 `number(price) * qty > 1000`。
+pub(crate) struct Filter {
+    condition: Condition,
+    file_name: String,
+}
+
+impl Filter {
+    /// 没有指定筛选条件时返回 `None`，列名在这里按标题行解析为列的位置
+    pub(crate) fn new(params: &SplitParams, headers: &StringRecord) -> Result<Option<Filter>, Error> {
+        let Some(expression) = params.filter.as_deref().filter(|f| !f.trim().is_empty()) else {
+            return Ok(None);
+        };
+        let condition = parse_condition(expression, headers, &|reason| Error::InvalidFilter { reason })?;
+        Ok(Some(Filter { condition, file_name: source_file_name(params) }))
+    }
+
+    /// 记录是否满足条件，`row_number` 为在原文件中的数据行号
+    pub(crate) fn matches(&self, record: &StringRecord, row_number: usize) -> bool {
+        self.condition.eval(&Row { record, row_number, file_name: &self.file_name })
+    }
+}
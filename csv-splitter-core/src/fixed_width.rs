@@ -0,0 +1,248 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use csv::StringRecord;
+use serde::{Deserialize, Serialize};
+
+use crate::i18n::Message;
+use crate::source::RecordSource;
+use crate::Error;
+
+/// 定长文本的列布局
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FixedWidthLayout {
+    pub columns: Vec<FixedWidthColumn>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FixedWidthColumn {
+    pub name: String,
+    /// 起始位置，从1开始，按字符计数
+    pub start: usize,
+    /// 列宽（字符数）
+    pub width: usize,
+    #[serde(default)]
+    pub trim: TrimRule,
+}
+
+/// 字段两侧填充空白的去除方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrimRule {
+    None,
+    Left,
+    Right,
+    #[default]
+    Both,
+}
+
+impl TrimRule {
+    fn apply<'a>(&self, value: &'a str) -> &'a str {
+        match self {
+            TrimRule::None => value,
+            TrimRule::Left => value.trim_start(),
+            TrimRule::Right => value.trim_end(),
+            TrimRule::Both => value.trim(),
+        }
+    }
+}
+
+impl FixedWidthLayout {
+    fn validate(&self) -> Result<(), Error> {
+        let invalid = |key| Error::invalid_params(Message::new(key));
+        if self.columns.is_empty() {
+            return Err(invalid("fixed_width.no_columns"));
+        }
+        for column in &self.columns {
+            let invalid = |key| Error::invalid_params(Message::new(key).arg("name", column.name.as_str()));
+            if column.name.trim().is_empty() {
+                return Err(invalid("fixed_width.empty_name"));
+            }
+            if column.start == 0 {
+                return Err(invalid("fixed_width.zero_start"));
+            }
+            if column.width == 0 {
+                return Err(invalid("fixed_width.zero_width"));
+            }
+            if column.start.checked_add(column.width).is_none() {
+                return Err(invalid("fixed_width.too_wide"));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 定长文本输入：每行按布局截取为一条记录
+///
+/// 行长度不足时缺失的列为空值，空行会被跳过。
+pub(crate) struct FixedWidthSource {
+    reader: BufReader<File>,
+    layout: FixedWidthLayout,
+    headers: StringRecord,
+    line: String,
+    line_number: usize,
+}
+
+impl FixedWidthSource {
+    pub(crate) fn open(
+        input_path: &Path,
+        layout: &FixedWidthLayout,
+        has_header: bool,
+    ) -> Result<FixedWidthSource, Error> {
+        layout.validate()?;
+
+        let file = File::open(input_path)
+            .map_err(|e| Error::io(input_path, e))?;
+
+        let mut source = FixedWidthSource {
+            reader: BufReader::new(file),
+            layout: layout.clone(),
+            headers: StringRecord::from(
+                layout.columns.iter().map(|c| c.name.as_str()).collect::<Vec<_>>()
+            ),
+            line: String::new(),
+            line_number: 0,
+        };
+
+        // 列名以布局为准，文件自带的标题行直接跳过
+        if has_header {
+            source.next_line()?;
+        }
+
+        Ok(source)
+    }
+
+    /// 读取下一行（不含换行符），文件结束时返回 `Ok(false)`
+    fn next_line(&mut self) -> Result<bool, Error> {
+        self.line.clear();
+        let bytes_read = match self.reader.read_line(&mut self.line) {
+            Ok(bytes_read) => bytes_read,
+            // 不是UTF-8的行已被整行读走，行号同样前进，跳过该行后后续行的行号不会错位
+            Err(e) if e.kind() == std::io::ErrorKind::InvalidData => {
+                self.line_number += 1;
+                return Err(Error::Encoding { row: Some(self.line_number as u64), column: None, byte: None });
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if bytes_read == 0 {
+            return Ok(false);
+        }
+        self.line_number += 1;
+
+        // 跳过UTF-8 BOM
+        if self.line_number == 1 && self.line.starts_with('\u{feff}') {
+            self.line.drain(..'\u{feff}'.len_utf8());
+        }
+        let content_len = self.line.trim_end_matches(['\r', '\n']).len();
+        self.line.truncate(content_len);
+        Ok(true)
+    }
+}
+
+impl RecordSource for FixedWidthSource {
+    fn headers(&self) -> &StringRecord {
+        &self.headers
+    }
+
+    fn read_record(&mut self, record: &mut StringRecord) -> Result<bool, Error> {
+        loop {
+            if !self.next_line()? {
+                return Ok(false);
+            }
+            if !self.line.trim().is_empty() {
+                break;
+            }
+        }
+
+        // 预先计算每个字符的字节偏移，按字符位置截取以正确处理多字节字符
+        let offsets: Vec<usize> = self.line
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain(std::iter::once(self.line.len()))
+            .collect();
+        let char_count = offsets.len() - 1;
+
+        record.clear();
+        for column in &self.layout.columns {
+            let start = (column.start - 1).min(char_count);
+            let end = (column.start - 1 + column.width).min(char_count);
+            let value = &self.line[offsets[start]..offsets[end]];
+            record.push_field(column.trim.apply(value));
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(name: &str, start: usize, width: usize, trim: TrimRule) -> FixedWidthColumn {
+        FixedWidthColumn { name: name.into(), start, width, trim }
+    }
+
+    fn layout() -> FixedWidthLayout {
+        FixedWidthLayout {
+            columns: vec![
+                column("id", 1, 3, TrimRule::Both),
+                column("name", 4, 4, TrimRule::Right),
+                column("note", 8, 5, TrimRule::None),
+            ],
+        }
+    }
+
+    fn read_all(name: &str, content: &[u8], layout: &FixedWidthLayout, has_header: bool) -> Vec<Result<Vec<String>, Error>> {
+        let path = std::env::temp_dir().join(format!("csv-splitter-fixed-{}-{}", std::process::id(), name));
+        std::fs::write(&path, content).unwrap();
+        let mut source = FixedWidthSource::open(&path, layout, has_header).unwrap();
+        let mut rows = Vec::new();
+        let mut record = StringRecord::new();
+        loop {
+            match source.read_record(&mut record) {
+                Ok(false) => break,
+                Ok(true) => rows.push(Ok(record.iter().map(String::from).collect())),
+                Err(e) => rows.push(Err(e)),
+            }
+        }
+        std::fs::remove_file(&path).unwrap();
+        rows
+    }
+
+    #[test]
+    fn columns_are_cut_by_character_and_trimmed() {
+        let rows = read_all(
+            "cut.txt",
+            "\u{feff}ID NAMENOTE\r\n  1张三  ab   \r\n\n 22李  \n".as_bytes(),
+            &layout(),
+            true,
+        );
+        let rows: Vec<_> = rows.into_iter().map(Result::unwrap).collect();
+        assert_eq!(rows, [vec!["1", "张三", "ab   "], vec!["22", "李", ""]]);
+    }
+
+    #[test]
+    fn invalid_utf8_line_keeps_later_line_numbers() {
+        let rows = read_all("encoding.txt", b"  1abc \n  2\xff\xfe  \n  3def \n", &layout(), false);
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[1].as_ref().unwrap_err(), &Error::Encoding { row: Some(2), column: None, byte: None });
+        assert!(rows[1].as_ref().unwrap_err().is_row_error());
+        assert_eq!(rows[2].as_ref().unwrap(), &["3", "def", ""]);
+    }
+
+    #[test]
+    fn invalid_layouts_are_rejected() {
+        let cases = [
+            (Vec::new(), "fixed_width.no_columns"),
+            (vec![column(" ", 1, 1, TrimRule::Both)], "fixed_width.empty_name"),
+            (vec![column("a", 0, 1, TrimRule::Both)], "fixed_width.zero_start"),
+            (vec![column("a", 1, 0, TrimRule::Both)], "fixed_width.zero_width"),
+            (vec![column("a", usize::MAX, 1, TrimRule::Both)], "fixed_width.too_wide"),
+        ];
+        for (columns, key) in cases {
+            let error = FixedWidthLayout { columns }.validate().unwrap_err();
+            assert_eq!(error.code(), "invalid_params");
+            assert!(format!("{:?}", error).contains(key), "{:?}", error);
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use std::collections::HashSet;
+
+use csv::StringRecord;
+use serde::{Deserialize, Serialize};
+
+use crate::i18n::Message;
+use crate::naming::NameTemplate;
+use crate::projection::ColumnRef;
+use crate::source::InputFormat;
+use crate::{Error, SplitParams};
+
+/// 把某一列改名
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeaderRename {
+    /// 列名或从1开始的列序号
+    pub column: ColumnRef,
+    /// 新的列名
+    pub name: String,
+}
+
+/// 按参数确定输出的标题行：先按模板生成列名（CSV没有标题行时），再整行替换，最后逐列改名
+///
+/// 之后的灵活模式补齐值、列选择等按列名指定的参数都使用这里得到的列名。
+pub(crate) fn resolve_headers(params: &SplitParams, headers: &StringRecord) -> Result<StringRecord, Error> {
+    let mut names: Vec<String> = match params.header_template {
+        Some(ref template) if !params.has_header && params.input_format() == InputFormat::Csv => {
+            let names = generate_headers(template, headers.len())?;
+            ensure_unique(&names)?;
+            names
+        }
+        _ => headers.iter().map(str::to_string).collect(),
+    };
+
+    if !params.header_row.is_empty() {
+        if params.header_row.len() != names.len() {
+            return Err(Error::HeaderMismatch { expected: names.len(), found: params.header_row.len() });
+        }
+        ensure_unique(&params.header_row)?;
+        names = params.header_row.clone();
+    }
+
+    for rename in &params.rename_columns {
+        let position = rename.column.position(&StringRecord::from(names.clone()))?;
+        // 输入文件本身的重名列不受影响，只拒绝改名造成的重名
+        if names.iter().enumerate().any(|(i, name)| i != position && *name == rename.name) {
+            return Err(duplicate_name(&rename.name));
+        }
+        names[position] = rename.name.clone();
+    }
+
+    Ok(StringRecord::from(names))
+}
+
+/// 按模板生成 `column_count` 个列名，模板的写法与文件命名模板相同，见 [`NameTemplate::parse_column_template`]
+fn generate_headers(template: &str, column_count: usize) -> Result<Vec<String>, Error> {
+    let template = NameTemplate::parse_column_template(template)?;
+    Ok((1..=column_count).map(|index| template.render_column(index)).collect())
+}
+
+/// 模板、整行替换或改名得到的列名不能重复，否则筛选条件、列选择等按列名查找时无法区分
+fn duplicate_name(name: &str) -> Error {
+    Error::invalid_params(Message::new("header.duplicate_name").arg("name", name))
+}
+
+fn ensure_unique(names: &[String]) -> Result<(), Error> {
+    let mut seen = HashSet::new();
+    match names.iter().find(|name| !seen.insert(name.as_str())) {
+        Some(name) => Err(duplicate_name(name)),
+        None => Ok(()),
+    }
+}
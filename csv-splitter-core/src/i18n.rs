@@ -0,0 +1,553 @@
+use std::cell::Cell;
+use std::fmt;
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize, Serializer};
+
+/// 错误信息的语言
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Locale {
+    #[default]
+    #[serde(rename = "zh-CN")]
+    ZhCn,
+    #[serde(rename = "en-US")]
+    EnUs,
+}
+
+impl Locale {
+    /// 解析 `zh-CN`、`zh_CN.UTF-8`、`en`、`en-GB` 等写法，只看语言部分；不支持的语言返回 `None`
+    pub fn parse(tag: &str) -> Option<Locale> {
+        let language = tag
+            .split(['-', '_', '.', '@'])
+            .next()
+            .unwrap_or("")
+            .to_ascii_lowercase();
+        match language.as_str() {
+            "zh" => Some(Locale::ZhCn),
+            "en" => Some(Locale::EnUs),
+            _ => None,
+        }
+    }
+
+    /// 操作系统的语言，按 `LC_ALL`、`LC_MESSAGES`、`LANG` 的顺序读取
+    ///
+    /// 设置了其他语言时使用英文；都没有设置时（如Windows）使用中文。
+    pub fn system() -> Locale {
+        static SYSTEM: OnceLock<Locale> = OnceLock::new();
+        *SYSTEM.get_or_init(|| Locale::from_env(|name| std::env::var(name).ok()))
+    }
+
+    /// 按 [`Locale::system`] 的规则从环境变量中选出语言，`var` 读取指定的环境变量
+    fn from_env(var: impl Fn(&str) -> Option<String>) -> Locale {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .into_iter()
+            .filter_map(var)
+            .find(|value| !value.is_empty() && value != "C" && value != "POSIX")
+            .map(|value| Locale::parse(&value).unwrap_or(Locale::EnUs))
+            .unwrap_or_default()
+    }
+}
+
+/// 信息目录：键 -> (zh-CN, en-US)
+///
+/// 错误信息的键为错误码，同一错误码按上下文有多种说法时加后缀区分；错误原因和其他说明的键以所在模块为前缀。
+/// 模板中的 `{name}` 由上下文替换。
+const CATALOG: &[(&str, &str, &str)] = &[
+    ("io", "读写文件 {path} 失败: {reason}", "Failed to read or write {path}: {reason}"),
+    ("io.no_path", "读写文件失败: {reason}", "Failed to read or write file: {reason}"),
+    ("permission_denied", "没有读写权限: {path}", "Permission denied: {path}"),
+    ("not_found", "文件不存在: {path}", "File not found: {path}"),
+    ("unsupported_file.csv", "请选择有效的CSV文件", "Please choose a valid CSV file"),
+    ("unsupported_file.json", "请选择有效的JSON文件", "Please choose a valid JSON file"),
+    ("unsupported_file.fixed_width", "请选择有效的定长文本文件", "Please choose a valid fixed-width text file"),
+    ("empty_input", "输入文件为空", "The input file is empty"),
+    ("no_data_rows", "输入文件没有数据行", "The input file has no data rows"),
+    ("zero_rows_per_file", "每个文件的行数必须大于0", "Rows per file must be greater than 0"),
+    ("checkpoint_unsupported", "断点续传目前仅支持CSV输入", "Resuming is only supported for CSV input"),
+    ("layout_required", "定长文本输入需要提供列布局", "Fixed-width input requires a column layout"),
+    ("column_not_found", "找不到列: {name}", "Column not found: {name}"),
+    ("no_columns_selected", "列选择后没有剩下任何列", "No columns are left after column selection"),
+    (
+        "header_mismatch",
+        "替换的标题行有{found}列，与输入文件的{expected}列不一致",
+        "The replacement header has {found} columns, but the input has {expected}",
+    ),
+    ("invalid_params", "{reason}", "{reason}"),
+    ("invalid_filter", "筛选条件无效: {reason}", "Invalid filter expression: {reason}"),
+    (
+        "invalid_expression",
+        "计算列 {name} 的表达式无效: {reason}",
+        "Invalid expression for computed column {name}: {reason}",
+    ),
+    ("parse", "第{row}行解析失败: {reason}", "Failed to parse line {row}: {reason}"),
+    ("parse.no_row", "解析失败: {reason}", "Failed to parse input: {reason}"),
+    (
+        "field_count",
+        "第{row}行有{found}个字段，与前面的行（{expected}个字段）不一致",
+        "Line {row} has {found} fields, but previous lines have {expected}",
+    ),
+    (
+        "duplicate_field",
+        "第{row}条记录中有两个字段都对应列 {name}（嵌套对象展开后与带点的键同名）",
+        "Record {row} has two fields for column {name} (a nested object flattens to the same name as a dotted key)",
+    ),
+    ("invalid_record", "第{row}条记录无效: {reason}", "Record {row} is invalid: {reason}"),
+    ("encoding", "输入文件不是UTF-8编码", "The input file is not UTF-8 encoded"),
+    ("encoding.row", "输入文件不是UTF-8编码（第{row}行）", "The input file is not UTF-8 encoded (line {row})"),
+    (
+        "encoding.row_column",
+        "输入文件不是UTF-8编码（第{row}行第{column}列）",
+        "The input file is not UTF-8 encoded (line {row}, column {column})",
+    ),
+    ("limit_exceeded", "{what}超出上限 {limit}", "{what} exceeds the limit of {limit}"),
+    ("limit.excel_rows_per_file", "转换为Excel时每个文件的行数", "Rows per file when converting to Excel"),
+    ("cancelled", "任务已取消", "The task was cancelled"),
+    (
+        "output_exists",
+        "输出文件已存在: {path}，请更换输出目录或选择其他覆盖策略",
+        "Output file already exists: {path}. Choose another output directory or overwrite policy",
+    ),
+    ("checkpoint_not_found", "没有找到可以继续的分割任务", "No interrupted split was found to resume"),
+    (
+        "input_changed",
+        "输入文件在上次分割中断后已被修改，无法继续，请重新分割",
+        "The input file has changed since the split was interrupted; please split it again",
+    ),
+    ("batch_failed", "{total} 个文件中有 {failed} 个分割失败", "{failed} of {total} files failed to split"),
+    (
+        "duplicate_input",
+        "与 {path} 的文件名相同，输出文件会互相覆盖，已跳过",
+        "Skipped: same file name as {path}, the outputs would overwrite each other",
+    ),
+    ("verify_failed", "{reason}", "{reason}"),
+    ("other", "{reason}", "{reason}"),
+    (
+        "verify.mismatch",
+        "校验失败：{piece} 第{row}行（原文件第{source_row}行）{reason}",
+        "Verification failed at {piece} line {row} (source row {source_row}): {reason}",
+    ),
+    (
+        "verify.mismatch_no_source",
+        "校验失败：{piece} 第{row}行，{reason}",
+        "Verification failed at {piece} line {row}: {reason}",
+    ),
+    (
+        "verify.row_count",
+        "校验失败：原文件 {input_rows} 行，分块共 {piece_rows} 行",
+        "Verification failed: the input has {input_rows} rows but the pieces have {piece_rows}",
+    ),
+    ("verify.header_differs", "标题行与原文件不一致", "the header differs from the input"),
+    ("verify.extra_row", "分块中的数据行多于原文件", "the pieces have more data rows than the input"),
+    ("verify.row_differs", "数据行内容与原文件不一致", "the row differs from the input"),
+    ("verify.missing_row", "原文件的数据行缺失于分块中", "the input row is missing from the pieces"),
+    ("verify.no_pieces", "没有找到需要校验的分块文件", "No pieces were found to verify"),
+    ("verify.not_csv", "仅支持校验CSV分块: {path}", "Only CSV pieces can be verified: {path}"),
+    ("verify.read_header", "读取 {path} 的标题行失败: {reason}", "Failed to read the header of {path}: {reason}"),
+    ("verify.read_row", "读取 {path} 第{row}行失败: {reason}", "Failed to read line {row} of {path}: {reason}"),
+    ("split.failed", "分割失败", "The split failed"),
+    ("split.excel_failed", "将 {path} 转换为Excel失败: {reason}", "Failed to convert {path} to Excel: {reason}"),
+    ("split.worker_panicked", "分割线程异常退出", "A split worker thread exited unexpectedly"),
+    ("split.sort_with_checkpoint", "排序要读完整个文件后才开始写入分块，不能与断点续传一起使用", "Sorting reads the whole file before writing any piece and cannot be combined with checkpoints"),
+    ("split.group_with_checkpoint", "按组分割要读到下一组的第一行才能结束分块，不能与断点续传一起使用", "Group splitting must read the first row of the next group to end a piece and cannot be combined with checkpoints"),
+    ("split.max_rows_too_small", "每个分块最多的行数 {max} 不能小于每个文件的行数 {rows}", "The maximum rows per piece ({max}) cannot be less than the rows per file ({rows})"),
+    ("batch.no_inputs", "没有找到需要分割的文件", "No files were found to split"),
+    ("batch.not_run", "任务没有执行", "The job did not run"),
+    ("merge.no_inputs", "请至少选择一个要合并的文件", "Choose at least one file to merge"),
+    ("merge.union_requires_header", "按列名合并需要输入文件带有标题行", "Merging by column name requires input files with a header row"),
+    ("merge.output_is_input", "输出文件不能是输入文件之一: {path}", "The output file cannot be one of the inputs: {path}"),
+    ("merge.read_failed", "读取 {path} 失败: {reason}", "Failed to read {path}: {reason}"),
+    (
+        "merge.duplicate_column",
+        "文件 {path} 中存在重复的列 {name}（可能由别名映射导致）",
+        "Duplicate column {name} in {path} (possibly caused by an alias)",
+    ),
+    (
+        "merge.header_mismatch",
+        "文件 {path} 的标题行与 {first} 不一致，可改用按列名合并",
+        "The header of {path} differs from {first}; try merging by column name",
+    ),
+    ("manifest.invalid", "清单文件格式无效 {path}: {reason}", "Invalid manifest file {path}: {reason}"),
+    ("checkpoint.invalid", "断点文件格式无效 {path}: {reason}", "Invalid checkpoint file {path}: {reason}"),
+    (
+        "checkpoint.other_input",
+        "断点记录的输入文件是 {path}，与当前选择的文件不一致",
+        "The checkpoint was saved for {path}, not the selected file",
+    ),
+    (
+        "output.no_prefix",
+        "命名模板不以固定文本开头，无法按前缀清理输出目录",
+        "The file name template does not start with fixed text, so outputs cannot be cleaned by prefix",
+    ),
+    ("watch.preset_required", "请提供分割参数预设", "A split preset is required"),
+    ("watch.output_is_watch_dir", "输出目录不能与监视目录相同", "The output directory cannot be the watched directory"),
+    ("watch.invalid_preset", "预设文件格式无效 {path}: {reason}", "Invalid preset file {path}: {reason}"),
+    ("watch.already_running", "已经有一个监视任务在运行", "A watch task is already running"),
+    ("watch.thread_panicked", "监视线程异常退出", "The watch thread exited unexpectedly"),
+    ("watch.log.start", "开始监视", "Watching started"),
+    ("watch.log.stop", "停止监视", "Watching stopped"),
+    ("watch.log.read_failed", "无法读取监视目录: {reason}", "Cannot read the watched directory: {reason}"),
+    ("watch.log.done", "生成 {files} 个文件，已归档到 {path}", "{files} files written, archived to {path}"),
+    ("watch.log.unknown_error", "未知错误", "Unknown error"),
+    ("json_source.invalid_top_level", "JSON文件格式无效：顶层必须是对象或数组", "Invalid JSON file: the top level must be an object or an array"),
+    ("json_source.not_object", "JSON记录不是对象", "The JSON record is not an object"),
+    ("json_source.truncated_record", "JSON文件不完整：记录在文件结尾处被截断", "Incomplete JSON file: the last record is truncated"),
+    ("json_source.missing_comma", "JSON数组格式无效：元素之间缺少逗号", "Invalid JSON array: missing comma between elements"),
+    ("json_source.missing_bracket", "JSON文件不完整：缺少结尾的 ]", "Incomplete JSON file: missing the closing ]"),
+    ("fixed_width.no_columns", "定长布局至少需要一列", "A fixed-width layout needs at least one column"),
+    ("fixed_width.empty_name", "定长布局的列名不能为空", "Column names in a fixed-width layout cannot be empty"),
+    ("fixed_width.zero_start", "列 {name} 的起始位置必须从1开始", "The start of column {name} must be at least 1"),
+    ("fixed_width.zero_width", "列 {name} 的宽度必须大于0", "The width of column {name} must be greater than 0"),
+    ("fixed_width.too_wide", "列 {name} 的起始位置加宽度超出范围", "The start plus width of column {name} is out of range"),
+    ("header.only_index", "列名模板只支持 {index} 占位符: {value}", "Column name templates only support the {index} placeholder: {value}"),
+    ("header.index_required", "列名模板中需要包含 {index}: {value}", "Column name templates must contain {index}: {value}"),
+    ("header.duplicate_name", "列名 {name} 重复", "Duplicate column name {name}"),
+    ("projection.duplicate_column", "列 {name} 被选择了多次", "Column {name} is selected more than once"),
+    ("sort.zero_memory", "排序可使用的内存必须大于0", "The memory available for sorting must be greater than 0"),
+    ("computed.duplicate_name", "计算列与已有的列同名: {name}", "The computed column has the same name as an existing column: {name}"),
+    ("expr.at", "第{position}个字符附近{reason}", "Near character {position}: {reason}"),
+    ("expr.found", "{expected}，而不是 {token}", "{expected}, found {token}"),
+    ("expr.incomplete", "{expected}，表达式不完整", "{expected}, but the expression ended"),
+    ("expr.unclosed_quote", "的 {quote} 没有闭合", "unclosed {quote}"),
+    ("expr.bang_without_eq", "的 ! 后面需要 =", "! must be followed by ="),
+    ("expr.invalid_number", "的数字无效: {value}", "invalid number: {value}"),
+    ("expr.unknown_char", "有无法识别的字符 {value}", "unrecognized character {value}"),
+    ("expr.invalid_regex", "的正则表达式无效: {reason}", "invalid regular expression: {reason}"),
+    ("expr.unknown_function", "有未知的函数 {name}", "unknown function {name}"),
+    ("expr.argument_count", "的函数 {name} 的参数个数不正确", "wrong number of arguments for {name}"),
+    ("expr.extra_token", "有多余的 {token}", "unexpected {token}"),
+    ("expr.expected_null", "is 后面需要 null 或 not null", "expected null or not null after is"),
+    ("expr.expected_regex", "matches 后面需要用引号括起来的正则表达式", "expected a quoted regular expression after matches"),
+    ("expr.expected_in", "not 后面需要 in", "expected in after not"),
+    ("expr.expected_operator", "需要比较运算符、in、matches 或 is null", "expected a comparison operator, in, matches or is null"),
+    ("expr.expected_token", "需要 {token}", "expected {token}"),
+    ("expr.expected_value", "需要列名或值", "expected a column name or value"),
+    ("naming.unclosed_placeholder", "模板中的占位符没有闭合: {value}", "Unclosed placeholder in template: {value}"),
+    ("naming.unmatched_brace", "模板中存在多余的 }: {value}", "Unmatched } in template: {value}"),
+    (
+        "naming.illegal_template",
+        "命名模板包含文件名中不允许的字符: {value}",
+        "The file name template contains characters not allowed in file names: {value}",
+    ),
+    ("naming.empty_template", "命名模板不能为空", "The file name template cannot be empty"),
+    ("naming.invalid_width", "占位符 {{value}} 的宽度无效", "Invalid width in placeholder {{value}}"),
+    ("naming.unexpected_spec", "占位符 {{value}} 不支持格式参数", "Placeholder {{value}} does not take a format"),
+    ("naming.invalid_date_format", "日期格式无效: {value}", "Invalid date format: {value}"),
+    ("naming.illegal_date_format", "日期格式会生成文件名中不允许的字符: {value}", "The date format produces characters that are not allowed in file names: {value}"),
+    ("naming.unknown_placeholder", "未知的占位符: {{value}}", "Unknown placeholder: {{value}}"),
+    ("naming.empty_name", "生成的文件名为空", "The generated file name is empty"),
+    ("naming.illegal_name", "生成的文件名包含非法字符: {value}", "The generated file name contains illegal characters: {value}"),
+    ("naming.invalid_name", "生成的文件名无效: {value}", "Invalid generated file name: {value}"),
+    ("naming.trailing_dot", "文件名不能以点或空格结尾: {value}", "File names cannot end with a dot or a space: {value}"),
+    ("naming.reserved_name", "文件名是系统保留名称: {value}", "The file name is reserved by the system: {value}"),
+    (
+        "naming.duplicate_name",
+        "命名模板生成了重复的文件名: {value}，请在模板中加入 {index}",
+        "The file name template produced a duplicate name: {value}. Add {index} to the template",
+    ),
+    ("cli.error", "错误: {reason}", "Error: {reason}"),
+    ("cli.see_help", "使用 csv-splitter --help 查看用法", "Run csv-splitter --help for usage"),
+    ("cli.unknown_command", "未知的命令: {command}", "Unknown command: {command}"),
+    ("cli.option_needs_value", "选项 --{name} 需要一个值", "Option --{name} requires a value"),
+    ("cli.option_takes_no_value", "选项 --{name} 不需要值", "Option --{name} does not take a value"),
+    ("cli.unsupported_option", "不支持的选项: --{name}", "Unsupported option: --{name}"),
+    ("cli.unsupported_lang", "不支持的语言: {lang}", "Unsupported language: {lang}"),
+    ("cli.not_a_number", "选项 --{name} 需要一个正整数: {value}", "Option --{name} requires a positive integer: {value}"),
+    ("cli.open_preset", "无法打开预设文件 {path}: {reason}", "Cannot open preset file {path}: {reason}"),
+    ("cli.invalid_preset", "预设文件格式无效 {path}: {reason}", "Invalid preset file {path}: {reason}"),
+    ("cli.rows_required", "请使用 --rows 指定每个文件的行数", "Use --rows to set the rows per file"),
+    ("cli.header_conflict", "--header 和 --no-header 不能同时使用", "--header and --no-header cannot be used together"),
+    ("cli.invalid_default", "列的补齐值格式应为 列名=值: {value}", "A column default must be column=value: {value}"),
+    ("cli.invalid_rename", "列改名的格式应为 列=新列名: {value}", "A rename must be column=new_name: {value}"),
+    ("cli.invalid_compute", "计算列的格式应为 列名=表达式: {value}", "A computed column must be name=expression: {value}"),
+    ("cli.open_layout", "无法打开列布局文件 {path}: {reason}", "Cannot open layout file {path}: {reason}"),
+    ("cli.invalid_layout", "列布局文件格式无效: {reason}", "Invalid layout file: {reason}"),
+    ("cli.invalid_params", "参数无效: {reason}", "Invalid parameters: {reason}"),
+    (
+        "cli.invalid_sort_key",
+        "排序键 {key} 中的 {part} 无效，应为 asc、desc、text、number 或 date",
+        "Invalid {part} in sort key {key}, expected asc, desc, text, number or date",
+    ),
+    ("cli.paths_required", "请指定输入文件和输出目录", "Specify the input file and the output directory"),
+    ("cli.input_required", "请指定输入文件或 --input-dir", "Specify an input file or --input-dir"),
+    ("cli.output_required", "请使用 -o 指定输出文件", "Use -o to set the output file"),
+    ("cli.merge_inputs_required", "请指定要合并的文件", "Specify the files to merge"),
+    ("cli.invalid_alias", "列名别名格式应为 旧列名=新列名: {value}", "An alias must be old_name=new_name: {value}"),
+    (
+        "cli.verify_matched",
+        "校验通过：{pieces} 个分块共 {rows} 行，与原文件一致（SHA-256 {hash}）",
+        "Verified: {pieces} pieces with {rows} rows match the input (SHA-256 {hash})",
+    ),
+    ("cli.verify_failed", "校验失败: {reason}", "Verification failed: {reason}"),
+    ("cli.merge_done", "合并完成：{files} 个文件共 {rows} 行，已写入 {path}", "Merged {files} files with {rows} rows into {path}"),
+    ("cli.merge_failed", "合并失败: {reason}", "Merge failed: {reason}"),
+    ("cli.unknown_error", "未知错误", "Unknown error"),
+    ("cli.input_done", "{path}: 生成 {files} 个文件", "{path}: {files} files written"),
+    ("cli.input_failed", "{path}: 失败: {reason}", "{path}: failed: {reason}"),
+    ("cli.verify_rows", "校验通过：共 {rows} 行", "Verified: {rows} rows"),
+    ("cli.skipped_file", "已跳过已存在的文件: {path}", "Skipped existing file: {path}"),
+    ("cli.adjusted_rows", "已补齐或截断 {count} 个字段数不一致的行", "Padded or truncated {count} ragged rows"),
+    ("cli.filtered_rows", "已筛除 {count} 个不满足条件的行", "Filtered out {count} rows"),
+    ("cli.duplicate_rows", "已去除 {count} 个重复行", "Removed {count} duplicate rows"),
+    (
+        "cli.split_groups",
+        "注意：{count} 个组的行数超过每个分块最多的行数，已被切开",
+        "Note: {count} groups exceeded the maximum rows per piece and were cut",
+    ),
+    ("cli.rejected_rows", "已跳过 {count} 个格式错误的行", "Skipped {count} malformed rows"),
+    ("cli.rejected_rows_file", "已跳过 {count} 个格式错误的行，已写入 {path}", "Skipped {count} malformed rows, written to {path}"),
+    ("cli.split_done", "分割完成：共生成 {files} 个文件", "Split complete: {files} files written"),
+    ("cli.split_failed", "分割失败: {reason}", "Split failed: {reason}"),
+    (
+        "cli.checkpoint_saved",
+        "已保存断点，排除问题后可用 resume 继续分割",
+        "A checkpoint was saved; fix the problem and continue with resume",
+    ),
+    ("cli.print_json_failed", "无法输出JSON结果: {reason}", "Cannot print the JSON result: {reason}"),
+    ("cli.progress", "[{stage}]{percent} {rows} 行，{pieces} 个文件", "[{stage}]{percent} {rows} rows, {pieces} files"),
+    ("cli.stage.deduplicating", "查找重复行", "Finding duplicates"),
+    ("cli.stage.sorting", "排序", "Sorting"),
+    ("cli.stage.splitting", "分割", "Splitting"),
+    ("cli.stage.verifying", "校验", "Verifying"),
+    ("cli.stage.converting", "转换Excel", "Converting to Excel"),
+    ("cli.stage.finishing", "整理输出", "Finishing"),
+];
+
+/// 可按语言生成的说明：信息目录中的键和模板参数，或来自底层、保持原文的文本（如系统的IO错误）
+///
+/// 参数本身也是 `Message`，可以嵌套另一条需要翻译的说明。序列化为按 [`Localized`] 指定语言生成的文本。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    Text(String),
+    Key { key: &'static str, args: Vec<(&'static str, Message)> },
+}
+
+impl Message {
+    pub(crate) fn new(key: &'static str) -> Message {
+        Message::Key { key, args: Vec::new() }
+    }
+
+    /// 添加模板参数
+    pub(crate) fn arg(mut self, name: &'static str, value: impl Into<Message>) -> Message {
+        if let Message::Key { ref mut args, .. } = self {
+            args.push((name, value.into()));
+        }
+        self
+    }
+
+    /// 按指定语言生成文本
+    pub fn render(&self, locale: Locale) -> String {
+        match self {
+            Message::Text(text) => text.clone(),
+            Message::Key { key, args } => {
+                let args: Vec<(&str, String)> = args.iter().map(|(name, value)| (*name, value.render(locale))).collect();
+                render(lookup(key, locale), &args)
+            }
+        }
+    }
+}
+
+impl From<String> for Message {
+    fn from(text: String) -> Message {
+        Message::Text(text)
+    }
+}
+
+impl From<&str> for Message {
+    fn from(text: &str) -> Message {
+        Message::Text(text.to_string())
+    }
+}
+
+/// 按系统语言显示，见 [`Locale::system`]
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.render(Locale::system()))
+    }
+}
+
+impl Serialize for Message {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.render(serialize_locale()))
+    }
+}
+
+/// 按语言查找信息模板，目录中没有的键原样返回
+pub(crate) fn lookup(key: &'static str, locale: Locale) -> &'static str {
+    CATALOG
+        .iter()
+        .find(|(k, _, _)| *k == key)
+        .map(|&(_, zh, en)| match locale {
+            Locale::ZhCn => zh,
+            Locale::EnUs => en,
+        })
+        .unwrap_or(key)
+}
+
+/// 替换模板中的 `{name}`，替换进来的内容不再展开
+pub(crate) fn render(template: &str, args: &[(&str, String)]) -> String {
+    let mut text = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        text.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let value = after
+            .find('}')
+            .and_then(|end| args.iter().find(|(name, _)| *name == &after[..end]).map(|(_, v)| (end, v)));
+        match value {
+            Some((end, value)) => {
+                text.push_str(value);
+                rest = &after[end + 1..];
+            }
+            None => {
+                text.push('{');
+                rest = after;
+            }
+        }
+    }
+    text.push_str(rest);
+    text
+}
+
+thread_local! {
+    /// 正在序列化的 [`Localized`] 指定的语言
+    static SERIALIZE_LOCALE: Cell<Option<Locale>> = const { Cell::new(None) };
+}
+
+/// 序列化错误信息时使用的语言：在 [`Localized`] 内为其指定的语言，否则为系统语言
+pub(crate) fn serialize_locale() -> Locale {
+    SERIALIZE_LOCALE.get().unwrap_or_else(Locale::system)
+}
+
+/// 按指定语言序列化其中的错误信息，用于同一进程中需要按调用方选择语言的场合（如界面命令）
+#[derive(Debug, Clone)]
+pub struct Localized<T> {
+    pub value: T,
+    pub locale: Locale,
+}
+
+impl<T> Localized<T> {
+    pub fn new(value: T, locale: Locale) -> Localized<T> {
+        Localized { value, locale }
+    }
+}
+
+impl<T: Serialize> Serialize for Localized<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let previous = SERIALIZE_LOCALE.replace(Some(self.locale));
+        let result = self.value.serialize(serializer);
+        SERIALIZE_LOCALE.set(previous);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use regex::Regex;
+
+    use super::*;
+
+    #[test]
+    fn parses_language_tags() {
+        for tag in ["zh", "zh-CN", "zh_TW.UTF-8", "ZH", "zh@stroke"] {
+            assert_eq!(Locale::parse(tag), Some(Locale::ZhCn), "{}", tag);
+        }
+        for tag in ["en", "en-US", "en_GB.UTF-8", "EN-us"] {
+            assert_eq!(Locale::parse(tag), Some(Locale::EnUs), "{}", tag);
+        }
+        for tag in ["", "fr-FR", "C", "english"] {
+            assert_eq!(Locale::parse(tag), None, "{}", tag);
+        }
+    }
+
+    #[test]
+    fn system_locale_follows_the_environment() {
+        let locale = |vars: &[(&str, &str)]| {
+            Locale::from_env(|name| vars.iter().find(|(n, _)| *n == name).map(|(_, v)| v.to_string()))
+        };
+        assert_eq!(locale(&[]), Locale::ZhCn);
+        assert_eq!(locale(&[("LANG", "en_US.UTF-8")]), Locale::EnUs);
+        assert_eq!(locale(&[("LANG", "en_US.UTF-8"), ("LC_MESSAGES", "zh_CN.UTF-8")]), Locale::ZhCn);
+        assert_eq!(locale(&[("LANG", "zh_CN.UTF-8"), ("LC_MESSAGES", "zh_CN"), ("LC_ALL", "en_US")]), Locale::EnUs);
+        // 空值、C 和 POSIX 不算设置了语言，继续看下一个变量
+        assert_eq!(locale(&[("LC_ALL", ""), ("LC_MESSAGES", "C"), ("LANG", "en_US")]), Locale::EnUs);
+        assert_eq!(locale(&[("LC_ALL", "POSIX")]), Locale::ZhCn);
+        // 其他语言使用英文
+        assert_eq!(locale(&[("LANG", "de_DE.UTF-8")]), Locale::EnUs);
+    }
+
+    #[test]
+    fn renders_placeholders() {
+        let args = [("a", "1".to_string()), ("b", "{a}".to_string())];
+        assert_eq!(render("{a} and {b}, {a}", &args), "1 and {a}, 1");
+        assert_eq!(render("{missing} {a", &args), "{missing} {a");
+        assert_eq!(render("{{a}}", &args), "{1}");
+
+        let message = Message::new("column_not_found").arg("name", "id");
+        assert_eq!(message.render(Locale::EnUs), "Column not found: id");
+        assert_eq!(message.render(Locale::ZhCn), "找不到列: id");
+        let nested = Message::new("invalid_record").arg("row", "2").arg("reason", message);
+        assert_eq!(nested.render(Locale::EnUs), "Record 2 is invalid: Column not found: id");
+        assert_eq!(Message::from("raw {name}").render(Locale::EnUs), "raw {name}");
+        assert_eq!(lookup("no.such.key", Locale::EnUs), "no.such.key");
+    }
+
+    #[test]
+    fn localized_switches_the_language_while_serializing() {
+        let message = Message::new("empty_input");
+        let value = Localized::new((Localized::new(message.clone(), Locale::ZhCn), message.clone()), Locale::EnUs);
+        assert_eq!(
+            serde_json::to_value(value).unwrap(),
+            serde_json::json!(["输入文件为空", "The input file is empty"])
+        );
+        assert_eq!(SERIALIZE_LOCALE.get(), None);
+        assert_eq!(serde_json::to_value(&message).unwrap(), message.render(Locale::system()));
+    }
+
+    #[test]
+    fn catalog_covers_every_key_in_both_languages() {
+        let placeholder = Regex::new(r"\{(\w+)\}").unwrap();
+        let placeholders = |template: &str| {
+            let mut names: Vec<String> = placeholder.captures_iter(template).map(|c| c[1].to_string()).collect();
+            names.sort();
+            names
+        };
+        let mut keys = HashSet::new();
+        for (key, zh, en) in CATALOG {
+            assert!(keys.insert(*key), "duplicate key {}", key);
+            assert!(!zh.is_empty() && !en.is_empty(), "empty entry {}", key);
+            // 两种语言使用相同的参数
+            assert_eq!(placeholders(zh), placeholders(en), "{}", key);
+        }
+
+        // 错误码本身是键，只有 unsupported_file 按输入格式换成带后缀的键
+        let error_rs = include_str!("error.rs");
+        let codes = &error_rs[error_rs.find("pub fn code").unwrap()..error_rs.find("pub fn message").unwrap()];
+        for captures in Regex::new(r#"=> "(\w+)","#).unwrap().captures_iter(codes) {
+            assert!(&captures[1] == "unsupported_file" || keys.contains(&captures[1]), "error code {}", &captures[1]);
+        }
+
+        // 以字面量传给 Message::new 和各模块的 invalid 等辅助函数的键，以及按条件选择的带前缀的键
+        let used = Regex::new(
+            r#"(?:Message::new|invalid|invalid_json|lookup)\(\s*"([^"]+)"|what: "([^"]+)"|=> "(\w+\.[\w.]+)",|\(\w+\.\w+, "([\w.]+)"\)"#,
+        )
+        .unwrap();
+        let src = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+        let mut checked = 0;
+        for entry in std::fs::read_dir(&src).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_none_or(|e| e != "rs") {
+                continue;
+            }
+            let text = std::fs::read_to_string(&path).unwrap();
+            let text = text.split("#[cfg(test)]").next().unwrap();
+            for captures in used.captures_iter(text) {
+                let key = captures.iter().skip(1).flatten().next().unwrap().as_str();
+                assert!(keys.contains(key), "{} uses {} which is not in the catalog", path.display(), key);
+                checked += 1;
+            }
+        }
+        assert!(checked > 100, "only found {} keys", checked);
+    }
+}
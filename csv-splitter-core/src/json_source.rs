@@ -0,0 +1,415 @@
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+use csv::StringRecord;
+use serde_json::{Map, Value};
+
+use crate::i18n::Message;
+use crate::source::RecordSource;
+use crate::Error;
+
+/// JSON输入：支持JSON Lines和顶层数组两种布局
+///
+/// 嵌套对象展开为以 `.` 连接的列名，数组保留为JSON文本。
+/// 嵌套对象展开后与字面带点的键同名（如 `{"a":{"b":1}}` 与 `{"a.b":2}` 出现在同一条记录中）时，
+/// 该记录按格式错误的行处理，见 [`BadRowPolicy`](crate::BadRowPolicy)；
+/// 括号配对完整但无法解析或不是对象的记录同样按格式错误的行处理，跳过后继续读取下一条。
+/// 打开时先完整扫描一遍以合并所有记录的键，列顺序按键首次出现的顺序，
+/// 之后再流式读取记录，内存占用与文件大小无关。
+pub(crate) struct JsonSource {
+    records: JsonRecords<BufReader<File>>,
+    headers: StringRecord,
+    record_index: usize,
+}
+
+impl JsonSource {
+    pub(crate) fn open(input_path: &Path) -> Result<JsonSource, Error> {
+        // 第一遍：合并所有记录的键，得到稳定的标题行
+        let mut columns = Vec::new();
+        let mut seen = HashSet::new();
+        let mut scan = JsonRecords::open(input_path)?;
+        let mut record_index = 0;
+        loop {
+            // 无效的记录不参与合并列名，读取时再按格式错误的行处理
+            let object = match scan.next_object(record_index + 1) {
+                Ok(Some(object)) => object,
+                Ok(None) => break,
+                Err(e) if e.is_row_error() => {
+                    record_index += 1;
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            record_index += 1;
+            for (key, _) in flatten_object(object) {
+                if seen.insert(key.clone()) {
+                    columns.push(key);
+                }
+            }
+        }
+
+        if columns.is_empty() {
+            return Err(Error::NoDataRows);
+        }
+
+        // 第二遍：重新打开文件，供分割器流式读取
+        Ok(JsonSource {
+            records: JsonRecords::open(input_path)?,
+            headers: StringRecord::from(columns),
+            record_index: 0,
+        })
+    }
+}
+
+impl RecordSource for JsonSource {
+    fn headers(&self) -> &StringRecord {
+        &self.headers
+    }
+
+    fn read_record(&mut self, record: &mut StringRecord) -> Result<bool, Error> {
+        let object = match self.records.next_object(self.record_index + 1) {
+            Ok(Some(object)) => object,
+            Ok(None) => return Ok(false),
+            Err(e) => {
+                if e.is_row_error() {
+                    self.record_index += 1;
+                    record.clear();
+                }
+                return Err(e);
+            }
+        };
+        self.record_index += 1;
+
+        // 同名的字段保留第一个值，隔离模式下写入的行仍能看出记录的内容
+        let mut values: HashMap<String, String> = HashMap::new();
+        let mut duplicate = None;
+        for (key, value) in flatten_object(object) {
+            match values.entry(key) {
+                Entry::Occupied(entry) => {
+                    duplicate.get_or_insert_with(|| entry.key().clone());
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(value);
+                }
+            }
+        }
+        record.clear();
+        for column in self.headers.iter() {
+            record.push_field(values.remove(column).as_deref().unwrap_or(""));
+        }
+        match duplicate {
+            Some(column) => Err(Error::DuplicateField { row: self.record_index as u64, column }),
+            None => Ok(true),
+        }
+    }
+}
+
+/// 将嵌套对象展开为 `(a.b.c, 值)` 列表
+fn flatten_object(object: Map<String, Value>) -> Vec<(String, String)> {
+    let mut fields = Vec::new();
+    flatten_into(String::new(), Value::Object(object), &mut fields);
+    fields
+}
+
+fn flatten_into(key: String, value: Value, fields: &mut Vec<(String, String)>) {
+    match value {
+        Value::Object(object) if !object.is_empty() => {
+            for (child, value) in object {
+                let child_key = if key.is_empty() {
+                    child
+                } else {
+                    format!("{}.{}", key, child)
+                };
+                flatten_into(child_key, value, fields);
+            }
+        }
+        // 空对象保留为空值列，避免列从结果中消失
+        Value::Object(_) => fields.push((key, String::new())),
+        Value::Null => fields.push((key, String::new())),
+        Value::String(s) => fields.push((key, s)),
+        Value::Bool(b) => fields.push((key, b.to_string())),
+        Value::Number(n) => fields.push((key, n.to_string())),
+        Value::Array(_) => fields.push((key, value.to_string())),
+    }
+}
+
+/// 流式JSON记录读取器，根据首个非空白字符判断文件布局
+struct JsonRecords<R: BufRead> {
+    reader: R,
+    path: PathBuf,
+    layout: JsonLayout,
+}
+
+#[derive(PartialEq)]
+enum JsonLayout {
+    /// 顶层数组，`finished` 表示已读到结尾的 `]`
+    Array { finished: bool },
+    /// JSON Lines，也兼容多个对象首尾相接的写法
+    Lines,
+}
+
+impl JsonRecords<BufReader<File>> {
+    fn open(input_path: &Path) -> Result<Self, Error> {
+        let file = File::open(input_path).map_err(|e| Error::io(input_path, e))?;
+        let mut reader = BufReader::new(file);
+
+        // 跳过UTF-8 BOM
+        if reader.fill_buf().map_err(|e| Error::io(input_path, e))?.starts_with(&[0xEF, 0xBB, 0xBF]) {
+            reader.consume(3);
+        }
+
+        let mut records = JsonRecords {
+            reader,
+            path: input_path.to_path_buf(),
+            layout: JsonLayout::Lines,
+        };
+
+        match records.peek_non_whitespace()? {
+            Some(b'[') => {
+                records.reader.consume(1);
+                records.layout = JsonLayout::Array { finished: false };
+            }
+            Some(b'{') => {}
+            Some(_) => return Err(invalid_json("json_source.invalid_top_level")),
+            None => return Err(Error::EmptyInput),
+        }
+
+        Ok(records)
+    }
+}
+
+impl<R: BufRead> JsonRecords<R> {
+    /// 读取下一个对象，`record_number` 仅用于错误提示
+    ///
+    /// 截取出的记录无法解析或不是对象时已整条读走，返回 [`Error::InvalidRecord`]，可以继续读取下一条。
+    fn next_object(&mut self, record_number: usize) -> Result<Option<Map<String, Value>>, Error> {
+        let bytes = match self.layout {
+            JsonLayout::Array { finished: true } => return Ok(None),
+            JsonLayout::Array { finished: false } => self.next_array_element()?,
+            JsonLayout::Lines => self.next_line_value()?,
+        };
+
+        let bytes = match bytes {
+            Some(bytes) => bytes,
+            None => return Ok(None),
+        };
+
+        match serde_json::from_slice::<Value>(&bytes) {
+            Ok(Value::Object(object)) => Ok(Some(object)),
+            Ok(_) => Err(Error::InvalidRecord {
+                row: record_number as u64,
+                reason: Message::new("json_source.not_object"),
+            }),
+            Err(e) => Err(Error::InvalidRecord {
+                row: record_number as u64,
+                reason: format!("{}: {}", self.path.display(), e).into(),
+            }),
+        }
+    }
+
+    fn peek_non_whitespace(&mut self) -> Result<Option<u8>, Error> {
+        loop {
+            let buffer = self.reader.fill_buf().map_err(|e| Error::io(&self.path, e))?;
+            if buffer.is_empty() {
+                return Ok(None);
+            }
+            match buffer.iter().position(|b| !b.is_ascii_whitespace()) {
+                Some(pos) => {
+                    let byte = buffer[pos];
+                    self.reader.consume(pos);
+                    return Ok(Some(byte));
+                }
+                None => {
+                    let len = buffer.len();
+                    self.reader.consume(len);
+                }
+            }
+        }
+    }
+
+    fn peek_byte(&mut self) -> Result<Option<u8>, Error> {
+        let buffer = self.reader.fill_buf().map_err(|e| Error::io(&self.path, e))?;
+        Ok(buffer.first().copied())
+    }
+
+    fn next_byte(&mut self) -> Result<Option<u8>, Error> {
+        let byte = self.peek_byte()?;
+        if byte.is_some() {
+            self.reader.consume(1);
+        }
+        Ok(byte)
+    }
+
+    /// 截取一个完整的JSON值：对象和数组以括号配对为界，
+    /// 标量在遇到分隔符时结束，分隔符留给调用方处理
+    fn read_value(&mut self) -> Result<Vec<u8>, Error> {
+        let mut bytes = Vec::new();
+        let mut depth = 0usize;
+        let mut in_string = false;
+        let mut escaped = false;
+
+        loop {
+            if depth == 0 && !in_string && !bytes.is_empty() {
+                match self.peek_byte()? {
+                    Some(b) if b != b',' && b != b']' && !b.is_ascii_whitespace() => {}
+                    _ => break,
+                }
+            }
+
+            let byte = match self.next_byte()? {
+                Some(byte) => byte,
+                None => break,
+            };
+            bytes.push(byte);
+
+            if in_string {
+                if escaped {
+                    escaped = false;
+                } else if byte == b'\\' {
+                    escaped = true;
+                } else if byte == b'"' {
+                    in_string = false;
+                }
+                continue;
+            }
+
+            match byte {
+                b'"' => in_string = true,
+                b'{' | b'[' => depth += 1,
+                b'}' | b']' => {
+                    depth = depth.saturating_sub(1);
+                    if depth == 0 {
+                        return Ok(bytes);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if in_string || depth > 0 {
+            return Err(invalid_json("json_source.truncated_record"));
+        }
+        Ok(bytes)
+    }
+
+    fn next_array_element(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        if self.peek_non_whitespace()? == Some(b']') {
+            self.reader.consume(1);
+            self.layout = JsonLayout::Array { finished: true };
+            return Ok(None);
+        }
+
+        let bytes = self.read_value()?;
+        match self.peek_non_whitespace()? {
+            Some(b',') => self.reader.consume(1),
+            Some(b']') => {
+                self.reader.consume(1);
+                self.layout = JsonLayout::Array { finished: true };
+            }
+            Some(_) => return Err(invalid_json("json_source.missing_comma")),
+            None => return Err(invalid_json("json_source.missing_bracket")),
+        }
+        Ok(Some(bytes))
+    }
+
+    fn next_line_value(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        if self.peek_non_whitespace()?.is_none() {
+            return Ok(None);
+        }
+        // 对象以括号配对为界，所以不要求一行一条，也能读取格式化过的多行对象
+        Ok(Some(self.read_value()?))
+    }
+}
+
+/// JSON文件的整体结构无效，无法定位到某条记录
+fn invalid_json(key: &'static str) -> Error {
+    Error::Parse { row: None, byte: None, reason: Message::new(key) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open(name: &str, content: &str) -> JsonSource {
+        let path = std::env::temp_dir().join(format!("csv-splitter-json-{}-{}", std::process::id(), name));
+        std::fs::write(&path, content).unwrap();
+        let source = JsonSource::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        source
+    }
+
+    fn read_all(source: &mut JsonSource) -> Vec<Result<Vec<String>, Error>> {
+        let mut rows = Vec::new();
+        let mut record = StringRecord::new();
+        loop {
+            match source.read_record(&mut record) {
+                Ok(false) => return rows,
+                Ok(true) => rows.push(Ok(record.iter().map(String::from).collect())),
+                Err(e) => rows.push(Err(e)),
+            }
+        }
+    }
+
+    #[test]
+    fn lines_and_array_layouts_read_the_same_records() {
+        let lines = "{\"id\":1,\"name\":\"a\"}\n\n{\"id\":2,\"name\":\"b\"}\n";
+        let array = "\u{feff}[\n  {\"id\": 1, \"name\": \"a\"},\n  {\"id\": 2, \"name\": \"b\"}\n]";
+        for (name, content) in [("lines.jsonl", lines), ("array.json", array)] {
+            let mut source = open(name, content);
+            assert_eq!(source.headers(), &StringRecord::from(vec!["id", "name"]));
+            let rows: Vec<_> = read_all(&mut source).into_iter().map(Result::unwrap).collect();
+            assert_eq!(rows, [["1", "a"], ["2", "b"]]);
+        }
+    }
+
+    #[test]
+    fn nested_objects_flatten_and_keys_are_merged() {
+        let mut source = open(
+            "nested.jsonl",
+            "{\"id\":1,\"user\":{\"name\":\"x\",\"tags\":[1,2]}}\n{\"id\":2,\"extra\":null,\"empty\":{}}\n",
+        );
+        assert_eq!(source.headers(), &StringRecord::from(vec!["id", "user.name", "user.tags", "empty", "extra"]));
+        let rows: Vec<_> = read_all(&mut source).into_iter().map(Result::unwrap).collect();
+        assert_eq!(rows, [["1", "x", "[1,2]", "", ""], ["2", "", "", "", ""]]);
+    }
+
+    #[test]
+    fn nested_key_colliding_with_dotted_key_is_a_row_error() {
+        let mut source = open("collision.jsonl", "{\"a\":{\"b\":1},\"a.b\":2}\n{\"a.b\":3}\n");
+        let rows = read_all(&mut source);
+        let error = rows[0].as_ref().unwrap_err();
+        assert_eq!(error, &Error::DuplicateField { row: 1, column: "a.b".into() });
+        assert!(error.is_row_error());
+        assert_eq!(rows[1].as_ref().unwrap(), &["3"]);
+    }
+
+    #[test]
+    fn malformed_records_are_row_errors() {
+        let mut source = open("malformed.jsonl", "{\"a\":1}\n{\"a\": tru}\n[1]\n{\"a\":2,\"b\":3}\n");
+        assert_eq!(source.headers(), &StringRecord::from(vec!["a", "b"]));
+        let rows = read_all(&mut source);
+        assert_eq!(rows.len(), 4);
+        assert_eq!(rows[0].as_ref().unwrap(), &["1", ""]);
+        for (row, result) in [(2, &rows[1]), (3, &rows[2])] {
+            let error = result.as_ref().unwrap_err();
+            assert_eq!((error.code(), error.row()), ("invalid_record", Some(row)));
+            assert!(error.is_row_error());
+        }
+        assert_eq!(rows[3].as_ref().unwrap(), &["2", "3"]);
+    }
+
+    #[test]
+    fn truncated_and_malformed_files_are_reported() {
+        let path = std::env::temp_dir().join(format!("csv-splitter-json-{}-bad.json", std::process::id()));
+        for content in ["[{\"a\":1}", "[{\"a\":1} {\"a\":2}]", "{\"a\":\"x", "42"] {
+            std::fs::write(&path, content).unwrap();
+            assert_eq!(JsonSource::open(&path).err().map(|e| e.code()), Some("parse"), "{}", content);
+        }
+        std::fs::write(&path, "   ").unwrap();
+        assert_eq!(JsonSource::open(&path).err(), Some(Error::EmptyInput));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,1506 @@
+//! CSV分割引擎，不依赖Tauri，界面程序、命令行和其他Rust程序共用
+//!
+//! 入口为 [`split`]、[`resume`]、[`verify`] 和 [`merge`]，进度通过 [`ProgressListener`] 回调。
+//! 错误信息支持中文和英文，见 [`Locale`]。
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use std::thread;
+
+use csv::{ReaderBuilder, Writer, WriterBuilder};
+use serde::Serialize;
+use rust_xlsxwriter::{Workbook, Format, FormatAlign};
+use memmap2::Mmap;
+
+mod bad_rows;
+mod batch;
+mod checkpoint;
+mod cli;
+mod computed;
+mod dedupe;
+mod error;
+mod expr;
+mod filter;
+mod fixed_width;
+mod header;
+mod i18n;
+mod json_source;
+mod manifest;
+mod merge;
+mod naming;
+mod output;
+mod pipeline;
+mod progress;
+mod projection;
+mod ragged;
+mod sort;
+mod source;
+mod verify;
+mod watch;
+
+pub use bad_rows::BadRowPolicy;
+pub use computed::ComputedColumn;
+pub use batch::InputResult;
+pub use dedupe::{Dedupe, DedupeKeep};
+pub use error::Error;
+pub use fixed_width::{FixedWidthColumn, FixedWidthLayout, TrimRule};
+pub use header::HeaderRename;
+pub use i18n::{Locale, Localized, Message};
+pub use manifest::{InputManifest, Manifest, PieceManifest};
+pub use merge::{HeaderMode, MergeFileReport, MergeParams, MergeResult};
+pub use output::OverwritePolicy;
+pub use progress::{NoProgress, Progress, ProgressListener, Stage, Throttled};
+pub use projection::ColumnRef;
+pub use ragged::LongRowPolicy;
+pub use sort::{SortCompare, SortKey};
+pub use source::InputFormat;
+pub use verify::{VerifyMismatch, VerifyParams, VerifyReport};
+pub use watch::{WatchEvent, WatchParams, WatchState};
+
+use bad_rows::{rejected_file_name, BadRows};
+use batch::split_batch;
+use checkpoint::{has_checkpoint, read_checkpoint, Checkpoint};
+use dedupe::Duplicates;
+use expr::uses_row_number;
+use header::resolve_headers;
+use manifest::write_manifest;
+use merge::merge_csv_internal;
+use naming::{NameTemplate, DEFAULT_TEMPLATE};
+use output::{commit_pieces, discard_staging, prepare_staging, staging_dir, working_piece_path};
+use pipeline::{RowOutcome, RowPipeline};
+use progress::REPORT_EVERY_ROWS;
+use projection::{project, Projection};
+use sort::Sorter;
+use source::{default_headers, open_source};
+use verify::{find_pieces, verify_pieces};
+
+/// Excel工作表最多1048576行，除去标题行后可写入的数据行数
+const EXCEL_MAX_DATA_ROWS: usize = 1_048_575;
+
+/// 分割结果，失败时 `success` 为 `false` 并在 `error` 中说明原因
+#[derive(Debug, Clone, Serialize)]
+pub struct SplitResult {
+    pub success: bool,
+    pub file_count: usize,
+    pub error: Option<Error>,
+    /// 分割后还原校验的结果（仅在开启校验时返回）
+    pub verification: Option<VerifyReport>,
+    /// 分割清单，内容与输出目录中的清单文件相同
+    pub manifest: Option<Manifest>,
+    /// 因同名文件已存在而按策略跳过的分块（目标文件名）
+    pub skipped_files: Vec<String>,
+    /// 失败时是否保留了断点，排除问题后可以用 [`resume`] 继续
+    pub checkpoint_saved: bool,
+    /// 按 [`BadRowPolicy`] 跳过的格式错误的行数
+    pub rejected_rows: usize,
+    /// 隔离模式下写入出错的行的文件
+    pub rejected_file: Option<String>,
+    /// 灵活模式下补齐或截断过的行数，各分块的数量见清单
+    pub adjusted_rows: usize,
+    /// 不满足筛选条件而未写入的行数
+    pub filtered_rows: usize,
+    /// 去重时丢弃的重复行数
+    pub duplicate_rows: usize,
+    /// 按组分割时因超过每个分块最多的行数而被切开的组数
+    pub split_groups: usize,
+    /// 批量处理时每个输入文件的结果
+    pub inputs: Vec<InputResult>,
+}
+
+impl SplitResult {
+    fn failure(error: impl Into<Error>) -> SplitResult {
+        SplitResult {
+            success: false,
+            file_count: 0,
+            error: Some(error.into()),
+            verification: None,
+            manifest: None,
+            skipped_files: Vec::new(),
+            checkpoint_saved: false,
+            rejected_rows: 0,
+            rejected_file: None,
+            adjusted_rows: 0,
+            filtered_rows: 0,
+            duplicate_rows: 0,
+            split_groups: 0,
+            inputs: Vec::new(),
+        }
+    }
+
+    /// 转换为 `Result`，便于调用方用 `?` 处理失败
+    pub fn into_result(self) -> Result<SplitResult, Error> {
+        match (self.success, &self.error) {
+            (true, _) => Ok(self),
+            (false, Some(error)) => Err(error.clone()),
+            (false, None) => Err(Message::new("split.failed").into()),
+        }
+    }
+}
+
+/// 分割阶段的产出
+struct SplitOutput {
+    pieces: Vec<PieceInfo>,
+    /// 跳过的格式错误的行数
+    rejected_rows: usize,
+    /// 隔离模式下暂存目录中写入出错的行的文件
+    rejected_file: Option<PathBuf>,
+    /// 不满足筛选条件的行数
+    filtered_rows: usize,
+    /// 丢弃的重复行数
+    duplicate_rows: usize,
+    /// 被切开的组数
+    split_groups: usize,
+}
+
+/// 分割过程中产生的单个分块
+#[derive(Debug, Clone)]
+struct PieceInfo {
+    path: PathBuf,
+    /// 第一行数据在原文件中的数据行号（从1开始，不含标题行）
+    first_row: usize,
+    last_row: usize,
+    row_count: usize,
+    /// 灵活模式下补齐或截断过的行数
+    adjusted_rows: usize,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+/// 分割参数，也是界面和预设文件使用的JSON格式
+pub struct SplitParams {
+    /// 输入文件，批量处理时可以为空
+    #[serde(default)]
+    pub input_path: String,
+    pub output_dir: String,
+    pub rows_per_file: usize,
+    pub has_header: bool,
+    pub convert_to_excel: bool,
+    /// 输入格式，未指定时根据扩展名推断（提供了定长布局时按定长文本处理）
+    #[serde(default)]
+    pub input_format: Option<InputFormat>,
+    /// 定长文本的列布局
+    #[serde(default)]
+    pub fixed_width_layout: Option<FixedWidthLayout>,
+    /// 分割完成后校验分块能否还原为原文件
+    #[serde(default)]
+    pub verify: bool,
+    /// 输出文件命名模板（不含扩展名），默认为 `{stem}_{index}`
+    #[serde(default)]
+    pub file_name_template: Option<String>,
+    /// 输出文件已存在时的处理方式
+    #[serde(default)]
+    pub overwrite_policy: OverwritePolicy,
+    /// 分割过程中记录断点，中断后可用 `resume_split` 继续（仅支持CSV输入，按单线程处理）
+    #[serde(default)]
+    pub checkpoint: bool,
+    /// 批量处理的输入文件列表
+    #[serde(default)]
+    pub input_paths: Vec<String>,
+    /// 批量处理的输入目录，处理其中文件名匹配 `input_pattern` 的文件（不含子目录）
+    #[serde(default)]
+    pub input_dir: Option<String>,
+    /// 输入目录中的文件名模式，支持 `*` 和 `?`，默认为 `*.csv`
+    #[serde(default)]
+    pub input_pattern: Option<String>,
+    /// 批量处理时同时分割的文件数，0表示使用默认值
+    #[serde(default)]
+    pub max_parallel_jobs: usize,
+    /// 遇到格式错误的行时的处理方式，默认报错
+    #[serde(default)]
+    pub bad_row_policy: BadRowPolicy,
+    /// 灵活模式：字段数与标题行（没有标题行时为第一行）不一致的行按列数补齐或截断，不作为格式错误
+    #[serde(default)]
+    pub flexible: bool,
+    /// 灵活模式下补齐缺失字段的值，默认为空
+    #[serde(default)]
+    pub fill_value: String,
+    /// 灵活模式下按列名指定的补齐值，优先于 `fill_value`
+    #[serde(default)]
+    pub column_defaults: HashMap<String, String>,
+    /// 灵活模式下字段多于标题行的行的处理方式
+    #[serde(default)]
+    pub long_rows: LongRowPolicy,
+    /// CSV没有标题行时生成列名的模板，如 `col_{index:03}`，默认为 `column_{index}`
+    #[serde(default)]
+    pub header_template: Option<String>,
+    /// 替换整个标题行，列数须与输入文件一致
+    #[serde(default)]
+    pub header_row: Vec<String>,
+    /// 按列名或列序号给列改名，在 `header_row` 之后应用；其他按列名指定的参数使用改名后的列名
+    #[serde(default)]
+    pub rename_columns: Vec<HeaderRename>,
+    /// 追加在每行末尾的计算列，按顺序计算
+    #[serde(default)]
+    pub computed_columns: Vec<ComputedColumn>,
+    /// 行筛选条件，如 `status = 'paid' and amount > 100`，只有满足条件的行写入分块；列名为改名后、列选择前的列名，可以引用计算列
+    #[serde(default)]
+    pub filter: Option<String>,
+    /// 输出的列及其顺序（列名或从1开始的列序号），为空时输出全部列
+    #[serde(default)]
+    pub columns: Vec<ColumnRef>,
+    /// 不输出的列
+    #[serde(default)]
+    pub exclude_columns: Vec<ColumnRef>,
+    /// 去除重复行，在筛选之后、列选择之前进行；需要先完整扫描一遍输入
+    #[serde(default)]
+    pub dedupe: Option<Dedupe>,
+    /// 分割前按这些键排序（依次比较），在去重之后、列选择之前进行，可以引用计算列
+    #[serde(default)]
+    pub sort: Vec<SortKey>,
+    /// 排序可使用的内存（MB），超过时借助暂存目录中的临时文件，默认256
+    #[serde(default)]
+    pub sort_memory_mb: Option<usize>,
+    /// 按这些列分组，同一组的行不会分到两个分块中：分块写满后延续到当前组结束再换下一个分块；
+    /// 输入须已按这些列排序或分组（可配合 `sort`）
+    #[serde(default)]
+    pub group_by: Vec<ColumnRef>,
+    /// 按组分割时每个分块最多的行数，一组超过该行数时从中间切开；
+    /// 默认为 `rows_per_file` 的两倍，转换为Excel时不超过工作表的上限
+    #[serde(default)]
+    pub max_rows_per_file: Option<usize>,
+}
+
+impl SplitParams {
+    fn name_template(&self) -> Result<NameTemplate, Error> {
+        NameTemplate::parse(self.file_name_template.as_deref().unwrap_or(DEFAULT_TEMPLATE))
+    }
+
+    /// 是否为批量任务（指定了多个输入文件或输入目录）
+    fn is_batch(&self) -> bool {
+        !self.input_paths.is_empty() || self.input_dir.is_some()
+    }
+
+    /// 是否需要按顺序读取全部行：记录断点、去重、排序、按组分割、筛选，或计算列中用到了原文件行号
+    ///
+    /// 多线程按行数预先划分分块，筛掉的行会让分块的行数不足，所以有筛选条件时同样按顺序处理。
+    fn requires_sequential(&self) -> bool {
+        self.checkpoint
+            || self.dedupe.is_some()
+            || !self.sort.is_empty()
+            || !self.group_by.is_empty()
+            || self.filter.as_deref().is_some_and(|filter| !filter.trim().is_empty())
+            || self.computed_columns.iter().any(|c| uses_row_number(&c.expression))
+    }
+
+    /// 每个分块最多的行数，不按组分割时为 `rows_per_file`
+    fn max_rows_per_piece(&self) -> usize {
+        if self.group_by.is_empty() {
+            return self.rows_per_file;
+        }
+        self.max_rows_per_file.unwrap_or_else(|| {
+            let default = self.rows_per_file.saturating_mul(2);
+            if self.convert_to_excel { default.min(EXCEL_MAX_DATA_ROWS).max(self.rows_per_file) } else { default }
+        })
+    }
+
+    fn input_format(&self) -> InputFormat {
+        match self.input_format {
+            Some(format) => format,
+            None if self.fixed_width_layout.is_some() => InputFormat::FixedWidth,
+            None => InputFormat::from_path(Path::new(&self.input_path)),
+        }
+    }
+}
+
+/// 分割CSV文件
+///
+/// 指定了多个输入文件或输入目录时按批量任务处理，结果中列出每个文件的成功或失败。
+pub fn split(params: SplitParams, progress: &dyn ProgressListener) -> SplitResult {
+    if params.is_batch() {
+        return split_batch(&params, progress);
+    }
+    split_single(params, progress).unwrap_or_else(SplitResult::failure)
+}
+
+/// 从断点继续上次中断的分割
+///
+/// 使用断点中保存的分割参数，先确认输入文件未变化、已完成的分块完好，再从最后一个完好的分块之后继续。
+pub fn resume(params: &ResumeParams, progress: &dyn ProgressListener) -> SplitResult {
+    resume_single(params, progress).unwrap_or_else(SplitResult::failure)
+}
+
+/// 校验分割结果能否还原为原文件
+pub fn verify(params: &VerifyParams) -> Result<VerifyReport, Error> {
+    let pieces = if params.pieces.is_empty() {
+        find_pieces(&params.split)?
+    } else {
+        params.pieces.iter().map(PathBuf::from).collect()
+    };
+    verify_pieces(&params.split, &pieces)
+}
+
+/// 合并多个CSV文件（分割的逆操作）
+pub fn merge(params: &MergeParams) -> MergeResult {
+    match merge_csv_internal(params) {
+        Ok(files) => MergeResult {
+            success: true,
+            row_count: files.iter().map(|f| f.row_count).sum(),
+            files,
+            error: None,
+        },
+        Err(e) => MergeResult {
+            success: false,
+            row_count: 0,
+            files: Vec::new(),
+            error: Some(e),
+        },
+    }
+}
+
+/// 分割单个输入文件
+fn split_single(params: SplitParams, progress: &dyn ProgressListener) -> Result<SplitResult, Error> {
+    // 根据文件大小决定是否使用多线程优化
+    let input_path = Path::new(&params.input_path);
+    let metadata = match std::fs::metadata(input_path) {
+        Ok(meta) => meta,
+        Err(e) => return Err(Error::io(input_path, e)),
+    };
+    
+    // 对于大文件(>50万行或>100MB)使用多线程处理，多线程按换行符分块，仅适用于CSV输入
+    // 记录断点、去重、排序、按组分割、筛选或用到原文件行号时需要按顺序处理，不使用多线程
+    let use_multithread = params.input_format() == InputFormat::Csv && !params.requires_sequential() && (metadata.len() > 100 * 1024 * 1024 || {
+        // 快速估算行数
+        match File::open(input_path) {
+            Ok(f) => {
+                let mut reader = BufReader::new(f);
+                let mut line_count = 0;
+                let mut buffer = [0; 8192];
+                
+                while let Ok(bytes_read) = reader.read(&mut buffer) {
+                    if bytes_read == 0 { break; }
+                    line_count += buffer[..bytes_read].iter().filter(|&&b| b == b'\n').count();
+                    if line_count > 500_000 { break; }
+                }
+                line_count > 500_000
+            },
+            Err(_) => false
+        }
+    });
+    
+    // 命名模板和Excel行数在分割前校验，避免处理完大文件后才发现参数有误
+    if let Err(e) = params.name_template() {
+        return Ok(SplitResult::failure(e));
+    }
+    if params.convert_to_excel && params.max_rows_per_piece() > EXCEL_MAX_DATA_ROWS {
+        return Ok(SplitResult::failure(Error::LimitExceeded {
+            what: "limit.excel_rows_per_file",
+            limit: EXCEL_MAX_DATA_ROWS as u64,
+        }));
+    }
+    
+    // 分块先写入暂存目录，整个任务成功后才移入输出目录
+    if let Err(e) = prepare_staging(&params) {
+        return Ok(SplitResult::failure(e));
+    }
+    
+    let split_result = if use_multithread {
+        split_csv_multithread(params.clone(), progress)
+    } else {
+        split_csv_internal(params.clone(), None, progress)
+    };
+    
+    Ok(complete_split(&params, split_result, use_multithread, progress))
+}
+
+/// 继续上次中断的分割任务的参数
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct ResumeParams {
+    pub input_path: String,
+    pub output_dir: String,
+}
+
+fn resume_single(params: &ResumeParams, progress: &dyn ProgressListener) -> Result<SplitResult, Error> {
+    let checkpoint = read_checkpoint(&params.input_path, &params.output_dir)
+        .and_then(|checkpoint| checkpoint.ok_or(Error::CheckpointNotFound))
+        .and_then(|mut checkpoint| checkpoint.validate().map(|_| checkpoint));
+    let checkpoint = match checkpoint {
+        Ok(checkpoint) => checkpoint,
+        Err(e) => {
+            return Ok(SplitResult::failure(e));
+        }
+    };
+    
+    let split_params = checkpoint.params.clone();
+    let split_result = split_csv_internal(split_params.clone(), Some(checkpoint), progress);
+    
+    Ok(complete_split(&split_params, split_result, false, progress))
+}
+
+/// 分割结束后的统一处理：成功时收尾，失败时清理暂存目录
+///
+/// 记录了断点的任务在分割阶段失败时保留暂存目录，以便之后继续。
+fn complete_split(
+    params: &SplitParams,
+    split_result: Result<SplitOutput, Error>,
+    use_multithread: bool,
+    progress: &dyn ProgressListener,
+) -> SplitResult {
+    let result = match split_result {
+        Ok(output) => finish_split(params, output, use_multithread, progress),
+        Err(e) if params.checkpoint && has_checkpoint(params) => {
+            return SplitResult {
+                checkpoint_saved: true,
+                ..SplitResult::failure(e)
+            };
+        }
+        Err(e) => Err(e),
+    };
+    // 无论成功与否，暂存目录中剩下的都是未采用的分块或被替换文件的备份
+    discard_staging(params);
+    
+    match result {
+        Ok(result) => result,
+        Err(e) => SplitResult::failure(e),
+    }
+}
+
+/// 分割完成后的收尾：按需校验，按需转换为Excel，再按命名模板和覆盖策略移入输出目录，最后写入清单
+///
+/// 校验读取的是CSV分块，所以必须在转换之前进行；校验不通过时不会输出任何分块，
+/// 报告中记录了第一处不一致的位置。
+/// 转换在暂存目录中进行，不会因为中间产物与目录中已有的CSV同名而覆盖用户文件。
+/// 被拒绝的行的文件与分块一起按覆盖策略移入，清单写入失败时一并撤销。
+fn finish_split(
+    params: &SplitParams,
+    output: SplitOutput,
+    use_multithread: bool,
+    progress: &dyn ProgressListener,
+) -> Result<SplitResult, Error> {
+    let SplitOutput { mut pieces, rejected_rows, mut rejected_file, filtered_rows, duplicate_rows, split_groups } = output;
+    let report_stage = |stage: Stage, pieces: &[PieceInfo]| {
+        let mut current = Progress::new(&params.input_path, stage, 0);
+        current.rows = pieces.iter().map(|p| p.row_count).sum();
+        current.pieces = pieces.len();
+        progress.on_progress(&current);
+    };
+    if progress.is_cancelled() {
+        return Err(Error::Cancelled);
+    }
+    
+    let verification = if params.verify {
+        report_stage(Stage::Verifying, &pieces);
+        let piece_paths: Vec<_> = pieces.iter().map(|p| p.path.clone()).collect();
+        let report = verify_pieces(params, &piece_paths)?;
+        if !report.matched {
+            return Ok(SplitResult {
+                success: false,
+                file_count: pieces.len(),
+                error: Some(Error::VerifyFailed { reason: report.summary() }),
+                verification: Some(report),
+                manifest: None,
+                skipped_files: Vec::new(),
+                checkpoint_saved: false,
+                rejected_rows,
+                rejected_file: None,
+                adjusted_rows: pieces.iter().map(|p| p.adjusted_rows).sum(),
+                filtered_rows,
+                duplicate_rows,
+                split_groups,
+                inputs: Vec::new(),
+            });
+        }
+        Some(report)
+    } else {
+        None
+    };
+    
+    // 如果需要转换为Excel格式
+    if params.convert_to_excel {
+        report_stage(Stage::Converting, &pieces);
+        let csv_files: Vec<_> = pieces.iter().map(|p| p.path.clone()).collect();
+        if use_multithread {
+            // 在所有CSV文件生成后，并行转换为Excel
+            convert_all_csv_to_excel(csv_files)?;
+        } else {
+            convert_csv_files_to_excel(&csv_files)?;
+        }
+        // 两种转换方式都是将 <分块>.csv 转换为同名的 .xlsx
+        for piece in &mut pieces {
+            piece.path.set_extension("xlsx");
+        }
+    }
+    
+    report_stage(Stage::Finishing, &pieces);
+    let (commit, skipped) = commit_pieces(params, &mut pieces, &mut rejected_file)?;
+    let manifest = match write_manifest(params, &pieces) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            commit.rollback();
+            return Err(e);
+        }
+    };
+    
+    Ok(SplitResult {
+        success: true,
+        file_count: pieces.len(),
+        error: None,
+        verification,
+        manifest: Some(manifest),
+        skipped_files: skipped.iter().map(|p| p.display().to_string()).collect(),
+        checkpoint_saved: false,
+        rejected_rows,
+        rejected_file: rejected_file.map(|path| path.display().to_string()),
+        adjusted_rows: pieces.iter().map(|p| p.adjusted_rows).sum(),
+        filtered_rows,
+        duplicate_rows,
+        split_groups,
+        inputs: Vec::new(),
+    })
+}
+
+/// 内部CSV分割实现
+///
+/// `resume` 为上次中断时的断点，提供时跳过已完成的分块，从断点位置继续读取。
+fn split_csv_internal(
+    params: SplitParams,
+    resume: Option<Checkpoint>,
+    progress: &dyn ProgressListener,
+) -> Result<SplitOutput, Error> {
+    let input_path = Path::new(&params.input_path);
+    let output_dir = Path::new(&params.output_dir);
+    
+    // 验证输入文件存在
+    if !input_path.exists() {
+        return Err(Error::NotFound { path: params.input_path.clone() });
+    }
+    
+    // 验证输入文件扩展名与输入格式一致
+    let input_format = params.input_format();
+    let extension = input_path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase());
+    let extensions = input_format.extensions();
+    if !extensions.is_empty() && !extension.is_some_and(|ext| extensions.contains(&ext.as_str())) {
+        return Err(Error::UnsupportedFile { format: input_format });
+    }
+    
+    // 检查文件是否为空
+    let metadata = std::fs::metadata(input_path)
+        .map_err(|e| Error::io(input_path, e))?;
+    if metadata.len() == 0 {
+        return Err(Error::EmptyInput);
+    }
+    
+    // 创建输出目录（如果不存在）
+    if !output_dir.exists() {
+        std::fs::create_dir_all(output_dir)
+            .map_err(|e| Error::io(output_dir, e))?;
+    }
+    
+    // 检查输出目录是否可写
+    if std::fs::File::create(output_dir.join("test_write.tmp")).is_err() {
+        return Err(Error::PermissionDenied { path: params.output_dir.clone() });
+    } else {
+        let _ = std::fs::remove_file(output_dir.join("test_write.tmp"));
+    }
+    
+    // 验证行数参数
+    if params.rows_per_file == 0 {
+        return Err(Error::ZeroRowsPerFile);
+    }
+    
+    // 按输入格式打开记录源，标题行由记录源负责读取或生成
+    let mut source = open_source(&params)?;
+    let headers = resolve_headers(&params, source.headers())?;
+    // 计算列追加在末尾，筛选条件、去重和列选择都可以引用
+    let pipeline = RowPipeline::new(&params, &headers)?;
+    let output_headers = pipeline.headers();
+    let mut filtered_rows = 0;
+    // 标题行和每行数据写入前按列选择投影
+    let projection = Projection::new(&params, output_headers)?;
+    
+    // 记录断点需要记录源支持定位
+    let resuming = resume.is_some();
+    let mut checkpoint = match resume {
+        Some(checkpoint) => Some(checkpoint),
+        None if params.checkpoint => Some(Checkpoint::new(&params)?),
+        None => None,
+    };
+    if checkpoint.is_some() && source.position().is_none() {
+        return Err(Error::CheckpointUnsupported);
+    }
+    if checkpoint.is_some() && !params.sort.is_empty() {
+        return Err(Error::invalid_params(Message::new("split.sort_with_checkpoint")));
+    }
+    if checkpoint.is_some() && !params.group_by.is_empty() {
+        return Err(Error::invalid_params(Message::new("split.group_with_checkpoint")));
+    }
+    if params.max_rows_per_piece() < params.rows_per_file {
+        return Err(Error::invalid_params(
+            Message::new("split.max_rows_too_small")
+                .arg("max", params.max_rows_per_piece().to_string())
+                .arg("rows", params.rows_per_file.to_string()),
+        ));
+    }
+    // 暂存目录已创建，新任务先保存一份空的断点
+    if !resuming
+        && let Some(ref mut checkpoint) = checkpoint
+    {
+        checkpoint.save()?;
+    }
+    
+    let mut record = csv::StringRecord::new();
+    let mut record_count = 0;
+    
+    let mut current_progress = Progress::new(&params.input_path, Stage::Splitting, metadata.len());
+    
+    // 获取基础文件名（不含扩展名）
+    let file_stem = input_path.file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("output");
+    
+    // 格式错误的行按策略报错、跳过或写入暂存目录中的隔离文件
+    let mut bad_rows = BadRows::new(
+        params.bad_row_policy,
+        staging_dir(output_dir, file_stem).join(rejected_file_name(file_stem)),
+        Some(&headers),
+    );
+    
+    // 去重时先扫描一遍输入，找出要丢弃的重复行
+    let mut duplicates = Duplicates::scan(&params, &pipeline, &staging_dir(output_dir, file_stem), progress)?;
+    // 排序时先读完全部行，排好序后再写入分块
+    let mut sorter = Sorter::new(&params, output_headers, &staging_dir(output_dir, file_stem))?;
+    if sorter.is_some() {
+        current_progress.stage = Stage::Sorting;
+    }
+    let mut pieces = PieceWriter {
+        params: &params,
+        output_dir,
+        file_stem,
+        headers: output_headers,
+        projection: projection.as_ref(),
+        projected: csv::StringRecord::new(),
+        writer: None,
+        pieces: Vec::new(),
+        next_index: 1,
+        rows_in_piece: 0,
+        groups: Groups::new(&params, output_headers)?,
+        split_groups: 0,
+    };
+    
+    // 从断点继续：已完成的分块保留，从最后一个完好分块之后的位置读起
+    if let Some(ref checkpoint) = checkpoint {
+        if let Some(position) = checkpoint.resume_position() {
+            source.seek(position)?;
+        }
+        pieces.next_index = checkpoint.next_index();
+        record_count = checkpoint.rows_done();
+        pieces.pieces = checkpoint.piece_infos();
+        let (rejects_end, rejected_rows) = checkpoint.rejected();
+        bad_rows.resume(rejects_end, rejected_rows)?;
+        filtered_rows = checkpoint.filtered_rows();
+        if let Some(ref mut duplicates) = duplicates {
+            let (seen, count) = checkpoint.duplicates();
+            duplicates.resume(seen, count)?;
+        }
+    }
+    
+    loop {
+        let has_record = match source.read_record(&mut record) {
+            Ok(has_record) => has_record,
+            Err(e) => {
+                // 跳过的行也计入数据行号，分块的行号范围与原文件保持一致
+                bad_rows.handle(e, &record)?;
+                record_count += 1;
+                continue;
+            }
+        };
+        if !has_record {
+            break; // 文件结束
+        }
+        
+        record_count += 1;
+        if record_count % REPORT_EVERY_ROWS == 0 {
+            current_progress.rows = record_count;
+            current_progress.pieces = pieces.finished();
+            current_progress.bytes_read = source.position().map_or(0, |p| p.byte());
+            progress.on_progress(&current_progress);
+            if progress.is_cancelled() {
+                return Err(Error::Cancelled);
+            }
+        }
+        
+        // 不满足筛选条件的行和重复的行不写入，但计入数据行号
+        let adjusted = match pipeline.process(&mut record, record_count) {
+            RowOutcome::Filtered => {
+                filtered_rows += 1;
+                continue;
+            }
+            RowOutcome::Kept { adjusted } => adjusted,
+        };
+        if let Some(ref mut duplicates) = duplicates
+            && duplicates.is_duplicate()?
+        {
+            continue;
+        }
+        if let Some(ref mut sorter) = sorter {
+            sorter.push(&record, record_count, adjusted)?;
+            continue;
+        }
+        
+        // 达到每文件行数限制时关闭当前文件
+        if let Some(w) = pieces.write(&record, record_count, adjusted)? {
+            close_piece(w, checkpoint.as_mut(), &mut bad_rows, filtered_rows, duplicates.as_ref(), pieces.last(), source.position())?;
+        }
+    }
+    
+    if record_count == 0 {
+        return Err(Error::NoDataRows);
+    }
+    
+    // 按排序后的顺序写入分块，原文件已读完，这一阶段按已写入的行数占比估算进度
+    if let Some(sorter) = sorter {
+        let mut sorted = sorter.finish()?;
+        let total_rows = sorted.row_count().max(1) as u64;
+        current_progress.stage = Stage::Splitting;
+        current_progress.rows = 0;
+        while let Some(row) = sorted.next()? {
+            if let Some(w) = pieces.write(&row.record, row.row_number, row.adjusted)? {
+                close_piece(w, None, &mut bad_rows, filtered_rows, None, pieces.last(), None)?;
+            }
+            current_progress.rows += 1;
+            if current_progress.rows.is_multiple_of(REPORT_EVERY_ROWS) {
+                current_progress.pieces = pieces.finished();
+                current_progress.bytes_read = current_progress.total_bytes * current_progress.rows as u64 / total_rows;
+                progress.on_progress(&current_progress);
+                if progress.is_cancelled() {
+                    return Err(Error::Cancelled);
+                }
+            }
+        }
+    }
+    
+    // 确保最后一个文件被正确关闭
+    if let Some(w) = pieces.writer.take() {
+        close_piece(w, checkpoint.as_mut(), &mut bad_rows, filtered_rows, duplicates.as_ref(), pieces.last(), source.position())?;
+    }
+    bad_rows.flush()?;
+    
+    if pieces.pieces.is_empty() {
+        return Err(Error::NoDataRows);
+    }
+    
+    Ok(SplitOutput {
+        pieces: pieces.pieces,
+        rejected_rows: bad_rows.count(),
+        rejected_file: bad_rows.path().map(Path::to_path_buf),
+        filtered_rows,
+        duplicate_rows: duplicates.as_ref().map_or(0, Duplicates::count),
+        split_groups: pieces.split_groups,
+    })
+}
+
+/// 按每个文件的行数依次写入分块，标题行和数据行写入前按列选择投影
+struct PieceWriter<'a> {
+    params: &'a SplitParams,
+    output_dir: &'a Path,
+    file_stem: &'a str,
+    headers: &'a csv::StringRecord,
+    projection: Option<&'a Projection>,
+    projected: csv::StringRecord,
+    /// 正在写入的分块
+    writer: Option<Writer<BufWriter<File>>>,
+    pieces: Vec<PieceInfo>,
+    /// 下一个分块的序号
+    next_index: usize,
+    /// 当前分块已写入的行数
+    rows_in_piece: usize,
+    /// 按组分割时判断组的边界
+    groups: Option<Groups>,
+    /// 因超过最多行数而被切开的组数
+    split_groups: usize,
+}
+
+impl PieceWriter<'_> {
+    /// 写入一行，`row_number` 为在原文件中的数据行号；写满一个分块时返回该分块的写入器，由调用方关闭
+    ///
+    /// 按组分割时要读到下一组的第一行才知道分块在哪里结束，返回的是写入这一行之前结束的分块。
+    fn write(
+        &mut self,
+        record: &csv::StringRecord,
+        row_number: usize,
+        adjusted: bool,
+    ) -> Result<Option<Writer<BufWriter<File>>>, Error> {
+        let mut finished = None;
+        if let Some(ref mut groups) = self.groups {
+            let new_group = groups.starts_new(record);
+            if self.writer.is_some() {
+                let max_rows = self.params.max_rows_per_piece();
+                if new_group && self.rows_in_piece >= self.params.rows_per_file {
+                    finished = self.writer.take();
+                } else if self.rows_in_piece >= max_rows {
+                    // 一组的行数超过上限，只能从中间切开，每组只计一次
+                    if !groups.split {
+                        groups.split = true;
+                        self.split_groups += 1;
+                    }
+                    finished = self.writer.take();
+                }
+                if finished.is_some() {
+                    self.rows_in_piece = 0;
+                }
+            }
+        }
+        
+        let writer = match self.writer {
+            Some(ref mut writer) => writer,
+            None => {
+                let output_file = working_piece_path(self.output_dir, self.file_stem, self.next_index);
+                let file = File::create(&output_file)
+                    .map_err(|e| Error::io(&output_file, e))?;
+                let mut writer = WriterBuilder::new()
+                    .flexible(self.params.flexible)
+                    .from_writer(BufWriter::new(file));
+                writer.write_record(project(self.projection, self.headers, &mut self.projected))?;
+                self.pieces.push(PieceInfo {
+                    path: output_file,
+                    first_row: row_number,
+                    last_row: row_number,
+                    row_count: 0,
+                    adjusted_rows: 0,
+                });
+                self.next_index += 1;
+                self.writer.insert(writer)
+            }
+        };
+        writer.write_record(project(self.projection, record, &mut self.projected))?;
+        if let Some(piece) = self.pieces.last_mut() {
+            piece.last_row = row_number;
+            piece.row_count += 1;
+            piece.adjusted_rows += usize::from(adjusted);
+        }
+        
+        self.rows_in_piece += 1;
+        if self.groups.is_none() && self.rows_in_piece >= self.params.rows_per_file {
+            self.rows_in_piece = 0;
+            return Ok(self.writer.take());
+        }
+        Ok(finished)
+    }
+    
+    /// 已写完的分块数
+    fn finished(&self) -> usize {
+        self.pieces.len() - usize::from(self.writer.is_some())
+    }
+    
+    /// 最近开始写入的分块
+    fn last(&self) -> Option<&PieceInfo> {
+        self.pieces.last()
+    }
+}
+
+/// 按组分割时的分组键，连续的分组键相同的行为一组
+struct Groups {
+    positions: Vec<usize>,
+    /// 上一行的分组键
+    key: Vec<String>,
+    /// 当前组是否已被切开
+    split: bool,
+}
+
+impl Groups {
+    /// 没有指定分组列时返回 `None`，列名按处理后（含计算列）的标题行查找
+    fn new(params: &SplitParams, headers: &csv::StringRecord) -> Result<Option<Groups>, Error> {
+        if params.group_by.is_empty() {
+            return Ok(None);
+        }
+        let positions = params.group_by
+            .iter()
+            .map(|column| column.position(headers))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Some(Groups { positions, key: Vec::new(), split: false }))
+    }
+
+    /// 记下这一行的分组键，返回它是否开始了新的一组
+    fn starts_new(&mut self, record: &csv::StringRecord) -> bool {
+        let same = self.key.len() == self.positions.len()
+            && self.positions
+                .iter()
+                .zip(&self.key)
+                .all(|(&position, key)| record.get(position).unwrap_or("") == key);
+        if !same {
+            self.key = self.positions.iter().map(|&p| record.get(p).unwrap_or("").to_string()).collect();
+            self.split = false;
+        }
+        !same
+    }
+}
+
+/// 写完一个分块后关闭文件；记录断点时先把分块和隔离文件落盘再更新断点
+fn close_piece(
+    mut writer: Writer<BufWriter<File>>,
+    checkpoint: Option<&mut Checkpoint>,
+    bad_rows: &mut BadRows,
+    filtered_rows: usize,
+    duplicates: Option<&Duplicates>,
+    piece: Option<&PieceInfo>,
+    end: Option<csv::Position>,
+) -> Result<(), Error> {
+    writer.flush()?;
+    if let (Some(checkpoint), Some(piece), Some(end)) = (checkpoint, piece, end) {
+        writer.get_ref().get_ref().sync_all()?;
+        let rejects_end = bad_rows.flush()?;
+        let duplicates = duplicates.map_or((0, 0), Duplicates::state);
+        checkpoint.record_piece(piece, &end, rejects_end, bad_rows.count(), filtered_rows, duplicates)?;
+    }
+    Ok(())
+}
+
+/// 将分割后的CSV文件转换为Excel XLSX格式 - 优化版本
+fn convert_csv_files_to_excel(csv_files: &[PathBuf]) -> Result<(), Error> {
+    // 串行处理转换多个文件，避免并行复杂性
+    for csv_path in csv_files {
+        let xlsx_path = csv_path.with_extension("xlsx");
+        
+        if !csv_path.exists() {
+            continue;
+        }
+        
+        convert_csv_to_excel_minimal(csv_path, &xlsx_path)
+            .map_err(|e| excel_error(csv_path, e))?;
+        
+        // 删除原始CSV文件
+        std::fs::remove_file(csv_path).map_err(|e| Error::io(csv_path, e))?;
+    }
+    
+    Ok(())
+}
+
+/// 高性能CSV转Excel转换 - 使用大缓冲区和并行优化
+fn convert_csv_to_excel_fast(
+    csv_path: &Path,
+    xlsx_path: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // 使用大缓冲区提高IO性能
+    let file = File::open(csv_path)?;
+    let mut reader = ReaderBuilder::new()
+        .has_headers(true)
+        .flexible(true)
+        .from_reader(BufReader::with_capacity(8 * 1024 * 1024, file)); // 8MB缓冲区
+
+    let mut workbook = Workbook::new();
+    let worksheet = workbook.add_worksheet();
+
+    // 优化格式设置
+    let header_format = Format::new()
+        .set_bold()
+        .set_align(FormatAlign::Center)
+        .set_background_color("#D9E1F2");
+
+    let headers = reader.headers()?.clone();
+
+    // 批量写入标题行
+    for (col, header) in headers.iter().enumerate() {
+        worksheet.write_string_with_format(0, col as u16, header, &header_format)?;
+    }
+
+    // 批量处理数据
+    let mut row = 1;
+    let mut records = Vec::with_capacity(10000); // 预分配内存
+    
+    for record in reader.records() {
+        let record = record?;
+        records.push(record);
+        
+        // 每10000行批量写入一次
+        if records.len() >= 10000 {
+            for (offset, rec) in records.iter().enumerate() {
+                let current_row = row + offset as u32;
+                for (col, field) in rec.iter().enumerate() {
+                    if let Ok(num) = field.parse::<f64>() {
+                        worksheet.write_number(current_row, col as u16, num)?;
+                    } else {
+                        worksheet.write_string(current_row, col as u16, field)?;
+                    }
+                }
+            }
+            row += records.len() as u32;
+            records.clear();
+        }
+    }
+    
+    // 写入剩余数据
+    for (offset, rec) in records.iter().enumerate() {
+        let current_row = row + offset as u32;
+        for (col, field) in rec.iter().enumerate() {
+            if let Ok(num) = field.parse::<f64>() {
+                worksheet.write_number(current_row, col as u16, num)?;
+            } else {
+                worksheet.write_string(current_row, col as u16, field)?;
+            }
+        }
+    }
+
+    // 自动调整列宽
+    for col in 0..headers.len() {
+        worksheet.set_column_width(col as u16, 15)?;
+    }
+
+    workbook.save(xlsx_path)?;
+    Ok(())
+}
+
+/// 极低内存模式的CSV转Excel转换函数
+/// 
+/// 该函数采用流式处理方式，逐行读取CSV文件并写入Excel，
+/// 内存占用极低，适合处理超大文件
+fn convert_csv_to_excel_minimal(
+    csv_path: &Path,
+    xlsx_path: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // 打开CSV文件
+    let file = File::open(csv_path)?;
+    let mut reader = ReaderBuilder::new()
+        .has_headers(true)
+        .flexible(true)
+        .from_reader(BufReader::new(file));
+
+    // 创建Excel工作簿和工作表
+    let mut workbook = Workbook::new();
+    let worksheet = workbook.add_worksheet();
+
+    // 读取标题行
+    let headers = reader.headers()?;
+    for (col, header) in headers.iter().enumerate() {
+        worksheet.write_string(0, col as u16, header)?;
+    }
+
+    // 逐行写入数据
+    for (row, result) in (1..).zip(reader.records()) {
+        let record = result?;
+        for (col, field) in record.iter().enumerate() {
+            // 尝试解析为数字，失败则作为字符串处理
+            if let Ok(num) = field.parse::<f64>() {
+                worksheet.write_number(row, col as u16, num)?;
+            } else {
+                worksheet.write_string(row, col as u16, field)?;
+            }
+        }
+    }
+
+    // 保存Excel文件
+    workbook.save(xlsx_path)?;
+    Ok(())
+}
+
+/// 在所有CSV文件生成后，并行转换为Excel文件 - 高性能版本
+///
+/// 只转换本次任务生成的分块，不扫描输出目录，避免误转换、误删除目录中的其他文件。
+fn convert_all_csv_to_excel(csv_files: Vec<PathBuf>) -> Result<(), Error> {
+    if csv_files.is_empty() {
+        return Ok(());
+    }
+
+    // 根据CPU核心数决定并行线程数
+    let cpu_cores = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(4)
+        .min(8); // 最多8个并行线程
+
+    // 使用线程池并行转换
+    use std::thread;
+    let mut handles = vec![];
+
+    for csv_path in csv_files {
+        let handle = thread::spawn(move || -> Result<(), Error> {
+            let xlsx_path = csv_path.with_extension("xlsx");
+            convert_csv_to_excel_fast(&csv_path, &xlsx_path).map_err(|e| excel_error(&csv_path, e))?;
+            
+            // 转换完成后删除CSV文件
+            std::fs::remove_file(&csv_path).map_err(|e| Error::io(&csv_path, e))
+        });
+        
+        handles.push(handle);
+
+        // 控制并发数
+        if handles.len() >= cpu_cores {
+            for handle in handles.drain(..) {
+                handle.join().map_err(|_| Message::new("split.worker_panicked"))??;
+            }
+        }
+    }
+
+    // 等待剩余线程完成
+    for handle in handles {
+        handle.join().map_err(|_| Message::new("split.worker_panicked"))??;
+    }
+
+    Ok(())
+}
+
+/// 转换Excel出错，带上出错的分块
+fn excel_error(csv_path: &Path, error: Box<dyn std::error::Error>) -> Error {
+    Message::new("split.excel_failed")
+        .arg("path", csv_path.display().to_string())
+        .arg("reason", error.to_string())
+        .into()
+}
+
+/// 多线程并发CSV分割实现 - 真正的高性能版本
+/// 使用线程池处理200万行以上大文件
+    fn split_csv_multithread(params: SplitParams, progress: &dyn ProgressListener) -> Result<SplitOutput, Error> {
+        use std::sync::mpsc;
+    
+    
+    let input_path = Path::new(&params.input_path);
+    let output_dir = Path::new(&params.output_dir);
+    
+    // 验证输入文件存在
+    if !input_path.exists() {
+        return Err(Error::NotFound { path: params.input_path.clone() });
+    }
+    
+    // 创建输出目录（如果不存在）
+    if !output_dir.exists() {
+        std::fs::create_dir_all(output_dir)
+            .map_err(|e| Error::io(output_dir, e))?;
+    }
+    
+    // 验证行数参数
+    if params.rows_per_file == 0 {
+        return Err(Error::ZeroRowsPerFile);
+    }
+    
+    // 获取基础文件名
+    let file_stem = input_path.file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("output");
+    
+    // 使用内存映射快速计算总行数
+    let file = File::open(input_path).map_err(|e| Error::io(input_path, e))?;
+    let metadata = file.metadata()?;
+    let file_size = metadata.len();
+    
+    // 根据文件大小智能决定线程数，优化并发性能
+    let _thread_count = match file_size {
+        0..=50_000_000 => 2,            // < 50MB: 2线程
+        50_000_001..=200_000_000 => 4,  // 50MB-200MB: 4线程
+        200_000_001..=1_000_000_000 => 8, // 200MB-1GB: 8线程
+        _ => 12,                         // > 1GB: 12线程（充分利用CPU）
+    };
+    
+    // 使用内存映射文件进行高效处理
+    let mmap = unsafe { Mmap::map(&file)? };
+    let file_size = mmap.len();
+    
+    if file_size == 0 {
+        return Err(Error::EmptyInput);
+    }
+    
+    // 找到所有换行符的位置，用于精确分块
+    let mut line_breaks = Vec::new();
+    for (i, &byte) in mmap.iter().enumerate() {
+        if byte == b'\n' {
+            line_breaks.push(i);
+        }
+    }
+    
+    // 计算总行数
+    let mut total_lines = line_breaks.len();
+    if !line_breaks.is_empty() && line_breaks.last() != Some(&(file_size - 1)) {
+        total_lines += 1; // 处理最后一行
+    }
+    
+    // 如果有标题行，减去1
+    let data_lines = if params.has_header { total_lines.saturating_sub(1) } else { total_lines };
+    
+    if data_lines == 0 {
+        return Err(Error::NoDataRows);
+    }
+    
+    // 计算需要创建的文件数量
+    let file_count = data_lines.div_ceil(params.rows_per_file).max(1);
+    let rows_per_chunk = data_lines.div_ceil(file_count);
+    
+    // 读取标题行
+    let headers = {
+        let mut reader = ReaderBuilder::new()
+            .has_headers(params.has_header)
+            .flexible(params.flexible)
+            .from_reader(&mmap[..]);
+        
+        let mut first_record = csv::StringRecord::new();
+        let col_count = if reader.read_record(&mut first_record)? {
+            first_record.len()
+        } else {
+            0
+        };
+        
+        let headers = if params.has_header {
+            reader.headers()?.clone()
+        } else {
+            default_headers(col_count)
+        };
+        resolve_headers(&params, &headers)?
+    };
+    // 灵活模式按原有的列补齐，之后再追加计算列
+    let pipeline_arc = Arc::new(RowPipeline::new(&params, &headers)?);
+    
+    // 创建线程间通信通道
+    let (tx, rx) = mpsc::channel();
+    let projection_arc = Arc::new(Projection::new(&params, pipeline_arc.headers())?);
+    
+    // 计算每个线程的字节范围
+    let mut chunk_boundaries = Vec::new();
+    
+    // 确定起始位置
+    let data_start_pos = if params.has_header && !line_breaks.is_empty() {
+        line_breaks[0] + 1 // 跳过标题行
+    } else {
+        0
+    };
+    
+    chunk_boundaries.push(data_start_pos);
+    
+    // 计算每个分块的行边界
+    let start_line_idx = if params.has_header && !line_breaks.is_empty() { 1 } else { 0 };
+    for chunk_idx in 1..file_count {
+        // 下一分块从第 target_line 行开始，边界是上一行换行符之后的位置
+        let target_line = start_line_idx + chunk_idx * rows_per_chunk;
+        if target_line <= line_breaks.len() {
+            chunk_boundaries.push(line_breaks[target_line - 1] + 1);
+        } else {
+            chunk_boundaries.push(file_size);
+            break;
+        }
+    }
+    
+    if chunk_boundaries.len() <= file_count {
+        chunk_boundaries.push(file_size);
+    }
+    
+    let mut handles = vec![];
+    
+    // 使用线程池并行处理，根据文件大小动态调整
+    
+    // 启动并发处理线程
+    for file_index in 1..=file_count {
+        if file_index >= chunk_boundaries.len() {
+            break;
+        }
+        
+        let start_pos = chunk_boundaries[file_index - 1];
+        let end_pos = if file_index < chunk_boundaries.len() { 
+            chunk_boundaries[file_index] 
+        } else { 
+            file_size 
+        };
+        
+        if start_pos >= end_pos {
+            continue;
+        }
+        
+        let input_path = input_path.to_path_buf();
+        let output_dir = output_dir.to_path_buf();
+        let file_stem = file_stem.to_string();
+        let pipeline = Arc::clone(&pipeline_arc);
+        let projection = Arc::clone(&projection_arc);
+        // 分块之前的行数，用于把分块内的出错位置换算为文件中的行号
+        let lines_before = line_breaks.partition_point(|&b| b < start_pos) as u64;
+        let tx = tx.clone();
+        let params = params.clone();
+        let handle = thread::spawn(move || {
+            let result = (|| -> Result<ChunkOutput, Error> {
+                let output_file = working_piece_path(&output_dir, &file_stem, file_index);
+                let file = File::create(&output_file).map_err(|e| Error::io(&output_file, e))?;
+                let mut writer = WriterBuilder::new()
+                    .flexible(params.flexible)
+                    .from_writer(BufWriter::with_capacity(1024 * 1024, file)); // 增大到1MB缓冲区提高性能
+                
+                // 写入标题行，标题行和每行数据都按列选择投影
+                let mut projected = csv::StringRecord::new();
+                writer.write_record(project(projection.as_ref().as_ref(), pipeline.headers(), &mut projected))?;
+                
+                // 使用内存映射文件，按行读取数据
+                let file = File::open(&input_path).map_err(|e| Error::io(&input_path, e))?;
+                let mmap = unsafe { Mmap::map(&file)? };
+                
+                // 找到当前分块的起始行和结束行
+                
+                // 跳过标题行
+                let skip_lines = if params.has_header && start_pos == 0 { 1 } else { 0 };
+                
+                // 计算当前分块应该处理的行数
+                let target_rows = if file_index == file_count {
+                    // 最后一个分块处理剩余所有行
+                    rows_per_chunk + (data_lines % file_count).saturating_sub(1)
+                } else {
+                    rows_per_chunk
+                };
+                
+                // 使用CSV读取器逐行读取，确保正确处理CSV格式；UTF-8按记录校验，出错的行按策略处理
+                let chunk_data = &mmap[start_pos..std::cmp::min(end_pos, mmap.len())];
+                let mut reader = ReaderBuilder::new()
+                    .has_headers(false)
+                    .flexible(params.flexible)
+                    .from_reader(chunk_data);
+                
+                // 跳过标题行（如果是第一个分块）
+                if skip_lines > 0 {
+                    let mut temp_record = csv::StringRecord::new();
+                    reader.read_record(&mut temp_record)?;
+                }
+                
+                // 各线程先写各自的隔离文件，全部完成后按分块顺序合并
+                let mut bad_rows = BadRows::new(
+                    params.bad_row_policy,
+                    staging_dir(&output_dir, &file_stem).join(format!("{}_{}.rejected.part.csv", file_stem, file_index)),
+                    None,
+                );
+                let mut record = csv::StringRecord::new();
+                let mut output = ChunkOutput {
+                    path: output_file,
+                    rows_read: 0,
+                    rows_written: 0,
+                    adjusted_rows: 0,
+                    filtered_rows: 0,
+                    first_row: 0,
+                    last_row: 0,
+                    bytes: chunk_data.len() as u64,
+                    bad_rows: None,
+                };
+                
+                while output.rows_read < target_rows {
+                    match reader.read_record(&mut record) {
+                        Ok(false) => break,
+                        Ok(true) => {
+                            output.rows_read += 1;
+                            // 有筛选条件或用到原文件行号时不会走多线程，这里不会筛掉行，行号也不会被用到
+                            let adjusted = match pipeline.process(&mut record, 0) {
+                                RowOutcome::Filtered => {
+                                    output.filtered_rows += 1;
+                                    continue;
+                                }
+                                RowOutcome::Kept { adjusted } => adjusted,
+                            };
+                            output.adjusted_rows += usize::from(adjusted);
+                            writer.write_record(project(projection.as_ref().as_ref(), &record, &mut projected))?;
+                            output.rows_written += 1;
+                            if output.first_row == 0 {
+                                output.first_row = output.rows_read;
+                            }
+                            output.last_row = output.rows_read;
+                        }
+                        Err(e) => {
+                            bad_rows.handle(Error::from(e).shifted(lines_before, start_pos as u64), &record)?;
+                            output.rows_read += 1;
+                        }
+                    }
+                }
+                
+                writer.flush()?;
+                bad_rows.flush()?;
+                output.bad_rows = Some(bad_rows);
+                
+                Ok(output)
+            })();
+            
+            // 出错或取消时接收端已提前返回，结果不再需要
+            let _ = tx.send((file_index, result));
+        });
+        
+        handles.push(handle);
+    }
+    
+    drop(tx); // 关闭发送端
+    
+    // 等待所有线程完成
+    let mut completed_files = Vec::new();
+    let mut current_progress = Progress::new(&params.input_path, Stage::Splitting, file_size as u64);
+    // 各线程出错的顺序不固定，报告最靠前的分块中的错误，与单线程处理时遇到的第一处错误一致
+    let mut first_error: Option<(usize, Error)> = None;
+    for (file_index, result) in rx {
+        match result {
+            Ok(output) => {
+                // 各线程完成的顺序不固定，按已完成分块的总量报告进度
+                current_progress.rows += output.rows_written;
+                current_progress.pieces += 1;
+                current_progress.bytes_read += output.bytes;
+                completed_files.push((file_index, output));
+                progress.on_progress(&current_progress);
+                if progress.is_cancelled() {
+                    return Err(Error::Cancelled);
+                }
+            }
+            Err(e) => {
+                if first_error.as_ref().is_none_or(|(index, _)| file_index < *index) {
+                    first_error = Some((file_index, e));
+                }
+            }
+        }
+    }
+    
+    // 确保所有线程完成，线程异常退出时没有发回结果
+    for handle in handles {
+        handle.join().map_err(|_| Message::new("split.worker_panicked"))?;
+    }
+    if let Some((_, e)) = first_error {
+        return Err(e);
+    }
+    
+    // 按分块顺序累计行号，得到每个分块对应的原文件数据行范围；全部是出错的行的分块不输出
+    completed_files.sort_by_key(|(file_index, _)| *file_index);
+    let mut pieces = Vec::with_capacity(completed_files.len());
+    let mut bad_rows = BadRows::new(
+        params.bad_row_policy,
+        staging_dir(output_dir, file_stem).join(rejected_file_name(file_stem)),
+        Some(&headers),
+    );
+    let mut rows_before = 0;
+    let mut filtered_rows = 0;
+    for (_, output) in completed_files {
+        filtered_rows += output.filtered_rows;
+        if let Some(part) = output.bad_rows {
+            bad_rows.append(part)?;
+        }
+        if output.rows_written > 0 {
+            pieces.push(PieceInfo {
+                path: output.path,
+                first_row: rows_before + output.first_row,
+                last_row: rows_before + output.last_row,
+                row_count: output.rows_written,
+                adjusted_rows: output.adjusted_rows,
+            });
+        } else {
+            let _ = std::fs::remove_file(&output.path);
+        }
+        rows_before += output.rows_read;
+    }
+    bad_rows.flush()?;
+    
+    if pieces.is_empty() {
+        return Err(Error::NoDataRows);
+    }
+    
+    Ok(SplitOutput {
+        pieces,
+        rejected_rows: bad_rows.count(),
+        rejected_file: bad_rows.path().map(Path::to_path_buf),
+        filtered_rows,
+        duplicate_rows: 0,
+        split_groups: 0,
+    })
+}
+
+/// 多线程分割时单个线程的产出
+struct ChunkOutput {
+    path: PathBuf,
+    /// 读过的数据行数（含跳过的行）
+    rows_read: usize,
+    rows_written: usize,
+    adjusted_rows: usize,
+    filtered_rows: usize,
+    /// 第一行和最后一行写入的数据在本段中的行号（从1开始）
+    first_row: usize,
+    last_row: usize,
+    bytes: u64,
+    bad_rows: Option<BadRows>,
+}
+
+/// 命令行版本的入口，参数不含程序名，返回进程退出码
+pub fn run_cli(args: Vec<String>) -> i32 {
+    cli::run(args)
+}
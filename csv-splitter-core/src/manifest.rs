@@ -0,0 +1,183 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::i18n::Message;
+use crate::output::staging_dir;
+use crate::{Error, PieceInfo, SplitParams};
+
+/// 分割清单：记录每个分块对应原文件的哪些行，以及文件大小和校验和
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub input: InputManifest,
+    pub params: SplitParams,
+    pub total_rows: usize,
+    pub pieces: Vec<PieceManifest>,
+    /// 生成时间（Unix时间戳，秒）
+    pub created_at: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InputManifest {
+    pub path: String,
+    pub byte_size: u64,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PieceManifest {
+    pub path: String,
+    /// 第一行数据在原文件中的数据行号（从1开始，不含标题行）；
+    /// 排序后为分块中第一行和最后一行各自的原行号，不再表示连续的范围
+    pub first_row: usize,
+    pub last_row: usize,
+    pub row_count: usize,
+    /// 灵活模式下补齐或截断过的行数
+    #[serde(default)]
+    pub adjusted_rows: usize,
+    pub byte_size: u64,
+    pub sha256: String,
+}
+
+/// 清单文件名，按原文件名区分，避免同一输出目录中的多个任务互相覆盖
+pub(crate) fn manifest_path(output_dir: &Path, file_stem: &str) -> std::path::PathBuf {
+    output_dir.join(format!("{}_manifest.json", file_stem))
+}
+
+/// 读取输出目录中已有的清单文件
+pub(crate) fn read_manifest(output_dir: &Path, file_stem: &str) -> Result<Option<Manifest>, Error> {
+    let path = manifest_path(output_dir, file_stem);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let file = File::open(&path).map_err(|e| Error::io(&path, e))?;
+    serde_json::from_reader(BufReader::new(file))
+        .map(Some)
+        .map_err(|e| Error::Parse {
+            row: None,
+            byte: None,
+            reason: Message::new("manifest.invalid").arg("path", path.display().to_string()).arg("reason", e.to_string()),
+        })
+}
+
+/// 计算各分块的大小和校验和，生成清单并写入输出目录
+pub(crate) fn write_manifest(params: &SplitParams, pieces: &[PieceInfo]) -> Result<Manifest, Error> {
+    let input_path = Path::new(&params.input_path);
+    let (input_size, input_hash) = file_digest(input_path)?;
+
+    let mut piece_manifests = Vec::with_capacity(pieces.len());
+    for piece in pieces {
+        let (byte_size, sha256) = file_digest(&piece.path)?;
+        piece_manifests.push(PieceManifest {
+            path: piece.path.display().to_string(),
+            first_row: piece.first_row,
+            last_row: piece.last_row,
+            row_count: piece.row_count,
+            adjusted_rows: piece.adjusted_rows,
+            byte_size,
+            sha256,
+        });
+    }
+
+    let manifest = Manifest {
+        input: InputManifest {
+            path: params.input_path.clone(),
+            byte_size: input_size,
+            sha256: input_hash,
+        },
+        params: params.clone(),
+        total_rows: pieces.iter().map(|p| p.row_count).sum(),
+        pieces: piece_manifests,
+        created_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+    };
+
+    let file_stem = input_path.file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("output");
+    let output_dir = Path::new(&params.output_dir);
+    let path = manifest_path(output_dir, file_stem);
+
+    // 先写到暂存目录再移过去，写入中途失败时不会破坏已有的清单
+    let temp_path = staging_dir(output_dir, file_stem).join("manifest.json");
+    let file = File::create(&temp_path).map_err(|e| Error::io(&temp_path, e))?;
+    let mut writer = BufWriter::new(file);
+    serde_json::to_writer_pretty(&mut writer, &manifest)
+        .map_err(|e| Error::Io { path: Some(temp_path.display().to_string()), reason: e.to_string() })?;
+    writer.flush().map_err(|e| Error::io(&temp_path, e))?;
+    drop(writer);
+    std::fs::rename(&temp_path, &path).map_err(|e| Error::io(&path, e))?;
+
+    Ok(manifest)
+}
+
+/// 流式计算文件大小和SHA-256
+pub(crate) fn file_digest(path: &Path) -> Result<(u64, String), Error> {
+    let file = File::open(path).map_err(|e| Error::io(path, e))?;
+    let mut reader = BufReader::with_capacity(1024 * 1024, file);
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 1024 * 1024];
+    let mut byte_size = 0u64;
+
+    loop {
+        let bytes_read = reader.read(&mut buffer).map_err(|e| Error::io(path, e))?;
+        if bytes_read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..bytes_read]);
+        byte_size += bytes_read as u64;
+    }
+
+    Ok((byte_size, format!("{:x}", hasher.finalize())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::prepare_staging;
+
+    #[test]
+    fn manifest_records_pieces_and_digests() {
+        let dir = std::env::temp_dir().join(format!("csv-splitter-manifest-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("sales.csv");
+        std::fs::write(&input, "id\n1\n2\n3\n").unwrap();
+        let params: SplitParams = serde_json::from_value(serde_json::json!({
+            "input_path": input.display().to_string(),
+            "output_dir": dir.display().to_string(),
+            "rows_per_file": 2,
+            "has_header": true,
+            "convert_to_excel": false,
+        }))
+        .unwrap();
+        prepare_staging(&params).unwrap();
+        let pieces: Vec<PieceInfo> = [("sales_1.csv", "id\n1\n2\n", 1, 2), ("sales_2.csv", "abc", 3, 3)]
+            .into_iter()
+            .map(|(name, content, first_row, last_row)| {
+                let path = dir.join(name);
+                std::fs::write(&path, content).unwrap();
+                PieceInfo { path, first_row, last_row, row_count: last_row - first_row + 1, adjusted_rows: 0 }
+            })
+            .collect();
+
+        let manifest = write_manifest(&params, &pieces).unwrap();
+        assert_eq!(manifest.total_rows, 3);
+        assert_eq!((manifest.input.byte_size, manifest.input.sha256.len()), (9, 64));
+        let second = &manifest.pieces[1];
+        assert_eq!((second.first_row, second.last_row, second.row_count, second.byte_size), (3, 3, 1, 3));
+        assert_eq!(second.sha256, "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert_eq!(file_digest(&pieces[0].path).unwrap(), (manifest.pieces[0].byte_size, manifest.pieces[0].sha256.clone()));
+
+        // 写入的清单文件与返回值一致，暂存目录中不留下临时文件
+        let saved = read_manifest(&dir, "sales").unwrap().unwrap();
+        assert_eq!(serde_json::to_value(&saved).unwrap(), serde_json::to_value(&manifest).unwrap());
+        assert!(!staging_dir(&dir, "sales").join("manifest.json").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
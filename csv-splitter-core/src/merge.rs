@@ -0,0 +1,451 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use csv::{ReaderBuilder, StringRecord, Writer, WriterBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::i18n::Message;
+use crate::Error;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MergeParams {
+    pub input_paths: Vec<String>,
+    pub output_path: String,
+    pub has_header: bool,
+    #[serde(default)]
+    pub header_mode: HeaderMode,
+    /// 列名别名映射（旧列名 -> 统一后的列名），用于对齐不同时期改过名的列
+    #[serde(default)]
+    pub column_aliases: HashMap<String, String>,
+    /// 按列名合并时，文件中缺失的列使用的填充值
+    #[serde(default)]
+    pub fill_value: String,
+}
+
+/// 多个文件标题行不一致时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HeaderMode {
+    /// 所有文件的标题行必须与第一个文件完全一致
+    #[default]
+    Strict,
+    /// 按列名合并所有文件的列，缺失的列使用填充值
+    Union,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MergeResult {
+    pub success: bool,
+    pub row_count: usize,
+    pub files: Vec<MergeFileReport>,
+    pub error: Option<Error>,
+}
+
+/// 单个输入文件的合并情况
+#[derive(Debug, Serialize)]
+pub struct MergeFileReport {
+    pub path: String,
+    pub row_count: usize,
+    /// 按列名合并时该文件缺少的列
+    pub missing_columns: Vec<String>,
+}
+
+/// 合并CSV文件的内部实现，返回每个输入文件的行数
+pub(crate) fn merge_csv_internal(params: &MergeParams) -> Result<Vec<MergeFileReport>, Error> {
+    if params.input_paths.is_empty() {
+        return Err(Error::invalid_params(Message::new("merge.no_inputs")));
+    }
+
+    if params.header_mode == HeaderMode::Union && !params.has_header {
+        return Err(Error::invalid_params(Message::new("merge.union_requires_header")));
+    }
+
+    // 按文件名中的数字自然排序，保证 _2 排在 _10 之前
+    let mut input_paths = params.input_paths.clone();
+    input_paths.sort_by(|a, b| natural_cmp(a, b));
+
+    let output_path = Path::new(&params.output_path);
+    for input in &input_paths {
+        let input_path = Path::new(input);
+        if !input_path.exists() {
+            return Err(Error::NotFound { path: input.clone() });
+        }
+        if same_file(input_path, output_path) {
+            return Err(Error::invalid_params(Message::new("merge.output_is_input").arg("path", input.as_str())));
+        }
+    }
+
+    // 先读取所有文件的标题行（已按别名统一列名），确定输出的列
+    let mut file_headers = Vec::with_capacity(input_paths.len());
+    if params.has_header {
+        for input in &input_paths {
+            let headers = read_headers(Path::new(input))?;
+            file_headers.push(apply_aliases(input, &headers, params)?);
+        }
+    }
+    let headers = if params.has_header {
+        Some(merged_headers(&input_paths, &file_headers, params.header_mode)?)
+    } else {
+        None
+    };
+
+    // 创建输出目录（如果不存在）
+    if let Some(parent) = output_path.parent()
+        && !parent.as_os_str().is_empty()
+        && !parent.exists()
+    {
+        std::fs::create_dir_all(parent).map_err(|e| Error::io(parent, e))?;
+    }
+
+    // 先写入同一目录下的临时文件，全部写完后再改名，出错时不留下写了一半的输出文件
+    let partial_path = partial_path(output_path);
+    let file = File::create(&partial_path).map_err(|e| Error::io(output_path, e))?;
+    let mut writer = WriterBuilder::new()
+        .flexible(!params.has_header)
+        .from_writer(BufWriter::new(file));
+    let written = write_rows(params, &input_paths, headers.as_ref(), &file_headers, &mut writer).and_then(|reports| {
+        writer.flush().map_err(|e| Error::io(output_path, e))?;
+        Ok(reports)
+    });
+    drop(writer);
+    let result = written.and_then(|reports| {
+        std::fs::rename(&partial_path, output_path).map_err(|e| Error::io(output_path, e))?;
+        Ok(reports)
+    });
+    if result.is_err() {
+        let _ = std::fs::remove_file(&partial_path);
+    }
+    result
+}
+
+/// 写入输出文件的临时文件，位于输出文件所在目录，改名时不会跨文件系统
+fn partial_path(output_path: &Path) -> PathBuf {
+    let name = output_path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    output_path.with_file_name(format!(".{}.partial", name))
+}
+
+/// 依次写入标题行和所有输入文件的行，返回每个输入文件的行数
+fn write_rows<W: Write>(
+    params: &MergeParams,
+    input_paths: &[String],
+    headers: Option<&StringRecord>,
+    file_headers: &[StringRecord],
+    writer: &mut Writer<W>,
+) -> Result<Vec<MergeFileReport>, Error> {
+    let output_path = Path::new(&params.output_path);
+
+    // 标题行只写一次
+    if let Some(headers) = headers {
+        writer.write_record(headers).map_err(|e| write_error(output_path, e))?;
+    }
+
+    let mut reports = Vec::with_capacity(input_paths.len());
+    let mut record = StringRecord::new();
+    let mut output_record = StringRecord::new();
+
+    for (file_index, input) in input_paths.iter().enumerate() {
+        let file = File::open(input).map_err(|e| Error::io(Path::new(input), e))?;
+        let mut reader = ReaderBuilder::new()
+            .has_headers(params.has_header)
+            .flexible(!params.has_header)
+            .from_reader(BufReader::new(file));
+
+        // 记录每个输出列在当前文件中的位置
+        let mut missing_columns = Vec::new();
+        let column_map: Option<Vec<Option<usize>>> = match headers {
+            Some(headers) if params.header_mode == HeaderMode::Union => {
+                let positions: HashMap<&str, usize> = file_headers[file_index]
+                    .iter()
+                    .enumerate()
+                    .map(|(i, name)| (name, i))
+                    .collect();
+                let column_map: Vec<Option<usize>> = headers
+                    .iter()
+                    .map(|name| positions.get(name).copied())
+                    .collect();
+                missing_columns = headers
+                    .iter()
+                    .zip(&column_map)
+                    .filter(|(_, position)| position.is_none())
+                    .map(|(name, _)| name.to_string())
+                    .collect();
+                Some(column_map)
+            }
+            _ => None,
+        };
+
+        let mut row_count = 0;
+        loop {
+            let has_record = reader.read_record(&mut record).map_err(|e| read_error(input, e))?;
+            if !has_record {
+                break;
+            }
+
+            let result = match column_map {
+                Some(ref column_map) => {
+                    output_record.clear();
+                    for position in column_map {
+                        output_record.push_field(
+                            position.and_then(|i| record.get(i)).unwrap_or(&params.fill_value)
+                        );
+                    }
+                    writer.write_record(&output_record)
+                }
+                None => writer.write_record(&record),
+            };
+            result.map_err(|e| write_error(output_path, e))?;
+            row_count += 1;
+        }
+
+        reports.push(MergeFileReport {
+            path: input.clone(),
+            row_count,
+            missing_columns,
+        });
+    }
+
+    Ok(reports)
+}
+
+fn read_headers(input_path: &Path) -> Result<StringRecord, Error> {
+    let file = File::open(input_path).map_err(|e| Error::io(input_path, e))?;
+    let mut reader = ReaderBuilder::new()
+        .has_headers(true)
+        .from_reader(BufReader::new(file));
+    reader.headers()
+        .cloned()
+        .map_err(|e| read_error(&input_path.display().to_string(), e))
+}
+
+/// 读取输入文件出错：IO错误带上文件路径，格式错误带上行号
+fn read_error(input: &str, error: csv::Error) -> Error {
+    match Error::from(error) {
+        Error::Io { reason, .. } => Error::Io { path: Some(input.to_string()), reason },
+        Error::Parse { row, byte, reason } => Error::Parse {
+            row,
+            byte,
+            reason: Message::new("merge.read_failed").arg("path", input).arg("reason", reason),
+        },
+        other => other,
+    }
+}
+
+/// 写入输出文件出错
+fn write_error(output_path: &Path, error: csv::Error) -> Error {
+    match Error::from(error) {
+        Error::Io { reason, .. } => Error::Io { path: Some(output_path.display().to_string()), reason },
+        other => other,
+    }
+}
+
+/// 按别名映射统一列名
+///
+/// 按列名合并时列名必须唯一，统一后出现重名列时报错。
+fn apply_aliases(
+    input: &str,
+    headers: &StringRecord,
+    params: &MergeParams,
+) -> Result<StringRecord, Error> {
+    let mut renamed = StringRecord::new();
+    for name in headers.iter() {
+        let canonical = params.column_aliases.get(name).map(|s| s.as_str()).unwrap_or(name);
+        if params.header_mode == HeaderMode::Union && renamed.iter().any(|existing| existing == canonical) {
+            return Err(Error::invalid_params(
+                Message::new("merge.duplicate_column").arg("path", input).arg("name", canonical),
+            ));
+        }
+        renamed.push_field(canonical);
+    }
+    Ok(renamed)
+}
+
+/// 根据合并模式计算输出的标题行
+fn merged_headers(
+    input_paths: &[String],
+    file_headers: &[StringRecord],
+    mode: HeaderMode,
+) -> Result<StringRecord, Error> {
+    let first = &file_headers[0];
+    match mode {
+        HeaderMode::Strict => {
+            for (input, headers) in input_paths.iter().zip(file_headers).skip(1) {
+                if headers != first {
+                    return Err(Error::invalid_params(
+                        Message::new("merge.header_mismatch").arg("path", input.as_str()).arg("first", input_paths[0].as_str()),
+                    ));
+                }
+            }
+            Ok(first.clone())
+        }
+        HeaderMode::Union => {
+            // 列顺序按列名首次出现的顺序
+            let mut merged = StringRecord::new();
+            for headers in file_headers {
+                for name in headers.iter() {
+                    if !merged.iter().any(|existing| existing == name) {
+                        merged.push_field(name);
+                    }
+                }
+            }
+            Ok(merged)
+        }
+    }
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// 自然排序：连续的数字按数值比较，其余字符按原样比较
+pub(crate) fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a_chars = a.chars().peekable();
+    let mut b_chars = b.chars().peekable();
+
+    loop {
+        match (a_chars.peek().copied(), b_chars.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let x_digits = take_digits(&mut a_chars);
+                let y_digits = take_digits(&mut b_chars);
+                let x_trimmed = x_digits.trim_start_matches('0');
+                let y_trimmed = y_digits.trim_start_matches('0');
+                let ordering = x_trimmed.len()
+                    .cmp(&y_trimmed.len())
+                    .then_with(|| x_trimmed.cmp(y_trimmed))
+                    .then_with(|| x_digits.len().cmp(&y_digits.len()));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(&y);
+                }
+                a_chars.next();
+                b_chars.next();
+            }
+        }
+    }
+}
+
+fn take_digits(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    let mut digits = String::new();
+    while let Some(&c) = chars.peek() {
+        if !c.is_ascii_digit() {
+            break;
+        }
+        digits.push(c);
+        chars.next();
+    }
+    digits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn natural_cmp_orders_numbers_by_value() {
+        let mut names = vec!["data_10.csv", "data_2.csv", "data_1.csv", "data_02.csv", "Data_3.csv", "data_1a.csv", "data_.csv"];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(names, ["Data_3.csv", "data_.csv", "data_1.csv", "data_1a.csv", "data_2.csv", "data_02.csv", "data_10.csv"]);
+        assert_eq!(natural_cmp("a99999999999999999999999", "a100000000000000000000000"), Ordering::Less);
+        assert_eq!(natural_cmp("a007", "a007"), Ordering::Equal);
+    }
+
+    #[test]
+    fn failed_merge_leaves_no_output() {
+        let dir = std::env::temp_dir().join(format!("csv-splitter-merge-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let good = dir.join("a_1.csv");
+        let bad = dir.join("a_2.csv");
+        let output = dir.join("merged.csv");
+        std::fs::write(&good, "id,name\n1,x\n").unwrap();
+        std::fs::write(&bad, "id,name\n2,y,extra\n").unwrap();
+        std::fs::write(&output, "old\n").unwrap();
+        let mut params = MergeParams {
+            input_paths: vec![bad.display().to_string(), good.display().to_string()],
+            output_path: output.display().to_string(),
+            has_header: true,
+            header_mode: HeaderMode::Strict,
+            column_aliases: HashMap::new(),
+            fill_value: String::new(),
+        };
+
+        assert_eq!(merge_csv_internal(&params).unwrap_err().code(), "field_count");
+        assert_eq!(std::fs::read_to_string(&output).unwrap(), "old\n");
+        assert!(!partial_path(&output).exists());
+
+        params.input_paths.remove(0);
+        let reports = merge_csv_internal(&params).unwrap();
+        assert_eq!(reports[0].row_count, 1);
+        assert_eq!(std::fs::read_to_string(&output).unwrap(), "id,name\n1,x\n");
+        assert!(!partial_path(&output).exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn union_merge_aligns_columns_by_name() {
+        let dir = std::env::temp_dir().join(format!("csv-splitter-union-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let input = |name: &str, content: &str| {
+            let path = dir.join(name);
+            std::fs::write(&path, content).unwrap();
+            path.display().to_string()
+        };
+        // 第2个文件调换了列的顺序、少了 city、多了 email；第3个文件把 name 改名为 customer
+        let inputs = vec![
+            input("part_10.csv", "id,customer,phone\n3,C,555\n"),
+            input("part_2.csv", "name,id,email\nB,2,b@x\n"),
+            input("part_1.csv", "id,name,city\n1,A,X\n4,D,Y\n"),
+        ];
+        let output = dir.join("merged.csv");
+        let mut params = MergeParams {
+            input_paths: inputs.clone(),
+            output_path: output.display().to_string(),
+            has_header: true,
+            header_mode: HeaderMode::Union,
+            column_aliases: HashMap::from([("customer".to_string(), "name".to_string())]),
+            fill_value: "-".into(),
+        };
+
+        let reports = merge_csv_internal(&params).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&output).unwrap(),
+            "id,name,city,email,phone\n1,A,X,-,-\n4,D,Y,-,-\n2,B,-,b@x,-\n3,C,-,-,555\n",
+        );
+        let summary: Vec<_> = reports
+            .iter()
+            .map(|r| (Path::new(&r.path).file_name().unwrap().to_str().unwrap(), r.row_count, r.missing_columns.clone()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("part_1.csv", 2, vec!["email".to_string(), "phone".to_string()]),
+                ("part_2.csv", 1, vec!["city".to_string(), "phone".to_string()]),
+                ("part_10.csv", 1, vec!["city".to_string(), "email".to_string()]),
+            ],
+        );
+
+        // 别名与已有的列重名
+        params.input_paths = vec![input("clash.csv", "name,customer\nA,B\n")];
+        let error = merge_csv_internal(&params).unwrap_err();
+        assert_eq!(error.code(), "invalid_params");
+        assert!(format!("{:?}", error).contains("merge.duplicate_column"), "{:?}", error);
+
+        // 严格模式下标题行不一致
+        params.input_paths = inputs;
+        params.header_mode = HeaderMode::Strict;
+        params.column_aliases.clear();
+        assert!(format!("{:?}", merge_csv_internal(&params).unwrap_err()).contains("merge.header_mismatch"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
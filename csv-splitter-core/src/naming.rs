@@ -0,0 +1,341 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local};
+use regex::Regex;
+
+use crate::i18n::Message;
+use crate::Error;
+
+/// 未指定命名模板时使用的默认模板，与早期版本的 `原文件名_序号` 保持一致
+pub(crate) const DEFAULT_TEMPLATE: &str = "{stem}_{index}";
+
+/// Windows下不能出现在文件名中的字符
+const ILLEGAL_CHARS: &[char] = &['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+/// Windows保留的设备名
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL",
+    "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// 输出文件命名模板
+///
+/// 支持的占位符：`{stem}` 原文件名、`{index}` 分块序号、`{total}` 分块总数、`{date}` 当天日期、
+/// `{first_row}` / `{last_row}` 分块对应的原文件数据行号。
+/// 数字占位符可指定补零宽度，如 `{index:04}`；`{date}` 可指定格式，如 `{date:%Y-%m-%d}`，
+/// 格式中不能出现文件名不允许的字符（如 `%H:%M`、`%m/%d`）。
+/// 模板不含扩展名，`{{` 和 `}}` 表示字面的花括号。
+#[derive(Debug, Clone)]
+pub(crate) struct NameTemplate {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone)]
+enum Part {
+    Literal(String),
+    Stem,
+    Index(usize),
+    Total(usize),
+    Date(String),
+    FirstRow(usize),
+    LastRow(usize),
+}
+
+/// 渲染单个分块文件名所需的信息
+pub(crate) struct NameContext<'a> {
+    pub(crate) stem: &'a str,
+    pub(crate) index: usize,
+    pub(crate) total: usize,
+    pub(crate) first_row: usize,
+    pub(crate) last_row: usize,
+    /// 任务开始时间，同一任务的所有分块使用相同的日期
+    pub(crate) started_at: DateTime<Local>,
+}
+
+impl NameTemplate {
+    pub(crate) fn parse(template: &str) -> Result<NameTemplate, Error> {
+        let parts = parse_parts(template)?;
+
+        // 模板中的固定文本不能包含路径分隔符或非法字符，防止写出到输出目录之外
+        for part in &parts {
+            if let Part::Literal(text) = part
+                && (text.contains(ILLEGAL_CHARS) || text.chars().any(|c| c.is_control()))
+            {
+                return Err(invalid("naming.illegal_template", template));
+            }
+        }
+
+        if parts.is_empty() {
+            return Err(Error::invalid_params(Message::new("naming.empty_template")));
+        }
+
+        Ok(NameTemplate { parts })
+    }
+
+    /// 解析没有标题行时生成列名的模板：只支持 `{index}`（从1开始的列序号，可指定补零宽度），
+    /// 列名不受文件名的字符限制
+    pub(crate) fn parse_column_template(template: &str) -> Result<NameTemplate, Error> {
+        let parts = parse_parts(template)?;
+        if parts.iter().any(|part| !matches!(part, Part::Literal(_) | Part::Index(_))) {
+            return Err(invalid("header.only_index", template));
+        }
+        if !parts.iter().any(|part| matches!(part, Part::Index(_))) {
+            return Err(invalid("header.index_required", template));
+        }
+        Ok(NameTemplate { parts })
+    }
+
+    /// 按列名模板生成第 `index` 列的列名
+    pub(crate) fn render_column(&self, index: usize) -> String {
+        let mut name = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => name.push_str(text),
+                Part::Index(width) => name.push_str(&pad(index, *width)),
+                _ => {}
+            }
+        }
+        name
+    }
+
+    /// 模板开头的固定文本（`{stem}` 会被替换），用于定位本任务可能产生的文件
+    pub(crate) fn prefix(&self, stem: &str) -> String {
+        let mut prefix = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => prefix.push_str(text),
+                Part::Stem => prefix.push_str(stem),
+                _ => break,
+            }
+        }
+        prefix
+    }
+
+    /// 文件名（不含扩展名）是否可能由本模板生成，数字占位符匹配任意数字，日期匹配任意文本
+    pub(crate) fn matches(&self, stem: &str, name: &str) -> bool {
+        let mut pattern = String::from("^");
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => pattern.push_str(&regex::escape(text)),
+                Part::Stem => pattern.push_str(&regex::escape(stem)),
+                Part::Index(width) | Part::Total(width) | Part::FirstRow(width) | Part::LastRow(width) => {
+                    pattern.push_str(&format!(r"\d{{{},}}", (*width).max(1)));
+                }
+                Part::Date(_) => pattern.push_str(".+?"),
+            }
+        }
+        pattern.push('$');
+        Regex::new(&pattern).is_ok_and(|regex| regex.is_match(name))
+    }
+
+    /// 渲染文件名（不含扩展名）并校验其合法性
+    pub(crate) fn render(&self, ctx: &NameContext) -> Result<String, Error> {
+        let mut name = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => name.push_str(text),
+                Part::Stem => name.push_str(ctx.stem),
+                Part::Index(width) => name.push_str(&pad(ctx.index, *width)),
+                Part::Total(width) => name.push_str(&pad(ctx.total, *width)),
+                Part::Date(format) => name.push_str(&ctx.started_at.format(format).to_string()),
+                Part::FirstRow(width) => name.push_str(&pad(ctx.first_row, *width)),
+                Part::LastRow(width) => name.push_str(&pad(ctx.last_row, *width)),
+            }
+        }
+        validate_file_name(&name)?;
+        Ok(name)
+    }
+}
+
+/// 把模板拆分为固定文本和占位符，`{{` 和 `}}` 表示字面的花括号
+fn parse_parts(template: &str) -> Result<Vec<Part>, Error> {
+    let mut parts = Vec::new();
+    let mut literal = String::new();
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                literal.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                literal.push('}');
+            }
+            '{' => {
+                let mut placeholder = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => placeholder.push(c),
+                        None => return Err(invalid("naming.unclosed_placeholder", template)),
+                    }
+                }
+                if !literal.is_empty() {
+                    parts.push(Part::Literal(std::mem::take(&mut literal)));
+                }
+                parts.push(parse_placeholder(&placeholder)?);
+            }
+            '}' => return Err(invalid("naming.unmatched_brace", template)),
+            c => literal.push(c),
+        }
+    }
+    if !literal.is_empty() {
+        parts.push(Part::Literal(literal));
+    }
+    Ok(parts)
+}
+
+fn parse_placeholder(placeholder: &str) -> Result<Part, Error> {
+    let (name, spec) = match placeholder.split_once(':') {
+        Some((name, spec)) => (name.trim(), Some(spec)),
+        None => (placeholder.trim(), None),
+    };
+
+    let width = || -> Result<usize, Error> {
+        match spec {
+            None => Ok(0),
+            Some(spec) => spec.trim().parse::<usize>()
+                .map_err(|_| invalid("naming.invalid_width", placeholder)),
+        }
+    };
+
+    match name {
+        "stem" if spec.is_some() => Err(invalid("naming.unexpected_spec", name)),
+        "stem" => Ok(Part::Stem),
+        "index" => Ok(Part::Index(width()?)),
+        "total" => Ok(Part::Total(width()?)),
+        "first_row" => Ok(Part::FirstRow(width()?)),
+        "last_row" => Ok(Part::LastRow(width()?)),
+        "date" => {
+            let format = spec.unwrap_or("%Y%m%d").to_string();
+            if StrftimeItems::new(&format).any(|item| matches!(item, Item::Error)) {
+                return Err(invalid("naming.invalid_date_format", &format));
+            }
+            // 日期格式在分割结束时才渲染，生成非法字符的格式要在开始前拒绝
+            let sample = Local::now().format(&format).to_string();
+            if sample.contains(ILLEGAL_CHARS) || sample.chars().any(|c| c.is_control()) {
+                return Err(invalid("naming.illegal_date_format", &format));
+            }
+            Ok(Part::Date(format))
+        }
+        _ => Err(invalid("naming.unknown_placeholder", placeholder)),
+    }
+}
+
+/// 模板或文件名无效，`value` 为出错的模板、占位符或文件名
+fn invalid(key: &'static str, value: &str) -> Error {
+    Error::invalid_params(Message::new(key).arg("value", value))
+}
+
+/// 按宽度补零，宽度为0时原样输出
+fn pad(value: usize, width: usize) -> String {
+    format!("{:0width$}", value, width = width)
+}
+
+/// 校验渲染后的文件名是否可以安全地创建在输出目录中
+pub(crate) fn validate_file_name(name: &str) -> Result<(), Error> {
+    if name.trim().is_empty() {
+        return Err(Error::invalid_params(Message::new("naming.empty_name")));
+    }
+    if name.contains(ILLEGAL_CHARS) || name.chars().any(|c| c.is_control()) {
+        return Err(invalid("naming.illegal_name", name));
+    }
+    if name == "." || name == ".." {
+        return Err(invalid("naming.invalid_name", name));
+    }
+    if name.ends_with('.') || name.ends_with(' ') {
+        return Err(invalid("naming.trailing_dot", name));
+    }
+    let base = name.split('.').next().unwrap_or(name).to_ascii_uppercase();
+    if RESERVED_NAMES.contains(&base.as_str()) {
+        return Err(invalid("naming.reserved_name", name));
+    }
+    // 确认生成的名称只有一级，不会跳出输出目录
+    if Path::new(name).components().count() != 1 {
+        return Err(invalid("naming.invalid_name", name));
+    }
+    Ok(())
+}
+
+/// 检查一批文件名中是否有重复（不区分大小写，兼容Windows文件系统）
+pub(crate) fn ensure_unique(names: &[String]) -> Result<(), Error> {
+    let mut seen = HashSet::new();
+    for name in names {
+        if !seen.insert(name.to_lowercase()) {
+            return Err(invalid("naming.duplicate_name", name));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn render(template: &str) -> Result<String, Error> {
+        NameTemplate::parse(template)?.render(&NameContext {
+            stem: "sales",
+            index: 7,
+            total: 12,
+            first_row: 61,
+            last_row: 70,
+            started_at: Local.with_ymd_and_hms(2024, 3, 9, 8, 0, 0).unwrap(),
+        })
+    }
+
+    #[test]
+    fn placeholders_render_with_padding_and_formats() {
+        assert_eq!(render(DEFAULT_TEMPLATE).unwrap(), "sales_7");
+        assert_eq!(render("{stem}-{index:03}-of-{total:3}").unwrap(), "sales-007-of-012");
+        assert_eq!(render("{first_row}_{last_row:5}").unwrap(), "61_00070");
+        assert_eq!(render("{date}_{date:%Y-%m-%d}").unwrap(), "20240309_2024-03-09");
+        assert_eq!(render("{{{stem}}}").unwrap(), "{sales}");
+    }
+
+    #[test]
+    fn invalid_templates_are_rejected() {
+        for template in [
+            "", "{stem", "stem}", "{size}", "{index:x}", "{stem:3}", "a/{index}", "{date:%Q}", "{partition}",
+            "{date:%H:%M}", "{date:%m/%d}", "{date:%D}", "{date:%T}",
+        ] {
+            assert_eq!(NameTemplate::parse(template).err().map(|e| e.code()), Some("invalid_params"), "{}", template);
+        }
+        for template in ["CON", "{stem}."] {
+            assert!(render(template).is_err(), "{}", template);
+        }
+    }
+
+    #[test]
+    fn prefix_and_matches_follow_the_template() {
+        let template = NameTemplate::parse("{stem}_part{index:02}_{date}").unwrap();
+        assert_eq!(template.prefix("sales"), "sales_part");
+        assert!(template.matches("sales", "sales_part07_20240309"));
+        assert!(template.matches("sales", "sales_part123_x"));
+        assert!(!template.matches("sales", "sales_part7_20240309"));
+        assert!(!template.matches("sales", "sales_partner_list"));
+        assert!(!template.matches("sales", "sales_part07"));
+        assert_eq!(NameTemplate::parse("{index}").unwrap().prefix("sales"), "");
+    }
+
+    #[test]
+    fn column_templates_only_take_index() {
+        let template = NameTemplate::parse_column_template("col/{index:03}.").unwrap();
+        assert_eq!(template.render_column(7), "col/007.");
+        assert_eq!(NameTemplate::parse_column_template("{{{index}}}").unwrap().render_column(12), "{12}");
+        for template in ["col", "{stem}_{index}", "{index", "{index:x}"] {
+            assert!(NameTemplate::parse_column_template(template).is_err(), "{}", template);
+        }
+    }
+
+    #[test]
+    fn duplicate_names_ignore_case() {
+        assert!(ensure_unique(&["a.csv".into(), "b.csv".into()]).is_ok());
+        assert!(ensure_unique(&["a.csv".into(), "A.CSV".into()]).is_err());
+    }
+}
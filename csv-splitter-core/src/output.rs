@@ -0,0 +1,354 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::i18n::Message;
+use crate::manifest::read_manifest;
+use crate::naming::{ensure_unique, NameContext};
+use crate::{Error, PieceInfo, SplitParams};
+
+/// 输出文件已存在时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverwritePolicy {
+    /// 存在同名文件时报错，不改动任何已有文件
+    #[default]
+    Fail,
+    /// 直接覆盖同名文件
+    Overwrite,
+    /// 保留已有文件，跳过本次对应的分块
+    Skip,
+    /// 在文件名后追加 ` (1)`、` (2)` 等序号避开同名文件
+    AutoRename,
+    /// 分割前先删除输出目录中以前的分块再写入：命名模板能生成的、或上次清单中记录的CSV/XLSX文件
+    CleanPrefix,
+}
+
+/// 本次任务的暂存目录，所有分块先写在这里，整个任务成功后才移入输出目录
+///
+/// 目录以点开头，分割中途失败时输出目录里不会出现残缺的分块。
+pub(crate) fn staging_dir(output_dir: &Path, file_stem: &str) -> PathBuf {
+    output_dir.join(format!(".{}.partial", file_stem))
+}
+
+/// 分块写入时使用的临时文件名，全部写完后再按命名模板重命名
+///
+/// 模板中的 `{total}`、`{last_row}` 等占位符要等分割结束才能确定。
+pub(crate) fn working_piece_path(output_dir: &Path, file_stem: &str, index: usize) -> PathBuf {
+    staging_dir(output_dir, file_stem).join(format!("{}_{}.part.csv", file_stem, index))
+}
+
+/// 分割开始前创建输出目录和空的暂存目录
+///
+/// 上次异常退出留下的暂存目录会被清空。
+pub(crate) fn prepare_staging(params: &SplitParams) -> Result<PathBuf, Error> {
+    let output_dir = Path::new(&params.output_dir);
+    if params.overwrite_policy == OverwritePolicy::CleanPrefix
+        && params.name_template()?.prefix(file_stem(params)).is_empty()
+    {
+        return Err(Error::invalid_params(Message::new("output.no_prefix")));
+    }
+
+    let staging = staging_dir(output_dir, file_stem(params));
+    if staging.exists() {
+        std::fs::remove_dir_all(&staging).map_err(|e| Error::io(&staging, e))?;
+    }
+    std::fs::create_dir_all(&staging).map_err(|e| Error::io(&staging, e))?;
+    Ok(staging)
+}
+
+/// 删除暂存目录（包括未采用的分块和被替换文件的备份）
+pub(crate) fn discard_staging(params: &SplitParams) {
+    let staging = staging_dir(Path::new(&params.output_dir), file_stem(params));
+    let _ = std::fs::remove_dir_all(staging);
+}
+
+/// 清理前缀模式下需要删除的旧文件：文件名可由命名模板生成或记录在上次清单中的 `.csv` / `.xlsx`，
+/// 不含输入文件本身；只是前缀相同的其他文件不会删除
+fn prefixed_outputs(params: &SplitParams) -> Result<Vec<PathBuf>, Error> {
+    let template = params.name_template()?;
+    let output_dir = Path::new(&params.output_dir);
+    let prefix = template.prefix(file_stem(params));
+    if prefix.is_empty() {
+        return Err(Error::invalid_params(Message::new("output.no_prefix")));
+    }
+
+    // 清单无法读取时只按模板匹配
+    let manifest_names: HashSet<String> = read_manifest(output_dir, file_stem(params))
+        .ok()
+        .flatten()
+        .map(|manifest| {
+            manifest.pieces.iter()
+                .filter_map(|piece| Path::new(&piece.path).file_name().map(|s| s.to_string_lossy().into_owned()))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let input_canonical = Path::new(&params.input_path).canonicalize().ok();
+    let mut outputs = Vec::new();
+    for entry in std::fs::read_dir(output_dir).map_err(|e| Error::io(output_dir, e))? {
+        let path = entry.map_err(|e| Error::io(output_dir, e))?.path();
+        let is_output = matches!(
+            path.extension().and_then(|s| s.to_str()),
+            Some("csv") | Some("xlsx")
+        );
+        let name = path.file_name().and_then(|s| s.to_str()).unwrap_or("");
+        let generated = name.starts_with(&prefix)
+            && path.file_stem()
+                .and_then(|s| s.to_str())
+                .is_some_and(|stem| template.matches(file_stem(params), stem));
+        if !path.is_file() || !is_output || !(generated || manifest_names.contains(name)) {
+            continue;
+        }
+        if input_canonical.is_some() && path.canonicalize().ok() == input_canonical {
+            continue;
+        }
+        outputs.push(path);
+    }
+    Ok(outputs)
+}
+
+/// 已移入输出目录的分块，以及被替换的旧文件的备份位置，用于出错时撤销
+pub(crate) struct OutputCommit {
+    /// (暂存路径, 最终路径)
+    moved: Vec<(PathBuf, PathBuf)>,
+    /// (备份路径, 原路径)
+    backups: Vec<(PathBuf, PathBuf)>,
+}
+
+impl OutputCommit {
+    /// 把分块移回暂存目录并恢复被替换的旧文件，输出目录回到任务开始前的状态
+    pub(crate) fn rollback(self) {
+        for (working, target) in self.moved.iter().rev() {
+            if std::fs::rename(target, working).is_err() {
+                let _ = std::fs::remove_file(target);
+            }
+        }
+        for (backup, original) in self.backups.iter().rev() {
+            let _ = std::fs::rename(backup, original);
+        }
+    }
+}
+
+/// 按命名模板和覆盖策略确定每个分块的最终文件名，并从暂存目录移入输出目录
+///
+/// 先计算所有目标路径再统一移动，`Fail` 策略下发现冲突时不会改动任何已有文件。
+/// 被覆盖或清理的旧文件先移到暂存目录作为备份，任一步失败都会撤销已做的改动。
+/// 被拒绝的行的文件 `rejected` 保留原文件名，与分块一样按覆盖策略移入，完成后改为最终路径，被跳过时为 `None`。
+/// 返回因 `Skip` 策略而被跳过的目标文件。
+pub(crate) fn commit_pieces(
+    params: &SplitParams,
+    pieces: &mut Vec<PieceInfo>,
+    rejected: &mut Option<PathBuf>,
+) -> Result<(OutputCommit, Vec<PathBuf>), Error> {
+    let template = params.name_template()?;
+    let output_dir = Path::new(&params.output_dir);
+    let staging = staging_dir(output_dir, file_stem(params));
+    let started_at = chrono::Local::now();
+    let total = pieces.len();
+
+    let mut names = Vec::with_capacity(total);
+    for (i, piece) in pieces.iter().enumerate() {
+        let name = template.render(&NameContext {
+            stem: file_stem(params),
+            index: i + 1,
+            total,
+            first_row: piece.first_row,
+            last_row: piece.last_row,
+            started_at,
+        })?;
+        let extension = piece.path.extension().and_then(|s| s.to_str()).unwrap_or("csv");
+        names.push(format!("{}.{}", name, extension));
+    }
+    if let Some(staged) = rejected {
+        names.push(staged.file_name().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default());
+    }
+    ensure_unique(&names)?;
+
+    // (目标路径, 是否写入)
+    let mut targets: Vec<(PathBuf, bool)> = Vec::with_capacity(names.len());
+    let mut reserved: HashSet<PathBuf> = names.iter().map(|n| output_dir.join(n)).collect();
+    for name in &names {
+        let target = output_dir.join(name);
+        if !target.exists() {
+            targets.push((target, true));
+            continue;
+        }
+        match params.overwrite_policy {
+            OverwritePolicy::Fail => {
+                return Err(Error::OutputExists { path: target.display().to_string() });
+            }
+            OverwritePolicy::Overwrite | OverwritePolicy::CleanPrefix => targets.push((target, true)),
+            OverwritePolicy::Skip => targets.push((target, false)),
+            OverwritePolicy::AutoRename => {
+                let renamed = available_path(&target, &reserved);
+                reserved.insert(renamed.clone());
+                targets.push((renamed, true));
+            }
+        }
+    }
+
+    let mut commit = OutputCommit { moved: Vec::new(), backups: Vec::new() };
+    let backup_dir = staging.join("backup");
+
+    // 需要替换的旧文件：清理前缀模式下是所有同前缀的输出，两种模式下都包括同名的目标文件
+    let mut replaced = if params.overwrite_policy == OverwritePolicy::CleanPrefix {
+        prefixed_outputs(params)?
+    } else {
+        Vec::new()
+    };
+    if matches!(params.overwrite_policy, OverwritePolicy::Overwrite | OverwritePolicy::CleanPrefix) {
+        for (target, _) in &targets {
+            if target.exists() && !replaced.contains(target) {
+                replaced.push(target.clone());
+            }
+        }
+    }
+    if !replaced.is_empty()
+        && let Err(e) = std::fs::create_dir_all(&backup_dir)
+    {
+        return Err(Error::io(&backup_dir, e));
+    }
+    for (i, original) in replaced.into_iter().enumerate() {
+        let name = original.file_name().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+        let backup = backup_dir.join(format!("{}_{}", i, name));
+        if let Err(e) = std::fs::rename(&original, &backup) {
+            commit.rollback();
+            return Err(Error::io(&original, e));
+        }
+        commit.backups.push((backup, original));
+    }
+
+    let mut skipped = Vec::new();
+    let mut kept = Vec::with_capacity(total);
+    let mut targets = targets.into_iter();
+    for (piece, (target, write)) in pieces.iter().zip(targets.by_ref()) {
+        if !write {
+            skipped.push(target);
+            continue;
+        }
+        if let Err(e) = std::fs::rename(&piece.path, &target) {
+            commit.rollback();
+            return Err(Error::io(&target, e));
+        }
+        commit.moved.push((piece.path.clone(), target.clone()));
+        kept.push(PieceInfo { path: target, ..piece.clone() });
+    }
+    *pieces = kept;
+
+    if let Some(staged) = rejected.take()
+        && let Some((target, write)) = targets.next()
+    {
+        if !write {
+            skipped.push(target);
+        } else if let Err(e) = std::fs::rename(&staged, &target) {
+            commit.rollback();
+            return Err(Error::io(&target, e));
+        } else {
+            commit.moved.push((staged, target.clone()));
+            *rejected = Some(target);
+        }
+    }
+
+    Ok((commit, skipped))
+}
+
+/// 找到 `name (n).ext` 形式的第一个可用路径
+fn available_path(target: &Path, reserved: &HashSet<PathBuf>) -> PathBuf {
+    let stem = target.file_stem().and_then(|s| s.to_str()).unwrap_or("output");
+    let extension = target.extension().and_then(|s| s.to_str()).unwrap_or("csv");
+    let parent = target.parent().unwrap_or(Path::new(""));
+    (1..)
+        .map(|n| parent.join(format!("{} ({}).{}", stem, n, extension)))
+        .find(|candidate| !candidate.exists() && !reserved.contains(candidate))
+        .expect("可用文件名的序号不会耗尽")
+}
+
+pub(crate) fn file_stem(params: &SplitParams) -> &str {
+    Path::new(&params.input_path)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("output")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 在临时目录中准备输入文件和暂存目录，`existing` 为输出目录中已有的文件
+    fn setup(name: &str, policy: &str, existing: &[(&str, &str)]) -> (PathBuf, SplitParams) {
+        let dir = std::env::temp_dir().join(format!("csv-splitter-output-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("data.txt");
+        std::fs::write(&input, "id\n1\n2\n").unwrap();
+        for (name, content) in existing {
+            std::fs::write(dir.join(name), content).unwrap();
+        }
+        let params: SplitParams = serde_json::from_value(serde_json::json!({
+            "input_path": input.display().to_string(),
+            "output_dir": dir.display().to_string(),
+            "rows_per_file": 1,
+            "has_header": true,
+            "convert_to_excel": false,
+            "overwrite_policy": policy,
+        }))
+        .unwrap();
+        prepare_staging(&params).unwrap();
+        (dir, params)
+    }
+
+    /// 在暂存目录中写入分块，`content` 为 `None` 时不创建文件，移入时会失败
+    fn piece(dir: &Path, index: usize, content: Option<&str>) -> PieceInfo {
+        let path = working_piece_path(dir, "data", index);
+        if let Some(content) = content {
+            std::fs::write(&path, content).unwrap();
+        }
+        PieceInfo { path, first_row: index, last_row: index, row_count: 1, adjusted_rows: 0 }
+    }
+
+    fn read(path: &Path) -> String {
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn failed_move_restores_overwritten_files() {
+        let (dir, params) = setup("failed-move", "overwrite", &[("data_1.csv", "old1"), ("data_2.csv", "old2")]);
+        let mut pieces = vec![piece(&dir, 1, Some("new1")), piece(&dir, 2, None)];
+        let staged = pieces[0].path.clone();
+
+        assert!(commit_pieces(&params, &mut pieces, &mut None).is_err());
+        assert_eq!(read(&dir.join("data_1.csv")), "old1");
+        assert_eq!(read(&dir.join("data_2.csv")), "old2");
+        assert_eq!(read(&staged), "new1");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rollback_after_commit_restores_cleaned_files() {
+        let (dir, params) = setup("rollback", "clean_prefix", &[("data_1.csv", "old1"), ("data_7.csv", "old7")]);
+        let mut pieces = vec![piece(&dir, 1, Some("new1")), piece(&dir, 2, Some("new2"))];
+        let staged: Vec<PathBuf> = pieces.iter().map(|p| p.path.clone()).collect();
+        let staged_rejected = staging_dir(&dir, "data").join("data_rejected.csv");
+        std::fs::write(&staged_rejected, "rejected").unwrap();
+        let mut rejected = Some(staged_rejected.clone());
+
+        let (commit, skipped) = commit_pieces(&params, &mut pieces, &mut rejected).unwrap();
+        assert!(skipped.is_empty());
+        assert_eq!(read(&dir.join("data_1.csv")), "new1");
+        assert_eq!(read(&dir.join("data_2.csv")), "new2");
+        assert!(!dir.join("data_7.csv").exists());
+        assert_eq!(rejected, Some(dir.join("data_rejected.csv")));
+
+        // 例如清单写入失败时撤销：新分块回到暂存目录，清理掉的旧文件全部恢复
+        commit.rollback();
+        assert_eq!(read(&dir.join("data_1.csv")), "old1");
+        assert_eq!(read(&dir.join("data_7.csv")), "old7");
+        assert!(!dir.join("data_2.csv").exists());
+        assert!(!dir.join("data_rejected.csv").exists());
+        assert_eq!(read(&staged[1]), "new2");
+        assert_eq!(read(&staged_rejected), "rejected");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
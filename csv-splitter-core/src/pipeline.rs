@@ -0,0 +1,144 @@
+use std::path::PathBuf;
+
+use csv::StringRecord;
+
+use crate::bad_rows::BadRows;
+use crate::computed::ComputedColumns;
+use crate::filter::Filter;
+use crate::ragged::Normalizer;
+use crate::source::RecordSource;
+use crate::{BadRowPolicy, Error, SplitParams};
+
+/// 每行写入分块前依次经过的处理：灵活模式下补齐或截断、追加计算列、按条件筛选
+pub(crate) struct RowPipeline {
+    normalizer: Option<Normalizer>,
+    computed: Option<ComputedColumns>,
+    filter: Option<Filter>,
+    headers: StringRecord,
+}
+
+/// 一行经过处理后的结果
+pub(crate) enum RowOutcome {
+    /// 不满足筛选条件
+    Filtered,
+    /// 保留，`adjusted` 表示灵活模式下补齐或截断过
+    Kept { adjusted: bool },
+}
+
+impl RowPipeline {
+    /// `headers` 为解析后的标题行，灵活模式按它补齐，计算列追加在它之后
+    pub(crate) fn new(params: &SplitParams, headers: &StringRecord) -> Result<RowPipeline, Error> {
+        let normalizer = Normalizer::new(params, headers);
+        let mut output_headers = headers.clone();
+        let computed = ComputedColumns::new(params, &mut output_headers)?;
+        let filter = Filter::new(params, &output_headers)?;
+        Ok(RowPipeline { normalizer, computed, filter, headers: output_headers })
+    }
+
+    /// 处理后的标题行（含计算列），筛选条件、去重和列选择都按它查找列
+    pub(crate) fn headers(&self) -> &StringRecord {
+        &self.headers
+    }
+
+    /// 处理一行，`row_number` 为在原文件中的数据行号
+    pub(crate) fn process(&self, record: &mut StringRecord, row_number: usize) -> RowOutcome {
+        let adjusted = self.normalizer.as_ref().is_some_and(|n| n.apply(record));
+        if let Some(ref computed) = self.computed {
+            computed.apply(record, row_number);
+        }
+        if self.filter.as_ref().is_some_and(|f| !f.matches(record, row_number)) {
+            return RowOutcome::Filtered;
+        }
+        RowOutcome::Kept { adjusted }
+    }
+}
+
+/// 从头读取原文件，依次产出处理后满足筛选条件的记录，用于去重扫描和还原校验
+///
+/// 分割时跳过的格式错误的行在这里同样跳过，并与分割时一样计入数据行号。
+pub(crate) struct PreparedRecords<'a> {
+    source: Box<dyn RecordSource>,
+    bad_rows: BadRows,
+    pipeline: &'a RowPipeline,
+    records_read: usize,
+}
+
+impl<'a> PreparedRecords<'a> {
+    pub(crate) fn new(params: &SplitParams, source: Box<dyn RecordSource>, pipeline: &'a RowPipeline) -> PreparedRecords<'a> {
+        let policy = match params.bad_row_policy {
+            BadRowPolicy::Strict => BadRowPolicy::Strict,
+            _ => BadRowPolicy::Skip,
+        };
+        PreparedRecords {
+            source,
+            bad_rows: BadRows::new(policy, PathBuf::new(), None),
+            pipeline,
+            records_read: 0,
+        }
+    }
+
+    /// 读取下一条满足筛选条件的记录，文件结束时返回 `Ok(false)`
+    pub(crate) fn next(&mut self, record: &mut StringRecord) -> Result<bool, Error> {
+        while self.bad_rows.read_record(self.source.as_mut(), record)? {
+            self.records_read += 1;
+            let row_number = self.records_read + self.bad_rows.count();
+            if let RowOutcome::Kept { .. } = self.pipeline.process(record, row_number) {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// 已读取的数据行数（含跳过的行）
+    pub(crate) fn rows_read(&self) -> usize {
+        self.records_read + self.bad_rows.count()
+    }
+
+    /// 已读取的输入字节数，无法得知时为0
+    pub(crate) fn bytes_read(&self) -> u64 {
+        self.source.position().map_or(0, |p| p.byte())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn computed_values_stay_in_their_columns_when_long_rows_are_preserved() {
+        let params: SplitParams = serde_json::from_value(json!({
+            "input_path": "in.csv",
+            "output_dir": "out",
+            "rows_per_file": 10,
+            "has_header": true,
+            "convert_to_excel": false,
+            "flexible": true,
+            "long_rows": "preserve",
+            "computed_columns": [
+                { "name": "ab", "expression": "concat(a, '-', b)" },
+                { "name": "ab2", "expression": "concat(ab, '!')" },
+            ],
+            "filter": "ab2 != '0-0!'",
+        }))
+        .unwrap();
+        let pipeline = RowPipeline::new(&params, &StringRecord::from(vec!["a", "b"])).unwrap();
+        assert_eq!(pipeline.headers(), &StringRecord::from(vec!["a", "b", "ab", "ab2"]));
+
+        let process = |fields: Vec<&str>| {
+            let mut record = StringRecord::from(fields);
+            let outcome = pipeline.process(&mut record, 1);
+            (record, matches!(outcome, RowOutcome::Kept { adjusted: true }))
+        };
+        assert_eq!(process(vec!["1", "2"]), (StringRecord::from(vec!["1", "2", "1-2", "1-2!"]), false));
+        assert_eq!(process(vec!["1"]), (StringRecord::from(vec!["1", "", "1-", "1-!"]), true));
+        assert_eq!(
+            process(vec!["1", "2", "x", "y"]),
+            (StringRecord::from(vec!["1", "2", "1-2", "1-2!", "x", "y"]), false)
+        );
+
+        let mut record = StringRecord::from(vec!["0", "0", "x"]);
+        assert!(matches!(pipeline.process(&mut record, 2), RowOutcome::Filtered));
+    }
+}
@@ -0,0 +1,125 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+/// 每写入多少行报告一次进度
+pub(crate) const REPORT_EVERY_ROWS: usize = 10_000;
+
+/// 分割所处的阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    /// 去重前扫描输入，查找重复的行
+    Deduplicating,
+    /// 读取输入并排序，排好序后再进入分割阶段
+    Sorting,
+    /// 读取输入并写入分块
+    Splitting,
+    /// 还原校验
+    Verifying,
+    /// 转换为Excel
+    Converting,
+    /// 重命名分块并写入清单
+    Finishing,
+}
+
+/// 分割进度
+#[derive(Debug, Clone, Serialize)]
+pub struct Progress {
+    pub input_path: String,
+    pub stage: Stage,
+    /// 已写入的数据行数
+    pub rows: usize,
+    /// 已完成的分块数
+    pub pieces: usize,
+    /// 已读取的输入字节数，无法得知时为0
+    pub bytes_read: u64,
+    pub total_bytes: u64,
+}
+
+impl Progress {
+    pub(crate) fn new(input_path: &str, stage: Stage, total_bytes: u64) -> Progress {
+        Progress {
+            input_path: input_path.to_string(),
+            stage,
+            rows: 0,
+            pieces: 0,
+            bytes_read: 0,
+            total_bytes,
+        }
+    }
+
+    /// 查找重复行、排序和分割阶段按已读取的字节估算百分比
+    pub fn percent(&self) -> Option<f64> {
+        (matches!(self.stage, Stage::Deduplicating | Stage::Sorting | Stage::Splitting) && self.total_bytes > 0 && self.bytes_read > 0)
+            .then(|| (self.bytes_read as f64 / self.total_bytes as f64 * 100.0).min(100.0))
+    }
+}
+
+/// 分割进度回调，界面、命令行等调用方各自决定如何展示
+pub trait ProgressListener: Sync {
+    fn on_progress(&self, progress: &Progress);
+
+    /// 返回 `true` 时分割在下一次报告进度时停止，结果为 [`Error::Cancelled`](crate::Error::Cancelled)
+    fn is_cancelled(&self) -> bool {
+        false
+    }
+}
+
+/// 不需要进度时使用
+pub struct NoProgress;
+
+impl ProgressListener for NoProgress {
+    fn on_progress(&self, _progress: &Progress) {}
+}
+
+impl<F: Fn(&Progress) + Sync> ProgressListener for F {
+    fn on_progress(&self, progress: &Progress) {
+        self(progress)
+    }
+}
+
+/// 限制回调频率：同一阶段内两次转发至少间隔 `interval`，阶段变化时总是转发
+pub struct Throttled<L> {
+    inner: L,
+    interval: Duration,
+    last: Mutex<Option<(Instant, Stage)>>,
+}
+
+impl<L: ProgressListener> Throttled<L> {
+    pub fn new(inner: L, interval: Duration) -> Throttled<L> {
+        Throttled {
+            inner,
+            interval,
+            last: Mutex::new(None),
+        }
+    }
+
+    pub fn inner(&self) -> &L {
+        &self.inner
+    }
+}
+
+impl<L: ProgressListener> ProgressListener for Throttled<L> {
+    fn on_progress(&self, progress: &Progress) {
+        let now = Instant::now();
+        {
+            let Ok(mut last) = self.last.lock() else {
+                return;
+            };
+            if let Some((at, stage)) = *last
+                && stage == progress.stage
+                && now.duration_since(at) < self.interval
+            {
+                return;
+            }
+            *last = Some((now, progress.stage));
+        }
+        self.inner.on_progress(progress);
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.inner.is_cancelled()
+    }
+}
@@ -0,0 +1,164 @@
+use csv::StringRecord;
+use serde::{Deserialize, Serialize};
+
+use crate::i18n::Message;
+use crate::{Error, SplitParams};
+
+/// 按列名或列序号（从1开始）指定的列
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ColumnRef {
+    Index(usize),
+    Name(String),
+}
+
+impl ColumnRef {
+    /// 在标题行中查找列的位置（从0开始）
+    pub(crate) fn position(&self, headers: &StringRecord) -> Result<usize, Error> {
+        let position = match self {
+            ColumnRef::Index(index) => index.checked_sub(1).filter(|&i| i < headers.len()),
+            ColumnRef::Name(name) => headers.iter().position(|h| h == name),
+        };
+        position.ok_or_else(|| Error::ColumnNotFound { column: self.to_string() })
+    }
+}
+
+impl std::fmt::Display for ColumnRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ColumnRef::Index(index) => write!(f, "{}", index),
+            ColumnRef::Name(name) => f.write_str(name),
+        }
+    }
+}
+
+/// 写入分块前对每行做的列选择、排序和删除
+pub(crate) struct Projection {
+    /// 输出的各列在原记录中的位置
+    positions: Vec<usize>,
+}
+
+impl Projection {
+    /// 先按 `columns` 选择并排序（为空时保留全部列），再去掉 `exclude_columns`；没有指定时返回 `None`
+    ///
+    /// 同一列（包括列名和列序号指向同一列）不能选择两次。
+    pub(crate) fn new(params: &SplitParams, headers: &StringRecord) -> Result<Option<Projection>, Error> {
+        if params.columns.is_empty() && params.exclude_columns.is_empty() {
+            return Ok(None);
+        }
+        let mut positions = if params.columns.is_empty() {
+            (0..headers.len()).collect()
+        } else {
+            let mut positions = Vec::with_capacity(params.columns.len());
+            for column in &params.columns {
+                let position = column.position(headers)?;
+                if positions.contains(&position) {
+                    return Err(Error::invalid_params(
+                        Message::new("projection.duplicate_column").arg("name", column.to_string()),
+                    ));
+                }
+                positions.push(position);
+            }
+            positions
+        };
+        for column in &params.exclude_columns {
+            let excluded = column.position(headers)?;
+            positions.retain(|&p| p != excluded);
+        }
+        if positions.is_empty() {
+            return Err(Error::NoColumnsSelected);
+        }
+        Ok(Some(Projection { positions }))
+    }
+
+    /// 输出的各列在原记录中的位置
+    pub(crate) fn positions(&self) -> &[usize] {
+        &self.positions
+    }
+
+    /// 把 `record` 中要输出的字段按顺序放入 `output`，原记录中没有的字段为空
+    pub(crate) fn apply(&self, record: &StringRecord, output: &mut StringRecord) {
+        output.clear();
+        for &position in &self.positions {
+            output.push_field(record.get(position).unwrap_or(""));
+        }
+    }
+}
+
+/// 要写入的记录：有投影时投影到 `buffer` 中，否则为原记录
+pub(crate) fn project<'a>(
+    projection: Option<&Projection>,
+    record: &'a StringRecord,
+    buffer: &'a mut StringRecord,
+) -> &'a StringRecord {
+    match projection {
+        Some(projection) => {
+            projection.apply(record, buffer);
+            buffer
+        }
+        None => record,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn projection(columns: serde_json::Value, exclude_columns: serde_json::Value) -> Result<Option<Projection>, Error> {
+        let params: SplitParams = serde_json::from_value(json!({
+            "input_path": "in.csv",
+            "output_dir": "out",
+            "rows_per_file": 10,
+            "has_header": true,
+            "convert_to_excel": false,
+            "columns": columns,
+            "exclude_columns": exclude_columns,
+        }))
+        .unwrap();
+        Projection::new(&params, &StringRecord::from(vec!["id", "name", "email", "city"]))
+    }
+
+    fn apply(projection: &Projection, fields: &[&str]) -> Vec<String> {
+        let mut output = StringRecord::new();
+        projection.apply(&StringRecord::from(fields.to_vec()), &mut output);
+        output.iter().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn selects_and_reorders_by_name_or_index() {
+        assert!(projection(json!([]), json!([])).unwrap().is_none());
+
+        let selected = projection(json!(["email", 1, "city"]), json!([])).unwrap().unwrap();
+        assert_eq!(selected.positions(), [2, 0, 3]);
+        assert_eq!(apply(&selected, &["1", "Ann", "ann@example.com", "Oslo"]), ["ann@example.com", "1", "Oslo"]);
+        // 字段不足的行缺少的列为空
+        assert_eq!(apply(&selected, &["2", "Bob"]), ["", "2", ""]);
+
+        let dropped = projection(json!([]), json!([2, "city"])).unwrap().unwrap();
+        assert_eq!(dropped.positions(), [0, 2]);
+        let both = projection(json!(["city", "name", "id"]), json!(["name"])).unwrap().unwrap();
+        assert_eq!(both.positions(), [3, 0]);
+    }
+
+    #[test]
+    fn rejects_unknown_and_duplicate_columns() {
+        for (columns, exclude_columns, column) in [
+            (json!(["phone"]), json!([]), "phone"),
+            (json!([0]), json!([]), "0"),
+            (json!([5]), json!([]), "5"),
+            (json!([]), json!(["phone"]), "phone"),
+        ] {
+            let error = projection(columns, exclude_columns).err().unwrap();
+            assert_eq!(error, Error::ColumnNotFound { column: column.into() });
+        }
+
+        for columns in [json!(["name", "name"]), json!(["id", 2, "name"])] {
+            let error = projection(columns, json!([])).err().unwrap();
+            assert!(format!("{:?}", error).contains("projection.duplicate_column"));
+        }
+
+        assert_eq!(projection(json!(["id"]), json!([1])).err(), Some(Error::NoColumnsSelected));
+    }
+}
@@ -0,0 +1,114 @@
+use csv::StringRecord;
+use serde::{Deserialize, Serialize};
+
+use crate::SplitParams;
+
+/// 灵活模式下字段多于标题行的行的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LongRowPolicy {
+    /// 截断多出的字段
+    #[default]
+    Truncate,
+    /// 保留多出的字段，输出的分块中各行字段数可能不同
+    Preserve,
+}
+
+/// 灵活模式下按标题行的列数补齐或截断字段数不一致的行
+pub(crate) struct Normalizer {
+    /// 每列补齐时使用的值
+    fill: Vec<String>,
+    long_rows: LongRowPolicy,
+}
+
+impl Normalizer {
+    /// 未开启灵活模式时返回 `None`
+    pub(crate) fn new(params: &SplitParams, headers: &StringRecord) -> Option<Normalizer> {
+        if !params.flexible {
+            return None;
+        }
+        let fill = headers
+            .iter()
+            .map(|name| params.column_defaults.get(name).unwrap_or(&params.fill_value).clone())
+            .collect();
+        Some(Normalizer { fill, long_rows: params.long_rows })
+    }
+
+    /// 补齐或截断一行，返回是否做了调整（原样保留的长行不算）
+    pub(crate) fn apply(&self, record: &mut StringRecord) -> bool {
+        let width = self.fill.len();
+        if record.len() < width {
+            for value in &self.fill[record.len()..] {
+                record.push_field(value);
+            }
+            true
+        } else if record.len() > width && self.long_rows == LongRowPolicy::Truncate {
+            record.truncate(width);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn normalizer(options: serde_json::Value) -> Option<Normalizer> {
+        let mut params = json!({
+            "input_path": "in.csv",
+            "output_dir": "out",
+            "rows_per_file": 10,
+            "has_header": true,
+            "convert_to_excel": false,
+        });
+        params.as_object_mut().unwrap().extend(options.as_object().unwrap().clone());
+        let params: SplitParams = serde_json::from_value(params).unwrap();
+        Normalizer::new(&params, &StringRecord::from(vec!["id", "name", "city"]))
+    }
+
+    fn apply(normalizer: &Normalizer, fields: &[&str]) -> (Vec<String>, bool) {
+        let mut record = StringRecord::from(fields.to_vec());
+        let adjusted = normalizer.apply(&mut record);
+        (record.iter().map(str::to_string).collect(), adjusted)
+    }
+
+    #[test]
+    fn only_enabled_in_flexible_mode() {
+        assert!(normalizer(json!({})).is_none());
+        assert!(normalizer(json!({ "flexible": true })).is_some());
+    }
+
+    #[test]
+    fn pads_and_truncates_to_the_header_width() {
+        let normalizer = normalizer(json!({ "flexible": true })).unwrap();
+        assert_eq!(apply(&normalizer, &["1", "a", "x"]), (vec!["1".into(), "a".into(), "x".into()], false));
+        assert_eq!(apply(&normalizer, &["1"]), (vec!["1".into(), "".into(), "".into()], true));
+        assert_eq!(apply(&normalizer, &["1", "a", "x", "extra"]), (vec!["1".into(), "a".into(), "x".into()], true));
+    }
+
+    #[test]
+    fn fills_missing_fields_per_column() {
+        let normalizer = normalizer(json!({
+            "flexible": true,
+            "fill_value": "-",
+            "column_defaults": { "city": "unknown", "missing": "ignored" },
+        }))
+        .unwrap();
+        assert_eq!(apply(&normalizer, &["1"]), (vec!["1".into(), "-".into(), "unknown".into()], true));
+        assert_eq!(apply(&normalizer, &["1", ""]), (vec!["1".into(), "".into(), "unknown".into()], true));
+    }
+
+    #[test]
+    fn preserves_long_rows_without_counting_them() {
+        let normalizer = normalizer(json!({ "flexible": true, "long_rows": "preserve" })).unwrap();
+        assert_eq!(
+            apply(&normalizer, &["1", "a", "x", "extra"]),
+            (vec!["1".into(), "a".into(), "x".into(), "extra".into()], false)
+        );
+        assert_eq!(apply(&normalizer, &["1", "a"]), (vec!["1".into(), "a".into(), "".into()], true));
+    }
+}
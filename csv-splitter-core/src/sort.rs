@@ -0,0 +1,443 @@
+use std::cmp::Ordering;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDateTime;
+use csv::{Reader, ReaderBuilder, StringRecord, Writer, WriterBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::expr::{parse_date, parse_number};
+use crate::i18n::Message;
+use crate::projection::ColumnRef;
+use crate::{Error, SplitParams};
+
+/// 没有指定时排序可使用的内存（MB）
+const DEFAULT_MEMORY_MB: usize = 256;
+/// 一次最多同时归并的临时文件数，超过时先分组归并
+const MERGE_WIDTH: usize = 64;
+
+/// 排序键
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SortKey {
+    /// 列名或从1开始的列序号
+    pub column: ColumnRef,
+    /// 是否降序
+    #[serde(default)]
+    pub descending: bool,
+    /// 比较方式
+    #[serde(default)]
+    pub compare: SortCompare,
+    /// 按日期比较时的格式，如 `%d/%m/%Y`，默认识别 `2024-01-31`、`2024-01-31 08:00:00` 等常见格式
+    #[serde(default)]
+    pub date_format: Option<String>,
+}
+
+/// 排序键的比较方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortCompare {
+    /// 按文本逐字节比较
+    #[default]
+    Text,
+    /// 按数值比较
+    Number,
+    /// 按日期时间比较
+    Date,
+}
+
+/// 排序中的一行，连同它在原文件中的数据行号和是否被补齐或截断过
+pub(crate) struct SortRow {
+    pub(crate) record: StringRecord,
+    pub(crate) row_number: usize,
+    pub(crate) adjusted: bool,
+    key: Vec<SortValue>,
+}
+
+/// 按比较方式转换后的排序键，空字段和无法转换的值为 `Null`，无论升序降序都排在最后
+#[derive(PartialEq, PartialOrd)]
+enum SortValue {
+    Null,
+    Text(String),
+    Number(f64),
+    Date(NaiveDateTime),
+}
+
+/// 排序键在行中的位置
+struct KeyColumn {
+    position: usize,
+    descending: bool,
+    compare: SortCompare,
+    date_format: Option<String>,
+}
+
+/// 外部归并排序：行先缓存在内存中，超过内存上限时排好序写入临时文件，全部读完后再逐个归并
+///
+/// 排序是稳定的，排序键相同的行保持原来的先后顺序。
+pub(crate) struct Sorter {
+    keys: Vec<KeyColumn>,
+    memory_limit: usize,
+    dir: PathBuf,
+    buffer: Vec<SortRow>,
+    buffer_bytes: usize,
+    /// 已写入的临时文件，按写入顺序排列
+    runs: Vec<PathBuf>,
+    next_run: usize,
+    /// 已加入的行数
+    rows: usize,
+}
+
+impl Sorter {
+    /// 没有指定排序键时返回 `None`；临时文件写在 `work_dir` 下，排序结束后删除
+    pub(crate) fn new(params: &SplitParams, headers: &StringRecord, work_dir: &Path) -> Result<Option<Sorter>, Error> {
+        if params.sort.is_empty() {
+            return Ok(None);
+        }
+        let keys = params.sort
+            .iter()
+            .map(|key| {
+                Ok(KeyColumn {
+                    position: key.column.position(headers)?,
+                    descending: key.descending,
+                    compare: key.compare,
+                    date_format: key.date_format.clone(),
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let memory_mb = params.sort_memory_mb.unwrap_or(DEFAULT_MEMORY_MB);
+        if memory_mb == 0 {
+            return Err(Error::invalid_params(Message::new("sort.zero_memory")));
+        }
+        Ok(Some(Sorter {
+            keys,
+            memory_limit: memory_mb * 1024 * 1024,
+            dir: work_dir.join(format!("sort-{}", std::process::id())),
+            buffer: Vec::new(),
+            buffer_bytes: 0,
+            runs: Vec::new(),
+            next_run: 0,
+            rows: 0,
+        }))
+    }
+
+    /// 加入一行，`row_number` 为在原文件中的数据行号
+    pub(crate) fn push(&mut self, record: &StringRecord, row_number: usize, adjusted: bool) -> Result<(), Error> {
+        // 粗略估算一行占用的内存：字段内容、字段边界和排序键
+        self.buffer_bytes += record.as_slice().len() + record.len() * 8 + self.keys.len() * 32 + 96;
+        let row = self.row(record.clone(), row_number, adjusted);
+        self.buffer.push(row);
+        self.rows += 1;
+        if self.buffer_bytes >= self.memory_limit {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    /// 结束输入，按排序后的顺序读取
+    pub(crate) fn finish(mut self) -> Result<SortedRows, Error> {
+        if self.runs.is_empty() {
+            let mut buffer = std::mem::take(&mut self.buffer);
+            buffer.sort_by(|a, b| compare_rows(&self.keys, a, b));
+            return Ok(SortedRows { source: SortedSource::Memory(buffer.into_iter()), sorter: self });
+        }
+        self.spill()?;
+        // 临时文件过多时先把相邻的一组归并为一个，保持稳定
+        while self.runs.len() > MERGE_WIDTH {
+            let runs = std::mem::take(&mut self.runs);
+            for group in runs.chunks(MERGE_WIDTH) {
+                if group.len() == 1 {
+                    self.runs.push(group[0].clone());
+                    continue;
+                }
+                let mut merge = Merge::open(group)?;
+                let path = self.create_run()?;
+                let mut writer = run_writer(&path)?;
+                while let Some(row) = merge.next(&self)? {
+                    write_row(&mut writer, &row)?;
+                }
+                writer.flush()?;
+                for run in group {
+                    std::fs::remove_file(run).map_err(|e| Error::io(run, e))?;
+                }
+                self.runs.push(path);
+            }
+        }
+        let merge = Merge::open(&self.runs)?;
+        Ok(SortedRows { source: SortedSource::Merge(merge), sorter: self })
+    }
+
+    fn row(&self, record: StringRecord, row_number: usize, adjusted: bool) -> SortRow {
+        let key = self.keys
+            .iter()
+            .map(|key| {
+                let text = record.get(key.position).unwrap_or("");
+                let value = match key.compare {
+                    SortCompare::Text => (!text.is_empty()).then(|| SortValue::Text(text.to_string())),
+                    SortCompare::Number => parse_number(text).map(SortValue::Number),
+                    SortCompare::Date => parse_date(text, key.date_format.as_deref()).map(SortValue::Date),
+                };
+                value.unwrap_or(SortValue::Null)
+            })
+            .collect();
+        SortRow { record, row_number, adjusted, key }
+    }
+
+    /// 把内存中的行排好序写入一个临时文件
+    fn spill(&mut self) -> Result<(), Error> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let mut buffer = std::mem::take(&mut self.buffer);
+        buffer.sort_by(|a, b| compare_rows(&self.keys, a, b));
+        let path = self.create_run()?;
+        let mut writer = run_writer(&path)?;
+        for row in &buffer {
+            write_row(&mut writer, row)?;
+        }
+        writer.flush()?;
+        self.buffer_bytes = 0;
+        self.runs.push(path);
+        Ok(())
+    }
+
+    fn create_run(&mut self) -> Result<PathBuf, Error> {
+        if self.next_run == 0 {
+            if self.dir.exists() {
+                std::fs::remove_dir_all(&self.dir).map_err(|e| Error::io(&self.dir, e))?;
+            }
+            std::fs::create_dir_all(&self.dir).map_err(|e| Error::io(&self.dir, e))?;
+        }
+        self.next_run += 1;
+        Ok(self.dir.join(format!("run_{}.csv", self.next_run)))
+    }
+}
+
+impl Drop for Sorter {
+    fn drop(&mut self) {
+        if self.next_run > 0 {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+}
+
+/// 排序后的行
+///
+/// 先关闭临时文件再由 `sorter` 删除临时目录，字段的顺序不能调换。
+pub(crate) struct SortedRows {
+    source: SortedSource,
+    sorter: Sorter,
+}
+
+enum SortedSource {
+    Memory(std::vec::IntoIter<SortRow>),
+    Merge(Merge),
+}
+
+impl SortedRows {
+    /// 排序的总行数
+    pub(crate) fn row_count(&self) -> usize {
+        self.sorter.rows
+    }
+
+    /// 按顺序读取下一行，全部读完时返回 `None`
+    pub(crate) fn next(&mut self) -> Result<Option<SortRow>, Error> {
+        match self.source {
+            SortedSource::Memory(ref mut rows) => Ok(rows.next()),
+            SortedSource::Merge(ref mut merge) => merge.next(&self.sorter),
+        }
+    }
+}
+
+/// 归并若干个已排好序的临时文件
+struct Merge {
+    readers: Vec<Reader<BufReader<File>>>,
+    /// 各文件当前的第一行
+    heads: Vec<Option<SortRow>>,
+    started: bool,
+}
+
+impl Merge {
+    fn open(runs: &[PathBuf]) -> Result<Merge, Error> {
+        let readers = runs
+            .iter()
+            .map(|path| {
+                let file = File::open(path).map_err(|e| Error::io(path, e))?;
+                Ok(ReaderBuilder::new()
+                    .has_headers(false)
+                    .flexible(true)
+                    .from_reader(BufReader::new(file)))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let heads = readers.iter().map(|_| None).collect();
+        Ok(Merge { readers, heads, started: false })
+    }
+
+    /// 取出各文件第一行中最小的一行；相同时取排在前面的文件，保持稳定
+    fn next(&mut self, sorter: &Sorter) -> Result<Option<SortRow>, Error> {
+        if !self.started {
+            self.started = true;
+            for run in 0..self.readers.len() {
+                self.heads[run] = read_row(&mut self.readers[run], sorter)?;
+            }
+        }
+        let mut smallest: Option<usize> = None;
+        for (run, head) in self.heads.iter().enumerate() {
+            let Some(head) = head else { continue };
+            let is_smaller = match smallest {
+                Some(current) => self.heads[current]
+                    .as_ref()
+                    .is_some_and(|current| compare_rows(&sorter.keys, head, current) == Ordering::Less),
+                None => true,
+            };
+            if is_smaller {
+                smallest = Some(run);
+            }
+        }
+        let Some(run) = smallest else {
+            return Ok(None);
+        };
+        let next = read_row(&mut self.readers[run], sorter)?;
+        Ok(std::mem::replace(&mut self.heads[run], next))
+    }
+}
+
+/// 按各排序键依次比较，空值排在最后
+fn compare_rows(keys: &[KeyColumn], a: &SortRow, b: &SortRow) -> Ordering {
+    for (key, (left, right)) in keys.iter().zip(a.key.iter().zip(&b.key)) {
+        let ordering = match (left, right) {
+            (SortValue::Null, SortValue::Null) => Ordering::Equal,
+            (SortValue::Null, _) => Ordering::Greater,
+            (_, SortValue::Null) => Ordering::Less,
+            _ => {
+                let ordering = left.partial_cmp(right).unwrap_or(Ordering::Equal);
+                if key.descending { ordering.reverse() } else { ordering }
+            }
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+/// 临时文件中每行依次为原文件行号、是否补齐或截断过和原有的字段
+fn run_writer(path: &Path) -> Result<Writer<BufWriter<File>>, Error> {
+    let file = File::create(path).map_err(|e| Error::io(path, e))?;
+    Ok(WriterBuilder::new().flexible(true).from_writer(BufWriter::new(file)))
+}
+
+fn write_row(writer: &mut Writer<BufWriter<File>>, row: &SortRow) -> Result<(), Error> {
+    writer.write_field(row.row_number.to_string())?;
+    writer.write_field(if row.adjusted { "1" } else { "0" })?;
+    writer.write_record(&row.record)?;
+    Ok(())
+}
+
+fn read_row(reader: &mut Reader<BufReader<File>>, sorter: &Sorter) -> Result<Option<SortRow>, Error> {
+    let mut stored = StringRecord::new();
+    if !reader.read_record(&mut stored)? {
+        return Ok(None);
+    }
+    let row_number = stored.get(0).and_then(|n| n.parse().ok()).unwrap_or(0);
+    let adjusted = stored.get(1) == Some("1");
+    let record: StringRecord = stored.iter().skip(2).collect();
+    Ok(Some(sorter.row(record, row_number, adjusted)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(sort: serde_json::Value, memory_mb: usize) -> SplitParams {
+        serde_json::from_value(serde_json::json!({
+            "input_path": "input.csv",
+            "output_dir": ".",
+            "rows_per_file": 10,
+            "has_header": true,
+            "convert_to_excel": false,
+            "sort": sort,
+            "sort_memory_mb": memory_mb,
+        }))
+        .unwrap()
+    }
+
+    /// 排序后依次返回各行的原行号；`spill` 为真时每加入一行就写一个临时文件
+    fn sorted(name: &str, sort: serde_json::Value, rows: &[[&str; 3]], spill: bool) -> Vec<usize> {
+        let dir = std::env::temp_dir().join(format!("csv-splitter-sort-{}-{}", std::process::id(), name));
+        let headers = StringRecord::from(vec!["group", "amount", "date"]);
+        let mut sorter = Sorter::new(&params(sort, 1), &headers, &dir).unwrap().unwrap();
+        if spill {
+            sorter.memory_limit = 1;
+        }
+        for (index, row) in rows.iter().enumerate() {
+            sorter.push(&StringRecord::from(row.to_vec()), index + 1, false).unwrap();
+        }
+        if spill {
+            assert_eq!(sorter.runs.len(), rows.len());
+        }
+        let mut sorted = sorter.finish().unwrap();
+        assert_eq!(sorted.row_count(), rows.len());
+        let mut numbers = Vec::new();
+        while let Some(row) = sorted.next().unwrap() {
+            assert_eq!(row.record.get(0), Some(rows[row.row_number - 1][0]));
+            numbers.push(row.row_number);
+        }
+        let runs = sorted.sorter.dir.clone();
+        drop(sorted);
+        assert!(!runs.exists(), "临时文件应在排序结束后删除");
+        let _ = std::fs::remove_dir(&dir);
+        numbers
+    }
+
+    const ROWS: [[&str; 3]; 6] = [
+        ["b", "10", "2024-01-03"],
+        ["a", "9", "2024-01-01"],
+        ["b", "", "2024-01-02"],
+        ["a", "10", "31/01/2023"],
+        ["b", "2", "2024-01-01"],
+        ["a", "9", ""],
+    ];
+
+    #[test]
+    fn later_keys_break_ties() {
+        let sort = serde_json::json!([
+            { "column": "group" },
+            { "column": 2, "compare": "number", "descending": true },
+        ]);
+        // 数值比较时 10 排在 9 前面，空值无论升降序都排在最后，9 相同的两行保持原顺序
+        assert_eq!(sorted("keys", sort, &ROWS, false), [4, 2, 6, 1, 5, 3]);
+    }
+
+    #[test]
+    fn equal_keys_keep_their_original_order() {
+        let sort = serde_json::json!([{ "column": "date", "compare": "date" }]);
+        assert_eq!(sorted("stable", sort, &ROWS, false), [2, 5, 3, 1, 4, 6]);
+        let sort = serde_json::json!([{ "column": "date", "compare": "date", "date_format": "%d/%m/%Y" }]);
+        assert_eq!(sorted("format", sort, &ROWS, false), [4, 1, 2, 3, 5, 6]);
+    }
+
+    #[test]
+    fn spilled_runs_merge_in_the_same_order() {
+        let sort = serde_json::json!([
+            { "column": "group", "descending": true },
+            { "column": "amount", "compare": "number" },
+        ]);
+        assert_eq!(sorted("memory", sort.clone(), &ROWS, false), sorted("runs", sort.clone(), &ROWS, true));
+
+        // 超过一次可归并的文件数时先分组归并，结果仍然是稳定的
+        let amounts: Vec<String> = (0..MERGE_WIDTH * 2 + 5).map(|i| (i % 7).to_string()).collect();
+        let rows: Vec<[&str; 3]> = amounts.iter().map(|a| ["g", a.as_str(), ""]).collect();
+        let mut by_amount: Vec<usize> = (1..=rows.len()).collect();
+        by_amount.sort_by_key(|&n| rows[n - 1][1].parse::<u32>().unwrap());
+        assert_eq!(sorted("wide", sort, &rows, true), by_amount);
+    }
+
+    #[test]
+    fn zero_memory_is_rejected() {
+        let error = Sorter::new(&params(serde_json::json!([{ "column": 1 }]), 0), &StringRecord::from(vec!["a"]), Path::new("."))
+            .err()
+            .unwrap();
+        assert_eq!(error.code(), "invalid_params");
+        assert!(format!("{:?}", error).contains("sort.zero_memory"));
+    }
+}
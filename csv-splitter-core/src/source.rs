@@ -0,0 +1,142 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use csv::{Position, Reader, ReaderBuilder, StringRecord};
+use serde::{Deserialize, Serialize};
+
+use crate::fixed_width::FixedWidthSource;
+use crate::json_source::JsonSource;
+use crate::{Error, SplitParams};
+
+/// 输入文件格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InputFormat {
+    Csv,
+    /// JSON Lines（每行一个对象）或顶层为数组的JSON文件
+    Json,
+    /// 定长文本，需要配合列布局使用
+    FixedWidth,
+}
+
+impl InputFormat {
+    /// 根据文件扩展名推断输入格式，无法识别时按CSV处理
+    ///
+    /// 定长文本没有固定的扩展名，只能通过显式指定或提供列布局来启用。
+    pub fn from_path(path: &Path) -> InputFormat {
+        match path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase())
+            .as_deref()
+        {
+            Some("json") | Some("jsonl") | Some("ndjson") => InputFormat::Json,
+            _ => InputFormat::Csv,
+        }
+    }
+
+    /// 该格式允许的文件扩展名，为空表示不限制
+    pub(crate) fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            InputFormat::Csv => &["csv"],
+            InputFormat::Json => &["json", "jsonl", "ndjson"],
+            InputFormat::FixedWidth => &[],
+        }
+    }
+}
+
+/// 统一的记录读取接口，分割器和各类输入格式通过它解耦
+pub(crate) trait RecordSource {
+    /// 标题行（无标题行时为生成的默认列名）
+    fn headers(&self) -> &StringRecord;
+
+    /// 读取下一条记录，文件结束时返回 `Ok(false)`
+    fn read_record(&mut self, record: &mut StringRecord) -> Result<bool, Error>;
+
+    /// 下一条记录在输入文件中的位置（字节、行号和记录序号），用于断点续传；不支持定位的格式返回 `None`
+    fn position(&self) -> Option<Position> {
+        None
+    }
+
+    /// 跳到 `position` 返回过的位置继续读取，之后报告的行号从该位置接着计数
+    fn seek(&mut self, _position: Position) -> Result<(), Error> {
+        Err(Error::CheckpointUnsupported)
+    }
+}
+
+/// CSV输入
+pub(crate) struct CsvSource {
+    reader: Reader<BufReader<File>>,
+    headers: StringRecord,
+}
+
+impl CsvSource {
+    /// `flexible` 为真时允许各行字段数不同，由调用方补齐或截断
+    pub(crate) fn open(input_path: &Path, has_header: bool, flexible: bool) -> Result<CsvSource, Error> {
+        let file = File::open(input_path)
+            .map_err(|e| Error::io(input_path, e))?;
+
+        let mut reader = ReaderBuilder::new()
+            .has_headers(has_header)
+            .flexible(flexible)
+            .from_reader(BufReader::new(file));
+
+        // 读取标题行（如果有）
+        let headers = if has_header {
+            reader.headers()?.clone()
+        } else {
+            // 如果没有标题行，生成默认列名
+            let column_count = reader.headers()?.len();
+            if column_count == 0 {
+                return Err(Error::NoDataRows);
+            }
+
+            default_headers(column_count)
+        };
+
+        Ok(CsvSource { reader, headers })
+    }
+}
+
+impl RecordSource for CsvSource {
+    fn headers(&self) -> &StringRecord {
+        &self.headers
+    }
+
+    fn read_record(&mut self, record: &mut StringRecord) -> Result<bool, Error> {
+        Ok(self.reader.read_record(record)?)
+    }
+
+    fn position(&self) -> Option<Position> {
+        Some(self.reader.position().clone())
+    }
+
+    fn seek(&mut self, position: Position) -> Result<(), Error> {
+        Ok(self.reader.seek(position)?)
+    }
+}
+
+/// 生成 `column_1..column_N` 形式的默认列名
+pub(crate) fn default_headers(column_count: usize) -> StringRecord {
+    StringRecord::from(
+        (0..column_count)
+            .map(|i| format!("column_{}", i + 1))
+            .collect::<Vec<_>>()
+    )
+}
+
+/// 按分割参数中的输入格式打开记录源
+pub(crate) fn open_source(params: &SplitParams) -> Result<Box<dyn RecordSource>, Error> {
+    let input_path = Path::new(&params.input_path);
+    match params.input_format() {
+        InputFormat::Csv => Ok(Box::new(CsvSource::open(input_path, params.has_header, params.flexible)?)),
+        InputFormat::Json => Ok(Box::new(JsonSource::open(input_path)?)),
+        InputFormat::FixedWidth => {
+            let layout = params.fixed_width_layout
+                .as_ref()
+                .ok_or(Error::LayoutRequired)?;
+            Ok(Box::new(FixedWidthSource::open(input_path, layout, params.has_header)?))
+        }
+    }
+}
@@ -0,0 +1,299 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use csv::{ReaderBuilder, StringRecord};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::dedupe::Duplicates;
+use crate::header::resolve_headers;
+use crate::i18n::Message;
+use crate::manifest::read_manifest;
+use crate::merge::natural_cmp;
+use crate::output::{file_stem, staging_dir};
+use crate::pipeline::{PreparedRecords, RowPipeline};
+use crate::progress::NoProgress;
+use crate::projection::{project, Projection};
+use crate::sort::Sorter;
+use crate::source::open_source;
+use crate::{Error, SplitParams};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerifyParams {
+    /// 与分割时相同的参数，用于按同样的方式读取原文件并定位分块
+    #[serde(flatten)]
+    pub split: SplitParams,
+    /// 要校验的分块文件（按顺序），为空时优先使用输出目录中的清单，
+    /// 没有清单时查找 `<原文件名>_<序号>.csv`
+    #[serde(default)]
+    pub pieces: Vec<String>,
+}
+
+/// 分块还原校验的结果
+#[derive(Debug, Clone, Serialize)]
+pub struct VerifyReport {
+    pub matched: bool,
+    pub piece_count: usize,
+    pub input_rows: usize,
+    pub piece_rows: usize,
+    /// 原文件数据行的SHA-256
+    pub input_hash: String,
+    /// 所有分块数据行依次拼接后的SHA-256
+    pub pieces_hash: String,
+    /// 第一处不一致的位置
+    pub mismatch: Option<VerifyMismatch>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VerifyMismatch {
+    pub piece: String,
+    /// 分块中的数据行号（从1开始，0表示标题行）
+    pub row: usize,
+    /// 对应的原文件数据行号（从1开始，与分割时一样计入跳过、筛掉和去重的行），
+    /// 0表示原文件中没有对应的行（标题行或多出的行）
+    pub source_row: usize,
+    pub reason: Message,
+}
+
+impl VerifyReport {
+    /// 校验失败时给用户看的简要说明
+    pub fn summary(&self) -> Message {
+        match self.mismatch {
+            Some(ref m) if m.source_row == 0 => Message::new("verify.mismatch_no_source")
+                .arg("piece", m.piece.as_str())
+                .arg("row", m.row.to_string())
+                .arg("reason", m.reason.clone()),
+            Some(ref m) => Message::new("verify.mismatch")
+                .arg("piece", m.piece.as_str())
+                .arg("row", m.row.to_string())
+                .arg("source_row", m.source_row.to_string())
+                .arg("reason", m.reason.clone()),
+            None => Message::new("verify.row_count")
+                .arg("input_rows", self.input_rows.to_string())
+                .arg("piece_rows", self.piece_rows.to_string()),
+        }
+    }
+}
+
+/// 查找分块文件：优先按清单中的顺序，否则在输出目录中按序号查找
+pub(crate) fn find_pieces(params: &SplitParams) -> Result<Vec<PathBuf>, Error> {
+    let output_dir = Path::new(&params.output_dir);
+    let file_stem = Path::new(&params.input_path)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("output");
+
+    if let Some(manifest) = read_manifest(output_dir, file_stem)? {
+        return Ok(manifest.pieces.iter().map(|p| PathBuf::from(&p.path)).collect());
+    }
+
+    let prefix = format!("{}_", file_stem);
+
+    let mut pieces: Vec<PathBuf> = std::fs::read_dir(output_dir)
+        .map_err(|e| Error::io(output_dir, e))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.extension().and_then(|s| s.to_str()) == Some("csv")
+                && path.file_stem()
+                    .and_then(|s| s.to_str())
+                    .and_then(|s| s.strip_prefix(&prefix))
+                    .is_some_and(|index| !index.is_empty() && index.bytes().all(|b| b.is_ascii_digit()))
+        })
+        .collect();
+
+    pieces.sort_by(|a, b| natural_cmp(&a.to_string_lossy(), &b.to_string_lossy()));
+    Ok(pieces)
+}
+
+/// 依次读取所有分块（跳过每个分块的标题行），与原文件逐行比对，
+/// 同时统计双方的行数和内容哈希
+pub(crate) fn verify_pieces(params: &SplitParams, pieces: &[PathBuf]) -> Result<VerifyReport, Error> {
+    if pieces.is_empty() {
+        return Err(Message::new("verify.no_pieces").into());
+    }
+
+    let source = open_source(params)?;
+    let source_headers = resolve_headers(params, source.headers())?;
+    // 分块中是补齐或截断、追加计算列、按条件筛选、去重、排序、再按列选择投影后的行，原文件的行按同样的方式处理后再比对；
+    // 分割时跳过的格式错误的行不会出现在分块中，校验时同样跳过
+    let pipeline = RowPipeline::new(params, &source_headers)?;
+    let projection = Projection::new(params, pipeline.headers())?;
+    let mut projected = StringRecord::new();
+    let expected_headers = project(projection.as_ref(), pipeline.headers(), &mut projected).clone();
+    // 单独校验时暂存目录可能已不存在，去重的临时文件改写在系统临时目录中
+    let work_dir = staging_dir(Path::new(&params.output_dir), file_stem(params));
+    let work_dir = if work_dir.is_dir() { work_dir } else { std::env::temp_dir() };
+    let mut duplicates = Duplicates::scan(params, &pipeline, &work_dir, &NoProgress)?;
+    let mut records = PreparedRecords::new(params, source, &pipeline);
+    // 处理后、去掉重复行后原文件中的下一行，返回它在原文件中的数据行号
+    let mut next_kept = |record: &mut StringRecord| -> Result<Option<usize>, Error> {
+        while records.next(record)? {
+            if let Some(ref mut duplicates) = duplicates
+                && duplicates.is_duplicate()?
+            {
+                continue;
+            }
+            return Ok(Some(records.rows_read()));
+        }
+        Ok(None)
+    };
+    // 分割时排过序的，原文件的行同样排序后再比对
+    let mut sorted = match Sorter::new(params, pipeline.headers(), &work_dir)? {
+        Some(mut sorter) => {
+            let mut record = StringRecord::new();
+            while let Some(row_number) = next_kept(&mut record)? {
+                sorter.push(&record, row_number, false)?;
+            }
+            Some(sorter.finish()?)
+        }
+        None => None,
+    };
+    // 读取原文件中下一条应出现在分块中的记录，返回它在原文件中的数据行号
+    let mut next_source_record = |record: &mut StringRecord| -> Result<Option<usize>, Error> {
+        let row_number = match sorted {
+            Some(ref mut sorted) => match sorted.next()? {
+                Some(row) => {
+                    *record = row.record;
+                    row.row_number
+                }
+                None => return Ok(None),
+            },
+            None => match next_kept(record)? {
+                Some(row_number) => row_number,
+                None => return Ok(None),
+            },
+        };
+        if let Some(ref projection) = projection {
+            projection.apply(record, &mut projected);
+            std::mem::swap(record, &mut projected);
+        }
+        Ok(Some(row_number))
+    };
+
+    let mut input_hasher = Sha256::new();
+    let mut pieces_hasher = Sha256::new();
+    let mut input_rows = 0;
+    let mut piece_rows = 0;
+    let mut mismatch: Option<VerifyMismatch> = None;
+    let mut source_record = StringRecord::new();
+    let mut piece_record = StringRecord::new();
+    let mut source_finished = false;
+    let mut source_row = 0;
+    let mut last_piece_rows = 0;
+
+    for piece in pieces {
+        let piece_name = piece.display().to_string();
+        if piece.extension().and_then(|s| s.to_str()) != Some("csv") {
+            return Err(Error::invalid_params(Message::new("verify.not_csv").arg("path", piece_name)));
+        }
+
+        let file = File::open(piece)
+            .map_err(|e| Error::io(piece, e))?;
+        let mut reader = ReaderBuilder::new()
+            .has_headers(true)
+            .flexible(params.flexible)
+            .from_reader(BufReader::new(file));
+
+        let headers = reader.headers()
+            .map_err(|e| Message::new("verify.read_header").arg("path", piece_name.as_str()).arg("reason", e.to_string()))?;
+        if mismatch.is_none() && *headers != expected_headers {
+            mismatch = Some(VerifyMismatch {
+                piece: piece_name.clone(),
+                row: 0,
+                source_row: 0,
+                reason: Message::new("verify.header_differs"),
+            });
+        }
+
+        let mut row = 0;
+        loop {
+            let has_record = reader.read_record(&mut piece_record)
+                .map_err(|e| {
+                    Message::new("verify.read_row")
+                        .arg("path", piece_name.as_str())
+                        .arg("row", (row + 1).to_string())
+                        .arg("reason", e.to_string())
+                })?;
+            if !has_record {
+                break;
+            }
+            row += 1;
+            piece_rows += 1;
+            hash_record(&mut pieces_hasher, &piece_record);
+
+            // 原文件逐行跟进
+            if !source_finished {
+                match next_source_record(&mut source_record)? {
+                    Some(row_number) => {
+                        source_row = row_number;
+                        input_rows += 1;
+                        hash_record(&mut input_hasher, &source_record);
+                    }
+                    None => source_finished = true,
+                }
+            }
+
+            if mismatch.is_none() {
+                if source_finished {
+                    mismatch = Some(VerifyMismatch {
+                        piece: piece_name.clone(),
+                        row,
+                        source_row: 0,
+                        reason: Message::new("verify.extra_row"),
+                    });
+                } else if piece_record != source_record {
+                    mismatch = Some(VerifyMismatch {
+                        piece: piece_name.clone(),
+                        row,
+                        source_row,
+                        reason: Message::new("verify.row_differs"),
+                    });
+                }
+            }
+        }
+        last_piece_rows = row;
+    }
+
+    // 原文件剩余的行都没有出现在分块中
+    while !source_finished {
+        match next_source_record(&mut source_record)? {
+            Some(row_number) => {
+                input_rows += 1;
+                hash_record(&mut input_hasher, &source_record);
+                if mismatch.is_none() {
+                    let last_piece = pieces[pieces.len() - 1].display().to_string();
+                    mismatch = Some(VerifyMismatch {
+                        piece: last_piece,
+                        row: last_piece_rows + 1,
+                        source_row: row_number,
+                        reason: Message::new("verify.missing_row"),
+                    });
+                }
+            }
+            None => source_finished = true,
+        }
+    }
+
+    let input_hash = format!("{:x}", input_hasher.finalize());
+    let pieces_hash = format!("{:x}", pieces_hasher.finalize());
+
+    Ok(VerifyReport {
+        matched: mismatch.is_none() && input_rows == piece_rows && input_hash == pieces_hash,
+        piece_count: pieces.len(),
+        input_rows,
+        piece_rows,
+        input_hash,
+        pieces_hash,
+        mismatch,
+    })
+}
+
+/// 按字段哈希，字段前写入长度，避免引号、分隔符的写法差异影响结果
+fn hash_record(hasher: &mut Sha256, record: &StringRecord) {
+    hasher.update((record.len() as u64).to_le_bytes());
+    for field in record.iter() {
+        hasher.update((field.len() as u64).to_le_bytes());
+        hasher.update(field.as_bytes());
+    }
+}
@@ -0,0 +1,462 @@
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::batch::wildcard_match;
+use crate::i18n::Message;
+use crate::progress::NoProgress;
+use crate::{split_single, Error, SplitParams, SplitResult};
+
+/// 监视模式的参数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchParams {
+    /// 监视的目录（不含子目录）
+    pub watch_dir: String,
+    /// 文件名模式，支持 `*` 和 `?`，默认为 `*.csv`
+    #[serde(default)]
+    pub pattern: Option<String>,
+    /// 分割参数预设，`input_path` 会被替换为检测到的文件
+    #[serde(default)]
+    pub preset: Option<SplitParams>,
+    /// 保存的预设文件（JSON格式的分割参数），未提供 `preset` 时使用
+    #[serde(default)]
+    pub preset_path: Option<String>,
+    /// 处理成功的文件移动到的归档目录，默认为监视目录下的 `archive`
+    #[serde(default)]
+    pub archive_dir: Option<String>,
+    /// 日志文件，默认为预设输出目录下的 `csv-splitter-watch.log`
+    #[serde(default)]
+    pub log_path: Option<String>,
+    /// 扫描间隔（秒），0表示使用默认值
+    #[serde(default)]
+    pub poll_interval_secs: u64,
+    /// 文件大小和修改时间保持不变多久后才认为已写完（秒），0表示使用默认值
+    #[serde(default)]
+    pub stable_secs: u64,
+}
+
+/// 默认扫描间隔（秒）
+const DEFAULT_POLL_INTERVAL_SECS: u64 = 5;
+
+/// 默认的文件稳定时间（秒）
+const DEFAULT_STABLE_SECS: u64 = 10;
+
+/// 单个文件处理完成后发给前端的事件
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchEvent {
+    pub input_path: String,
+    /// 归档后的路径，失败时文件保留在原处
+    pub archived_path: Option<String>,
+    pub result: SplitResult,
+}
+
+/// 文件最近一次观察到的状态
+struct Observed {
+    size: u64,
+    modified: Option<SystemTime>,
+    /// 大小和修改时间最近一次变化的时间
+    changed_at: Instant,
+    /// 以当前内容处理失败过，文件再次变化前不再重试
+    failed: bool,
+}
+
+/// 监视目录的扫描状态
+pub(crate) struct Watcher {
+    watch_dir: PathBuf,
+    pattern: String,
+    preset: SplitParams,
+    archive_dir: PathBuf,
+    log_path: PathBuf,
+    stable: Duration,
+    pub(crate) interval: Duration,
+    observed: HashMap<PathBuf, Observed>,
+}
+
+impl Watcher {
+    pub(crate) fn new(params: &WatchParams) -> Result<Watcher, Error> {
+        let watch_dir = PathBuf::from(&params.watch_dir);
+        if !watch_dir.is_dir() {
+            return Err(Error::NotFound { path: params.watch_dir.clone() });
+        }
+
+        let preset = match (&params.preset, &params.preset_path) {
+            (Some(preset), _) => preset.clone(),
+            (None, Some(preset_path)) => load_preset(Path::new(preset_path))?,
+            (None, None) => return Err(Error::invalid_params(Message::new("watch.preset_required"))),
+        };
+        preset.name_template()?;
+
+        // 分块写回监视目录会被当作新文件再次分割
+        if same_dir(Path::new(&preset.output_dir), &watch_dir) {
+            return Err(Error::invalid_params(Message::new("watch.output_is_watch_dir")));
+        }
+
+        let archive_dir = params.archive_dir
+            .as_ref()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| watch_dir.join("archive"));
+        std::fs::create_dir_all(&archive_dir).map_err(|e| Error::io(&archive_dir, e))?;
+
+        // 日志默认写在输出目录中，不往监视目录里添加文件
+        let log_path = match params.log_path {
+            Some(ref log_path) => PathBuf::from(log_path),
+            None => {
+                let output_dir = Path::new(&preset.output_dir);
+                std::fs::create_dir_all(output_dir).map_err(|e| Error::io(output_dir, e))?;
+                output_dir.join("csv-splitter-watch.log")
+            }
+        };
+
+        let secs = |value: u64, default: u64| Duration::from_secs(if value == 0 { default } else { value });
+
+        Ok(Watcher {
+            pattern: params.pattern.clone().unwrap_or_else(|| "*.csv".into()),
+            preset,
+            archive_dir,
+            log_path,
+            stable: secs(params.stable_secs, DEFAULT_STABLE_SECS),
+            interval: secs(params.poll_interval_secs, DEFAULT_POLL_INTERVAL_SECS),
+            observed: HashMap::new(),
+            watch_dir,
+        })
+    }
+
+    /// 扫描一次监视目录，分割已经稳定的新文件，返回本次处理的结果
+    pub(crate) fn poll(&mut self) -> Vec<WatchEvent> {
+        let now = Instant::now();
+        let mut present = Vec::new();
+        let mut ready = Vec::new();
+
+        let entries = match std::fs::read_dir(&self.watch_dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                self.log("ERROR", &self.watch_dir, Message::new("watch.log.read_failed").arg("reason", e.to_string()));
+                return Vec::new();
+            }
+        };
+        for path in entries.filter_map(|entry| entry.ok().map(|e| e.path())) {
+            let Some(name) = path.file_name().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            if name.starts_with('.') || !wildcard_match(&self.pattern, name) {
+                continue;
+            }
+            let Ok(metadata) = std::fs::metadata(&path) else {
+                continue;
+            };
+            if !metadata.is_file() {
+                continue;
+            }
+            let (size, modified) = (metadata.len(), metadata.modified().ok());
+            present.push(path.clone());
+
+            let observed = self.observed.entry(path.clone()).or_insert(Observed {
+                size,
+                modified,
+                changed_at: now,
+                failed: false,
+            });
+            if observed.size != size || observed.modified != modified {
+                observed.size = size;
+                observed.modified = modified;
+                observed.changed_at = now;
+                observed.failed = false;
+                continue;
+            }
+            if !observed.failed && now.duration_since(observed.changed_at) >= self.stable {
+                ready.push(path);
+            }
+        }
+        self.observed.retain(|path, _| present.contains(path));
+
+        ready.sort();
+        ready.into_iter().map(|path| self.process(&path)).collect()
+    }
+
+    /// 分割单个文件，成功后移入归档目录
+    fn process(&mut self, path: &Path) -> WatchEvent {
+        let mut params = self.preset.clone();
+        params.input_path = path.display().to_string();
+        params.input_paths = Vec::new();
+        params.input_dir = None;
+
+        let mut result = split_single(params, &NoProgress).unwrap_or_else(SplitResult::failure);
+        let mut archived_path = None;
+        if result.success {
+            match archive(path, &self.archive_dir) {
+                Ok(archived) => {
+                    self.observed.remove(path);
+                    archived_path = Some(archived.display().to_string());
+                }
+                Err(e) => {
+                    // 分块已经生成但无法归档，按失败处理，避免下次扫描时重复分割
+                    result.success = false;
+                    result.error = Some(e);
+                }
+            }
+        }
+        if !result.success
+            && let Some(observed) = self.observed.get_mut(path)
+        {
+            observed.failed = true;
+        }
+
+        match (&result.error, &archived_path) {
+            (None, Some(archived)) => self.log(
+                "OK",
+                path,
+                Message::new("watch.log.done")
+                    .arg("files", result.file_count.to_string())
+                    .arg("path", archived.as_str()),
+            ),
+            (error, _) => self.log(
+                "FAILED",
+                path,
+                error.as_ref().map_or_else(|| Message::new("watch.log.unknown_error"), |e| Message::Text(e.to_string())),
+            ),
+        }
+
+        WatchEvent {
+            input_path: path.display().to_string(),
+            archived_path,
+            result,
+        }
+    }
+
+    /// 追加一行日志，说明按系统语言写入，日志写入失败不影响处理
+    fn log(&self, status: &str, path: &Path, message: Message) {
+        if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(&self.log_path) {
+            let time = chrono::Local::now().format("%Y-%m-%d %H:%M:%S");
+            let _ = writeln!(file, "{}\t{}\t{}\t{}", time, status, path.display(), message);
+        }
+    }
+}
+
+/// 正在运行的监视任务
+struct WatchHandle {
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+
+/// 监视任务的全局状态，同一时间只运行一个监视任务
+#[derive(Default)]
+pub struct WatchState {
+    handle: Mutex<Option<WatchHandle>>,
+}
+
+impl WatchState {
+    /// 在后台线程中开始监视，每处理完一个文件调用一次 `on_event`
+    pub fn start<F>(&self, params: &WatchParams, mut on_event: F) -> Result<(), Error>
+    where
+        F: FnMut(&WatchEvent) + Send + 'static,
+    {
+        let mut handle = self.handle.lock().map_err(|_| Message::new("watch.thread_panicked"))?;
+        if handle.as_ref().is_some_and(|h| !h.thread.is_finished()) {
+            return Err(Message::new("watch.already_running").into());
+        }
+
+        let mut watcher = Watcher::new(params)?;
+        watcher.log("START", &watcher.watch_dir, Message::new("watch.log.start"));
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = Arc::clone(&stop);
+        let thread = std::thread::spawn(move || {
+            while !thread_stop.load(Ordering::Relaxed) {
+                for event in watcher.poll() {
+                    on_event(&event);
+                }
+                // 分段等待，停止时不必等满一个扫描间隔
+                let deadline = Instant::now() + watcher.interval;
+                while Instant::now() < deadline && !thread_stop.load(Ordering::Relaxed) {
+                    std::thread::sleep(Duration::from_millis(200));
+                }
+            }
+            watcher.log("STOP", &watcher.watch_dir, Message::new("watch.log.stop"));
+        });
+
+        *handle = Some(WatchHandle { stop, thread });
+        Ok(())
+    }
+
+    /// 停止监视，等待正在处理的文件完成；没有运行中的任务时返回 `false`
+    pub fn stop(&self) -> Result<bool, Error> {
+        let handle = self.handle.lock().map_err(|_| Message::new("watch.thread_panicked"))?.take();
+        match handle {
+            Some(handle) => {
+                handle.stop.store(true, Ordering::Relaxed);
+                handle.thread.join().map_err(|_| Message::new("watch.thread_panicked"))?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.handle
+            .lock()
+            .map(|h| h.as_ref().is_some_and(|h| !h.thread.is_finished()))
+            .unwrap_or(false)
+    }
+}
+
+fn load_preset(path: &Path) -> Result<SplitParams, Error> {
+    let file = File::open(path).map_err(|e| Error::io(path, e))?;
+    serde_json::from_reader(BufReader::new(file)).map_err(|e| Error::Parse {
+        row: None,
+        byte: None,
+        reason: Message::new("watch.invalid_preset").arg("path", path.display().to_string()).arg("reason", e.to_string()),
+    })
+}
+
+/// 移入归档目录，同名文件已存在时在文件名后追加时间戳
+fn archive(path: &Path, archive_dir: &Path) -> Result<PathBuf, Error> {
+    let name = path.file_name().ok_or_else(|| Error::NotFound { path: path.display().to_string() })?;
+    let mut target = archive_dir.join(name);
+    if target.exists() {
+        let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("input");
+        let timestamp = chrono::Local::now().format("%Y%m%d%H%M%S");
+        target = match path.extension().and_then(|s| s.to_str()) {
+            Some(extension) => archive_dir.join(format!("{}_{}.{}", stem, timestamp, extension)),
+            None => archive_dir.join(format!("{}_{}", stem, timestamp)),
+        };
+    }
+
+    // 归档目录可能在其他磁盘上，重命名失败时改为复制后删除
+    if std::fs::rename(path, &target).is_err() {
+        std::fs::copy(path, &target).map_err(|e| Error::io(&target, e))?;
+        std::fs::remove_file(path).map_err(|e| Error::io(path, e))?;
+    }
+    Ok(target)
+}
+
+/// 输出目录可能还不存在，按规范化后的绝对路径比较
+fn same_dir(a: &Path, b: &Path) -> bool {
+    normalize_dir(a) == normalize_dir(b)
+}
+
+/// 转为绝对路径并去掉 `.` 和 `..`，已存在的部分再解析符号链接，尚未创建的部分保持原样
+fn normalize_dir(path: &Path) -> PathBuf {
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut normalized = PathBuf::new();
+    for component in absolute.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+
+    let mut existing = normalized.as_path();
+    let mut missing = Vec::new();
+    while !existing.exists() {
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                missing.push(name);
+                existing = parent;
+            }
+            _ => break,
+        }
+    }
+    let mut resolved = existing.canonicalize().unwrap_or_else(|_| existing.to_path_buf());
+    resolved.extend(missing.iter().rev());
+    resolved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup(name: &str) -> (PathBuf, WatchParams) {
+        let dir = std::env::temp_dir().join(format!("csv-splitter-watch-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("in")).unwrap();
+        let preset: SplitParams = serde_json::from_value(serde_json::json!({
+            "input_path": "",
+            "output_dir": dir.join("out").display().to_string(),
+            "rows_per_file": 1,
+            "has_header": true,
+            "convert_to_excel": false,
+        }))
+        .unwrap();
+        let params = WatchParams {
+            watch_dir: dir.join("in").display().to_string(),
+            pattern: None,
+            preset: Some(preset),
+            preset_path: None,
+            archive_dir: None,
+            log_path: None,
+            poll_interval_secs: 0,
+            stable_secs: 0,
+        };
+        (dir, params)
+    }
+
+    fn file_names(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn output_dir_must_not_resolve_to_the_watch_dir() {
+        let (dir, mut params) = setup("same-dir");
+        for output_dir in [dir.join("in"), dir.join("in").join("missing").join(".."), dir.join("out").join("..").join("in")] {
+            params.preset.as_mut().unwrap().output_dir = output_dir.display().to_string();
+            let error = Watcher::new(&params).err().unwrap();
+            assert!(format!("{:?}", error).contains("watch.output_is_watch_dir"), "{}", output_dir.display());
+        }
+        assert!(!dir.join("in").join("missing").exists());
+        assert!(!same_dir(&dir.join("in").join("missing"), &dir.join("in")));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn stable_files_are_split_and_archived() {
+        let (dir, params) = setup("poll");
+        let watch_dir = dir.join("in");
+        std::fs::write(watch_dir.join("a.csv"), "id\n1\n2\n").unwrap();
+        std::fs::write(watch_dir.join("b.csv"), "").unwrap();
+        std::fs::write(watch_dir.join("notes.txt"), "id\n1\n").unwrap();
+        std::fs::create_dir_all(watch_dir.join("archive")).unwrap();
+        std::fs::write(watch_dir.join("archive").join("a.csv"), "old").unwrap();
+        let mut watcher = Watcher::new(&params).unwrap();
+        watcher.stable = Duration::ZERO;
+
+        // a.csv 分割后归档，归档目录中已有同名文件时加上时间戳；空文件 b.csv 失败后留在原处
+        let events = watcher.poll();
+        let outcomes: Vec<_> = events.iter().map(|e| (e.input_path.ends_with("a.csv"), e.result.success)).collect();
+        assert_eq!(outcomes, [(true, true), (false, false)]);
+        let archived = PathBuf::from(events[0].archived_path.as_ref().unwrap());
+        assert!(archived.file_name().unwrap().to_str().unwrap().starts_with("a_"));
+        assert_eq!(std::fs::read_to_string(&archived).unwrap(), "id\n1\n2\n");
+        assert_eq!(events[1].archived_path, None);
+        assert_eq!(file_names(&watch_dir), ["archive", "b.csv", "notes.txt"]);
+        assert_eq!(file_names(&dir.join("out")), ["a_1.csv", "a_2.csv", "a_manifest.json", "csv-splitter-watch.log"]);
+
+        // 失败的文件内容不变时不再重试，修改后等下一次扫描确认稳定再处理
+        assert!(watcher.poll().is_empty());
+        std::fs::write(watch_dir.join("b.csv"), "id\n3\n").unwrap();
+        assert!(watcher.poll().is_empty());
+        let events = watcher.poll();
+        assert_eq!(events.len(), 1);
+        assert!(events[0].result.success, "{:?}", events[0].result.error);
+        assert_eq!(file_names(&watch_dir.join("archive")).len(), 3);
+
+        let log = std::fs::read_to_string(dir.join("out").join("csv-splitter-watch.log")).unwrap();
+        let statuses: Vec<_> = log.lines().map(|line| line.split('\t').nth(1).unwrap()).collect();
+        assert_eq!(statuses, ["OK", "FAILED", "OK"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
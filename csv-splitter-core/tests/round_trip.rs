@@ -0,0 +1,522 @@
+//! 分割后按同样的参数还原校验，每个选项至少覆盖一次
+
+use std::path::PathBuf;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use csv_splitter_core::{
+    resume, split, verify, Locale, NoProgress, Progress, ProgressListener, ResumeParams, SplitParams, SplitResult,
+    VerifyParams,
+};
+use serde_json::{json, Value};
+
+/// 每个用例独立的临时目录
+struct Fixture {
+    dir: PathBuf,
+}
+
+impl Fixture {
+    fn new(name: &str) -> Fixture {
+        let dir = std::env::temp_dir().join(format!("csv-splitter-round-trip-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        Fixture { dir }
+    }
+
+    fn input(&self, name: &str, content: &str) -> String {
+        let path = self.dir.join(name);
+        std::fs::write(&path, content).unwrap();
+        path.display().to_string()
+    }
+
+    fn output_dir(&self) -> String {
+        self.dir.join("out").display().to_string()
+    }
+
+    /// 以 `extra` 覆盖默认参数分割，分割时开启校验，完成后再单独校验一次
+    fn split_and_verify(&self, input: &str, extra: Value) -> SplitResult {
+        let mut value = json!({
+            "input_path": input,
+            "output_dir": self.output_dir(),
+            "rows_per_file": 2,
+            "has_header": true,
+            "convert_to_excel": false,
+            "verify": true,
+        });
+        for (key, field) in extra.as_object().unwrap() {
+            value[key] = field.clone();
+        }
+        let params: SplitParams = serde_json::from_value(value).unwrap();
+        let result = split(params.clone(), &NoProgress);
+        assert!(result.success, "{:?}", result.error);
+        let report = result.verification.as_ref().expect("分割时应返回校验结果");
+        assert!(report.matched, "{:?}", report);
+
+        let report = verify(&VerifyParams { split: params, pieces: Vec::new() }).unwrap();
+        assert!(report.matched, "{:?}", report);
+        assert_eq!(report.piece_count, result.file_count);
+        result
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[test]
+fn json_key_collision_goes_through_bad_row_policy() {
+    let fixture = Fixture::new("json-collision");
+    let input = fixture.input(
+        "people.jsonl",
+        "{\"id\":1,\"a\":{\"b\":1}}\n{\"id\":2,\"a\":{\"b\":2},\"a.b\":3}\n{\"id\":3,\"a.b\":4}\n",
+    );
+    let result = fixture.split_and_verify(&input, json!({ "bad_row_policy": "skip" }));
+    assert_eq!(result.rejected_rows, 1);
+    assert_eq!(result.file_count, 1);
+
+    let params: SplitParams = serde_json::from_value(json!({
+        "input_path": input,
+        "output_dir": fixture.dir.join("strict").display().to_string(),
+        "rows_per_file": 2,
+        "has_header": true,
+        "convert_to_excel": false,
+    }))
+    .unwrap();
+    let result = split(params, &NoProgress);
+    assert_eq!(result.error.map(|e| e.code()), Some("duplicate_field"));
+}
+
+/// 读取隔离文件，返回每行的行号、字节位置、错误码和原有的字段
+fn rejected_rows(path: &str) -> Vec<(String, String, String, Vec<String>)> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_path(path).unwrap();
+    assert_eq!(&reader.headers().unwrap().iter().take(4).collect::<Vec<_>>(), &["row", "byte", "code", "reason"]);
+    reader
+        .records()
+        .map(|record| {
+            let record = record.unwrap();
+            (
+                record[0].to_string(),
+                record[1].to_string(),
+                record[2].to_string(),
+                record.iter().skip(4).map(String::from).collect(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn csv_field_count_errors_are_skipped_or_quarantined() {
+    let fixture = Fixture::new("field-count");
+    let input = fixture.input("orders.csv", "id,name\n1,a\n2,b,extra\n3\n4,d\n");
+
+    let result = fixture.split_and_verify(&input, json!({ "bad_row_policy": "skip" }));
+    assert_eq!((result.rejected_rows, result.rejected_file), (2, None));
+    assert_eq!(result.manifest.unwrap().total_rows, 2);
+
+    let result = fixture.split_and_verify(&input, json!({ "bad_row_policy": "quarantine", "overwrite_policy": "overwrite" }));
+    assert_eq!(result.rejected_rows, 2);
+    let pieces = result.manifest.unwrap().pieces;
+    assert_eq!(std::fs::read_to_string(&pieces[0].path).unwrap(), "id,name\n1,a\n4,d\n");
+    assert_eq!(
+        rejected_rows(&result.rejected_file.unwrap()),
+        [
+            ("3".into(), "12".into(), "field_count".into(), vec!["2".into(), "b".into(), "extra".into()]),
+            ("4".into(), "22".into(), "field_count".into(), vec!["3".into()]),
+        ],
+    );
+
+    let error = split(
+        serde_json::from_value(json!({
+            "input_path": input,
+            "output_dir": fixture.dir.join("strict").display().to_string(),
+            "rows_per_file": 2,
+            "has_header": true,
+            "convert_to_excel": false,
+        }))
+        .unwrap(),
+        &NoProgress,
+    )
+    .error
+    .unwrap();
+    let error = serde_json::to_value(&error).unwrap();
+    assert_eq!((&error["code"], &error["row"]), (&json!("field_count"), &json!(3)));
+}
+
+#[test]
+fn malformed_json_lines_are_quarantined() {
+    let fixture = Fixture::new("json-lines");
+    let input = fixture.input("events.jsonl", "{\"id\":1}\n{\"id\": 2,}\n\"text\"\n{\"id\":4}\n");
+    let result = fixture.split_and_verify(&input, json!({ "input_format": "json", "bad_row_policy": "quarantine" }));
+    assert_eq!(result.rejected_rows, 2);
+    let rows: Vec<_> = rejected_rows(&result.rejected_file.unwrap()).into_iter().map(|r| (r.0, r.2)).collect();
+    assert_eq!(rows, [("2".into(), "invalid_record".into()), ("3".into(), "invalid_record".into())]);
+    let piece = &result.manifest.unwrap().pieces[0].path;
+    assert_eq!(std::fs::read_to_string(piece).unwrap(), "id\n1\n4\n");
+}
+
+#[test]
+fn bad_rows_keep_file_positions_when_split_in_parallel() {
+    let fixture = Fixture::new("parallel-bad-rows");
+    // 超过50万行的输入按多线程分块，出错的位置要换算为整个文件中的行号和字节位置
+    let mut content = String::from("id,name\n");
+    let mut expected = Vec::new();
+    for i in 1..=520_000 {
+        if i == 3 || i == 444_444 {
+            expected.push(((i + 1).to_string(), content.len().to_string(), "field_count".to_string(), vec![i.to_string()]));
+            content.push_str(&format!("{}\n", i));
+        } else {
+            content.push_str(&format!("{},n{}\n", i, i));
+        }
+    }
+    let input = fixture.input("big.csv", &content);
+    let result = fixture.split_and_verify(&input, json!({ "rows_per_file": 100_000, "bad_row_policy": "quarantine" }));
+    assert_eq!(result.rejected_rows, 2);
+    assert_eq!(rejected_rows(&result.rejected_file.unwrap()), expected);
+    assert_eq!(result.manifest.unwrap().total_rows, 519_998);
+}
+
+#[test]
+fn mismatch_reports_the_real_source_row() {
+    let fixture = Fixture::new("source-row");
+    let input = fixture.input("orders.csv", "id,amount\n1,5\n2,50\n3,30\n4,1\n5,40\n6,20\n");
+    let extra = json!({ "filter": "amount > 10", "sort": [{ "column": "amount", "compare": "number" }] });
+    let result = fixture.split_and_verify(&input, extra.clone());
+    assert_eq!(result.filtered_rows, 2);
+
+    // 排序后第二个分块为 id=5（原文件第5行）和 id=2，改掉其中的第一行
+    let piece = &result.manifest.as_ref().unwrap().pieces[1].path;
+    let content = std::fs::read_to_string(piece).unwrap();
+    assert_eq!(content, "id,amount\n5,40\n2,50\n");
+    std::fs::write(piece, "id,amount\n5,41\n2,50\n").unwrap();
+
+    let mut value = json!({
+        "input_path": input,
+        "output_dir": fixture.output_dir(),
+        "rows_per_file": 2,
+        "has_header": true,
+        "convert_to_excel": false,
+    });
+    for (key, field) in extra.as_object().unwrap() {
+        value[key] = field.clone();
+    }
+    let report = verify(&VerifyParams { split: serde_json::from_value(value).unwrap(), pieces: Vec::new() }).unwrap();
+    let mismatch = report.mismatch.unwrap();
+    assert_eq!((mismatch.row, mismatch.source_row), (1, 5));
+}
+
+#[test]
+fn clean_prefix_only_removes_earlier_pieces() {
+    let fixture = Fixture::new("clean-prefix");
+    let input = fixture.input("sales.csv", "id\n1\n2\n3\n4\n5\n");
+    let out = fixture.dir.join("out");
+    std::fs::create_dir_all(&out).unwrap();
+    for name in ["sales_9.csv", "sales_notes.csv", "sales_2023.xlsx", "sales_summary.xlsx"] {
+        std::fs::write(out.join(name), "old\n").unwrap();
+    }
+
+    let result = fixture.split_and_verify(&input, json!({ "overwrite_policy": "clean_prefix" }));
+    assert_eq!(result.file_count, 3);
+    assert!(!out.join("sales_9.csv").exists());
+    assert!(!out.join("sales_2023.xlsx").exists());
+    assert!(out.join("sales_notes.csv").exists());
+    assert!(out.join("sales_summary.xlsx").exists());
+
+    // 换了命名模板后，上次清单中的分块同样会被清理
+    let result = fixture.split_and_verify(
+        &input,
+        json!({ "overwrite_policy": "clean_prefix", "rows_per_file": 5, "file_name_template": "sales_all_{index}" }),
+    );
+    assert_eq!(result.file_count, 1);
+    assert!(!out.join("sales_1.csv").exists());
+    assert!(out.join("sales_all_1.csv").exists());
+    assert!(out.join("sales_notes.csv").exists());
+}
+
+#[test]
+fn checkpoint_is_saved_before_the_first_piece() {
+    let fixture = Fixture::new("checkpoint");
+    let input = fixture.input("orders.csv", "id,amount\n1,5\n2,50\n3,30\n4,1\n5,40\n");
+    let result = fixture.split_and_verify(&input, json!({ "checkpoint": true }));
+    assert_eq!(result.file_count, 3);
+    assert!(!result.checkpoint_saved);
+
+    // 第一个分块写完之前出错，断点已经存在，续传能找到它
+    let input = fixture.input("broken.csv", "id,amount\n1,5\n2,50,extra\n3,30\n");
+    let params: SplitParams = serde_json::from_value(json!({
+        "input_path": input,
+        "output_dir": fixture.output_dir(),
+        "rows_per_file": 5,
+        "has_header": true,
+        "convert_to_excel": false,
+        "checkpoint": true,
+    }))
+    .unwrap();
+    let result = split(params, &NoProgress);
+    assert_eq!(result.error.map(|e| e.code()), Some("field_count"));
+    assert!(result.checkpoint_saved);
+    let resumed = resume(&ResumeParams { input_path: input, output_dir: fixture.output_dir() }, &NoProgress);
+    assert_eq!(resumed.error.map(|e| e.code()), Some("field_count"));
+    assert!(resumed.checkpoint_saved);
+}
+
+/// 写完指定数量的分块后取消任务，模拟中途退出
+struct CancelAfter {
+    pieces: usize,
+    finished: AtomicUsize,
+}
+
+impl ProgressListener for CancelAfter {
+    fn on_progress(&self, progress: &Progress) {
+        self.finished.store(progress.pieces, Ordering::SeqCst);
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.finished.load(Ordering::SeqCst) >= self.pieces
+    }
+}
+
+#[test]
+fn resumed_split_keeps_row_numbers() {
+    let fixture = Fixture::new("resume");
+    // 第5行和第25003行数据字段数不对，分别在中断前和续传后读到
+    let mut content = String::from("id,name\n");
+    for i in 1..=35_000 {
+        if i == 5 || i == 25_003 {
+            content.push_str(&format!("{}\n", i));
+        } else {
+            content.push_str(&format!("{},n{}\n", i, i));
+        }
+    }
+    let input = fixture.input("big.csv", &content);
+    let params: SplitParams = serde_json::from_value(json!({
+        "input_path": input,
+        "output_dir": fixture.output_dir(),
+        "rows_per_file": 10_000,
+        "has_header": true,
+        "convert_to_excel": false,
+        "checkpoint": true,
+        "verify": true,
+        "bad_row_policy": "quarantine",
+    }))
+    .unwrap();
+
+    let result = split(params.clone(), &CancelAfter { pieces: 1, finished: AtomicUsize::new(0) });
+    assert_eq!(result.error.map(|e| e.code()), Some("cancelled"));
+    assert!(result.checkpoint_saved);
+
+    let result = resume(&ResumeParams { input_path: input, output_dir: fixture.output_dir() }, &NoProgress);
+    assert!(result.success, "{:?}", result.error);
+    assert!(result.verification.unwrap().matched);
+    assert_eq!(result.rejected_rows, 2);
+    let rows: Vec<_> = rejected_rows(&result.rejected_file.unwrap()).into_iter().map(|r| (r.0, r.3)).collect();
+    assert_eq!(rows, [("6".into(), vec!["5".to_string()]), ("25004".into(), vec!["25003".to_string()])]);
+    let ranges: Vec<_> = result.manifest.unwrap().pieces.iter().map(|p| (p.first_row, p.last_row)).collect();
+    assert_eq!(ranges, [(1, 10_001), (10_002, 20_001), (20_002, 30_002), (30_003, 35_000)]);
+
+    let report = verify(&VerifyParams { split: params, pieces: Vec::new() }).unwrap();
+    assert!(report.matched, "{:?}", report);
+}
+
+#[test]
+fn fixed_width_with_a_bad_line() {
+    let fixture = Fixture::new("fixed-width");
+    let path = fixture.dir.join("people.txt");
+    std::fs::write(&path, b"  1alice\n  2b\xffb  \n\n  3carol\n  4dave \n").unwrap();
+    let result = fixture.split_and_verify(
+        &path.display().to_string(),
+        json!({
+            "has_header": false,
+            "bad_row_policy": "quarantine",
+            "fixed_width_layout": { "columns": [
+                { "name": "id", "start": 1, "width": 3 },
+                { "name": "name", "start": 4, "width": 5 },
+            ] },
+        }),
+    );
+    assert_eq!(result.rejected_rows, 1);
+    let rejected = std::fs::read_to_string(result.rejected_file.unwrap()).unwrap();
+    assert!(rejected.lines().nth(1).unwrap().starts_with("2,"), "{}", rejected);
+    let manifest = result.manifest.unwrap();
+    assert_eq!((manifest.pieces[1].first_row, manifest.pieces[1].last_row), (4, 4));
+}
+
+#[test]
+fn header_template_and_renames() {
+    let fixture = Fixture::new("headers");
+    let input = fixture.input("raw.csv", "1,a,x\n2,b,y\n3,c,z\n");
+    let result = fixture.split_and_verify(
+        &input,
+        json!({
+            "has_header": false,
+            "header_template": "c{index:02}",
+            "rename_columns": [{ "column": 2, "name": "name" }],
+            "columns": ["name", "c01"],
+        }),
+    );
+    let piece = &result.manifest.unwrap().pieces[0].path;
+    assert_eq!(std::fs::read_to_string(piece).unwrap(), "name,c01\na,1\nb,2\n");
+
+    let params: SplitParams = serde_json::from_value(json!({
+        "input_path": input,
+        "output_dir": fixture.output_dir(),
+        "rows_per_file": 2,
+        "has_header": false,
+        "convert_to_excel": false,
+        "header_template": "c{index}",
+        "rename_columns": [{ "column": "c3", "name": "c1" }],
+    }))
+    .unwrap();
+    let result = split(params, &NoProgress);
+    assert_eq!(result.error.map(|e| e.code()), Some("invalid_params"));
+}
+
+#[test]
+fn filter_and_computed_columns_fill_every_piece() {
+    let fixture = Fixture::new("filter");
+    // 超过50万行的输入原本会按多线程分块，筛选后每个分块仍应写满
+    let mut content = String::from("id,amount\n");
+    for i in 1..=520_000 {
+        content.push_str(&format!("{},{}\n", i, i % 4));
+    }
+    let input = fixture.input("big.csv", &content);
+    let result = fixture.split_and_verify(
+        &input,
+        json!({
+            "rows_per_file": 100_000,
+            "computed_columns": [{ "name": "double", "expression": "number(amount) * 2" }],
+            "filter": "double >= 4",
+        }),
+    );
+    assert_eq!(result.filtered_rows, 260_000);
+    let counts: Vec<usize> = result.manifest.unwrap().pieces.iter().map(|p| p.row_count).collect();
+    assert_eq!(counts, [100_000, 100_000, 60_000]);
+}
+
+#[test]
+fn computed_columns_are_written_and_checked() {
+    let fixture = Fixture::new("computed");
+    let input = fixture.input("orders.csv", "id,price,qty\n1,2.5,4\n2,3,1\n");
+    let result = fixture.split_and_verify(
+        &input,
+        json!({ "computed_columns": [
+            { "name": "total", "expression": "number(price) * qty" },
+            { "name": "label", "expression": "concat('#', id, '-', total)" },
+        ] }),
+    );
+    let piece = &result.manifest.unwrap().pieces[0].path;
+    assert_eq!(std::fs::read_to_string(piece).unwrap(), "id,price,qty,total,label\n1,2.5,4,10,#1-10\n2,3,1,3,#2-3\n");
+
+    let params: SplitParams = serde_json::from_value(json!({
+        "input_path": input,
+        "output_dir": fixture.output_dir(),
+        "rows_per_file": 2,
+        "has_header": true,
+        "convert_to_excel": false,
+        "computed_columns": [{ "name": "qty", "expression": "1" }],
+    }))
+    .unwrap();
+    let error = split(params, &NoProgress).error.unwrap();
+    assert_eq!(error.code(), "invalid_params");
+    assert!(error.message(Locale::EnUs).contains("same name as an existing column: qty"));
+}
+
+#[test]
+fn dedupe_keeps_the_last_row_per_key() {
+    let fixture = Fixture::new("dedupe");
+    let input = fixture.input("events.csv", "id,state\n1,new\n2,new\n1,paid\n3,new\n2,shipped\n");
+    let result = fixture.split_and_verify(&input, json!({ "dedupe": { "columns": ["id"], "keep": "last" } }));
+    let manifest = result.manifest.unwrap();
+    assert_eq!(manifest.total_rows, 3);
+    let contents: String = manifest.pieces.iter()
+        .map(|piece| std::fs::read_to_string(&piece.path).unwrap().lines().skip(1).map(|l| format!("{}\n", l)).collect::<String>())
+        .collect();
+    assert_eq!(contents, "1,paid\n3,new\n2,shipped\n");
+}
+
+#[test]
+fn groups_stay_together_up_to_the_maximum() {
+    let fixture = Fixture::new("group");
+    let input = fixture.input(
+        "orders.csv",
+        "customer,order\nc1,1\nc1,2\nc1,3\nc2,4\nc3,5\nc3,6\nc3,7\nc3,8\nc4,9\n",
+    );
+    let result = fixture.split_and_verify(&input, json!({ "group_by": ["customer"], "max_rows_per_file": 3 }));
+    let row_counts: Vec<usize> = result.manifest.unwrap().pieces.iter().map(|p| p.row_count).collect();
+    assert_eq!(row_counts, [3, 3, 2, 1]);
+    assert_eq!(result.split_groups, 1);
+
+    let invalid = |extra: Value| {
+        let mut value = json!({
+            "input_path": input,
+            "output_dir": fixture.output_dir(),
+            "rows_per_file": 2,
+            "has_header": true,
+            "convert_to_excel": false,
+            "group_by": ["customer"],
+        });
+        for (key, field) in extra.as_object().unwrap() {
+            value[key] = field.clone();
+        }
+        let error = split(serde_json::from_value(value).unwrap(), &NoProgress).error.unwrap();
+        assert_eq!(error.code(), "invalid_params");
+        error.message(Locale::EnUs)
+    };
+    assert!(invalid(json!({ "checkpoint": true })).contains("cannot be combined with checkpoints"));
+    assert!(invalid(json!({ "max_rows_per_file": 1 })).contains("maximum rows per piece (1) cannot be less than the rows per file (2)"));
+}
+
+#[test]
+fn rejected_file_follows_the_overwrite_policy() {
+    let fixture = Fixture::new("rejected-policy");
+    let input = fixture.input("orders.csv", "id,name\n1,a\n2,b,extra\n3,c\n");
+    let output_dir = fixture.dir.join("out");
+    std::fs::create_dir_all(&output_dir).unwrap();
+    let existing = output_dir.join("orders_rejected.csv");
+    std::fs::write(&existing, "old\n").unwrap();
+    let run = |policy: &str| {
+        let params: SplitParams = serde_json::from_value(json!({
+            "input_path": input,
+            "output_dir": fixture.output_dir(),
+            "rows_per_file": 2,
+            "has_header": true,
+            "convert_to_excel": false,
+            "bad_row_policy": "quarantine",
+            "overwrite_policy": policy,
+        }))
+        .unwrap();
+        split(params, &NoProgress)
+    };
+    let names = || {
+        let mut names: Vec<String> = std::fs::read_dir(&output_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    };
+
+    // 已有的隔离文件与分块一样按覆盖策略处理，报错时不改动输出目录
+    let result = run("fail");
+    assert_eq!(result.error.unwrap().code(), "output_exists");
+    assert_eq!(names(), ["orders_rejected.csv"]);
+    assert_eq!(std::fs::read_to_string(&existing).unwrap(), "old\n");
+
+    let result = run("skip");
+    assert!(result.success, "{:?}", result.error);
+    assert_eq!(result.rejected_file, None);
+    assert_eq!(result.skipped_files, [existing.display().to_string()]);
+    assert_eq!(std::fs::read_to_string(&existing).unwrap(), "old\n");
+
+    let result = run("auto_rename");
+    assert!(result.success, "{:?}", result.error);
+    let renamed = output_dir.join("orders_rejected (1).csv");
+    assert_eq!(result.rejected_file, Some(renamed.display().to_string()));
+    assert!(std::fs::read_to_string(&renamed).unwrap().contains("extra"));
+    assert_eq!(std::fs::read_to_string(&existing).unwrap(), "old\n");
+
+    let result = run("overwrite");
+    assert!(result.success, "{:?}", result.error);
+    assert!(std::fs::read_to_string(&existing).unwrap().contains("extra"));
+}
@@ -5,7 +5,11 @@ const { appDataDir } = window.__TAURI__.path;
 
 // 全局变量
 let csvFilePath = '';
+let csvFilePaths = [];
+let watching = false;
 let outputDir = '';
+// 后端按界面语言返回错误信息
+const locale = navigator.language;
 
 // DOM元素
 const csvFileInput = document.getElementById('csv-file-path');
@@ -13,9 +17,25 @@ const outputDirInput = document.getElementById('output-dir');
 const selectFileBtn = document.getElementById('select-file-btn');
 const selectDirBtn = document.getElementById('select-dir-btn');
 const splitBtn = document.getElementById('split-btn');
+const resumeBtn = document.getElementById('resume-btn');
+const watchBtn = document.getElementById('watch-btn');
 const hasHeaderCheckbox = document.getElementById('has-header');
 const convertExcelCheckbox = document.getElementById('convert-excel');
+const checkpointCheckbox = document.getElementById('checkpoint');
+const flexibleCheckbox = document.getElementById('flexible');
+const columnsInput = document.getElementById('columns');
+const renameColumnsInput = document.getElementById('rename-columns');
+const filterInput = document.getElementById('row-filter');
+const computedColumnsInput = document.getElementById('computed-columns');
+const dedupeCheckbox = document.getElementById('dedupe');
+const dedupeColumnsInput = document.getElementById('dedupe-columns');
+const dedupeKeepSelect = document.getElementById('dedupe-keep');
+const sortKeysInput = document.getElementById('sort-keys');
+const groupByInput = document.getElementById('group-by');
+const maxRowsPerFileInput = document.getElementById('max-rows-per-file');
 const rowsPerFileInput = document.getElementById('rows-per-file');
+const overwritePolicySelect = document.getElementById('overwrite-policy');
+const badRowPolicySelect = document.getElementById('bad-row-policy');
 const progressContainer = document.getElementById('progress-container');
 const progressBar = document.getElementById('progress-bar');
 const progressText = document.getElementById('progress-text');
@@ -45,6 +65,8 @@ function setupEventListeners() {
     csvFileInput.addEventListener('click', selectCsvFile);
     outputDirInput.addEventListener('click', selectOutputDirectory);
     splitBtn.addEventListener('click', startCsvSplit);
+    resumeBtn.addEventListener('click', resumeCsvSplit);
+    watchBtn.addEventListener('click', toggleWatch);
   
   // 监听输入变化以更新按钮状态
   [csvFileInput, outputDirInput, rowsPerFileInput].forEach(input => {
@@ -65,16 +87,23 @@ async function selectCsvFile() {
     
     console.log('Tauri available, opening file dialog...');
     const selected = await window.__TAURI__.dialog.open({
+      multiple: true,
       filters: [{
         name: 'CSV文件',
         extensions: ['csv']
+      }, {
+        name: 'JSON文件',
+        extensions: ['json', 'jsonl', 'ndjson']
       }]
     });
     
     console.log('File selected:', selected);
     if (selected) {
-      csvFilePath = Array.isArray(selected) ? selected[0] : selected;
-      csvFileInput.value = csvFilePath;
+      csvFilePaths = Array.isArray(selected) ? selected : [selected];
+      csvFilePath = csvFilePaths[0] || '';
+      csvFileInput.value = csvFilePaths.length > 1
+        ? `已选择 ${csvFilePaths.length} 个文件`
+        : csvFilePath;
       updateSplitButtonState();
       console.log('File path set:', csvFilePath);
     }
@@ -124,6 +153,64 @@ async function selectOutputDirectory() {
 function updateSplitButtonState() {
   const isValid = csvFilePath && outputDir && rowsPerFileInput.value > 0;
   splitBtn.disabled = !isValid;
+  resumeBtn.disabled = !(csvFilePaths.length === 1 && outputDir);
+  watchBtn.disabled = !watching && !(outputDir && rowsPerFileInput.value > 0);
+}
+
+// 逗号分隔的列：纯数字为列序号，其余为列名
+function parseColumnList(text) {
+  return text
+    .split(',')
+    .map(column => column.trim())
+    .filter(column => column !== '')
+    .map(parseColumnRef);
+}
+
+// 纯数字为列序号，其余为列名
+function parseColumnRef(column) {
+  return /^\d+$/.test(column) ? parseInt(column) : column;
+}
+
+// 去重参数，未勾选时为 null
+function dedupeParams() {
+  if (!dedupeCheckbox.checked) {
+    return null;
+  }
+  return { columns: parseColumnList(dedupeColumnsInput.value), keep: dedupeKeepSelect.value };
+}
+
+// 逗号分隔的排序键，每个键为 列[:asc|desc][:text|number|date]，无法识别的部分忽略
+function parseSortKeys(text) {
+  return text
+    .split(',')
+    .map(key => key.split(':').map(part => part.trim()))
+    .filter(parts => parts[0] !== '')
+    .map(([column, ...options]) => ({
+      column: parseColumnRef(column),
+      descending: options.includes('desc'),
+      compare: options.find(option => ['text', 'number', 'date'].includes(option)) || 'text'
+    }));
+}
+
+// 逗号分隔的 列=新列名，没有等号的项忽略
+function parseRenameList(text) {
+  return text
+    .split(',')
+    .map(item => item.split('='))
+    .filter(pair => pair.length === 2 && pair[0].trim() !== '')
+    .map(([column, name]) => ({ column: parseColumnRef(column.trim()), name: name.trim() }));
+}
+
+// 每行一个 列名=表达式，表达式中可以再出现等号
+function parseComputedColumns(text) {
+  return text
+    .split('\n')
+    .map(line => line.trim())
+    .filter(line => line.includes('='))
+    .map(line => {
+      const at = line.indexOf('=');
+      return { name: line.slice(0, at).trim(), expression: line.slice(at + 1).trim() };
+    });
 }
 
 // 开始CSV分割
@@ -145,26 +232,30 @@ async function startCsvSplit() {
     const convertToExcel = convertExcelCheckbox.checked;
     const result = await invoke('split_csv', {
       params: {
-        input_path: csvFilePath,
+        // 选择了多个文件时按批量任务处理
+        input_path: csvFilePaths.length > 1 ? '' : csvFilePath,
+        input_paths: csvFilePaths.length > 1 ? csvFilePaths : [],
         output_dir: outputDir,
         rows_per_file: rowsPerFile,
         has_header: hasHeader,
-        convert_to_excel: convertToExcel
-      }
+        convert_to_excel: convertToExcel,
+        overwrite_policy: overwritePolicySelect.value,
+        bad_row_policy: badRowPolicySelect.value,
+        flexible: flexibleCheckbox.checked,
+        rename_columns: parseRenameList(renameColumnsInput.value),
+        computed_columns: parseComputedColumns(computedColumnsInput.value),
+        filter: filterInput.value.trim() || null,
+        columns: parseColumnList(columnsInput.value),
+        dedupe: dedupeParams(),
+        sort: parseSortKeys(sortKeysInput.value),
+        group_by: parseColumnList(groupByInput.value),
+        max_rows_per_file: parseInt(maxRowsPerFileInput.value) || null,
+        checkpoint: checkpointCheckbox.checked
+      },
+      locale
     });
     
-    // 处理结果
-    if (result.success) {
-      updateProgress(100, '分割完成！');
-      showStatus(`分割完成！共生成 ${result.file_count} 个文件`, 'success');
-      
-      // 2秒后隐藏进度条
-      setTimeout(() => {
-        showProgress(false);
-      }, 2000);
-    } else {
-      throw new Error(result.error || '分割失败，请检查文件格式和权限');
-    }
+    handleSplitResult(result);
     
   } catch (error) {
     showProgress(false);
@@ -174,6 +265,170 @@ async function startCsvSplit() {
   }
 }
 
+// 从断点继续上次中断的分割（使用断点中保存的分割参数）
+async function resumeCsvSplit() {
+  try {
+    showProgress(true);
+    updateProgress(0, '正在检查断点...');
+    
+    const result = await invoke('resume_split', {
+      params: {
+        input_path: csvFilePath,
+        output_dir: outputDir
+      },
+      locale
+    });
+    
+    handleSplitResult(result);
+    
+  } catch (error) {
+    showProgress(false);
+    console.error('Resume error:', error);
+    const errorMessage = error?.message || error?.toString() || '未知错误';
+    showStatus('继续分割失败: ' + errorMessage, 'error');
+  }
+}
+
+// 开始/停止监视文件夹：新出现的CSV按当前设置自动分割，处理后移入监视目录下的 archive
+async function toggleWatch() {
+  try {
+    if (watching) {
+      await invoke('stop_watch');
+      watching = false;
+      watchBtn.textContent = '监视文件夹';
+      showStatus('已停止监视', 'info');
+      updateSplitButtonState();
+      return;
+    }
+    
+    const watchDir = await window.__TAURI__.dialog.open({
+      directory: true,
+      multiple: false
+    });
+    if (!watchDir) {
+      return;
+    }
+    
+    await invoke('start_watch', {
+      params: {
+        watch_dir: Array.isArray(watchDir) ? watchDir[0] : watchDir,
+        preset: {
+          input_path: '',
+          output_dir: outputDir,
+          rows_per_file: parseInt(rowsPerFileInput.value),
+          has_header: hasHeaderCheckbox.checked,
+          convert_to_excel: convertExcelCheckbox.checked,
+          overwrite_policy: overwritePolicySelect.value,
+          bad_row_policy: badRowPolicySelect.value,
+          flexible: flexibleCheckbox.checked,
+          rename_columns: parseRenameList(renameColumnsInput.value),
+          computed_columns: parseComputedColumns(computedColumnsInput.value),
+          filter: filterInput.value.trim() || null,
+          columns: parseColumnList(columnsInput.value),
+          dedupe: dedupeParams(),
+          sort: parseSortKeys(sortKeysInput.value),
+          group_by: parseColumnList(groupByInput.value),
+          max_rows_per_file: parseInt(maxRowsPerFileInput.value) || null
+        }
+      },
+      locale
+    });
+    watching = true;
+    watchBtn.textContent = '停止监视';
+    showStatus('正在监视文件夹，新文件写入完成后将自动分割', 'info');
+  } catch (error) {
+    console.error('Watch error:', error);
+    const errorMessage = error?.message || error?.toString() || '未知错误';
+    showStatus('监视失败: ' + errorMessage, 'error');
+  }
+}
+
+// 监视模式下每处理完一个文件收到一次结果
+window.__TAURI__?.event?.listen('watch-result', ({ payload }) => {
+  const fileName = payload.input_path.split(/[\\/]/).pop();
+  if (payload.result.success) {
+    showStatus(`${fileName} 分割完成，共生成 ${payload.result.file_count} 个文件`, 'success');
+  } else {
+    showStatus(`${fileName} 分割失败: ${describeError(payload.result.error)}`, 'error');
+  }
+});
+
+// 分割过程中的进度（批量任务中各文件的进度交替到达，显示最近一个）
+const STAGE_TEXT = {
+  deduplicating: '正在查找重复行',
+  sorting: '正在读取并排序',
+  verifying: '正在校验',
+  converting: '正在转换为Excel',
+  finishing: '正在保存'
+};
+window.__TAURI__?.event?.listen('split-progress', ({ payload }) => {
+  const fileName = payload.input_path.split(/[\\/]/).pop();
+  const percent = payload.total_bytes > 0
+    ? Math.min(99, Math.floor(payload.bytes_read / payload.total_bytes * 100))
+    : 0;
+  if (payload.stage === 'splitting') {
+    updateProgress(percent, `${fileName}：已写入 ${payload.rows} 行，${payload.pieces} 个文件`);
+  } else if (payload.stage === 'deduplicating' || payload.stage === 'sorting') {
+    updateProgress(percent, `${fileName}：${STAGE_TEXT[payload.stage]}，已读取 ${payload.rows} 行`);
+  } else {
+    updateProgress(99, `${fileName}：${STAGE_TEXT[payload.stage]}...`);
+  }
+});
+
+// 处理分割结果
+function handleSplitResult(result) {
+  if (result.success) {
+    updateProgress(100, '分割完成！');
+    const skipped = result.skipped_files?.length
+      ? `，跳过 ${result.skipped_files.length} 个已存在的文件`
+      : '';
+    const inputs = result.inputs?.length ? `${result.inputs.length} 个输入文件，` : '';
+    const rejected = result.rejected_rows
+      ? `，跳过 ${result.rejected_rows} 个格式错误的行${result.rejected_file ? `（已写入 ${result.rejected_file}）` : ''}`
+      : '';
+    const adjusted = result.adjusted_rows ? `，补齐或截断 ${result.adjusted_rows} 行` : '';
+    const filtered = result.filtered_rows ? `，筛除 ${result.filtered_rows} 行` : '';
+    const duplicates = result.duplicate_rows ? `，去除 ${result.duplicate_rows} 个重复行` : '';
+    const splitGroups = result.split_groups ? `，${result.split_groups} 个组超过最多行数被切开` : '';
+    showStatus(`分割完成！${inputs}共生成 ${result.file_count} 个文件${skipped}${filtered}${duplicates}${adjusted}${rejected}${splitGroups}`, 'success');
+    
+    // 2秒后隐藏进度条
+    setTimeout(() => {
+      showProgress(false);
+    }, 2000);
+  } else {
+    // 批量任务中列出失败的文件
+    const failures = (result.inputs || [])
+      .filter(input => !input.success)
+      .map(input => `${input.input_path}: ${describeError(input.error)}`);
+    let message = result.error ? describeError(result.error) : '分割失败，请检查文件格式和权限';
+    if (result.checkpoint_saved) {
+      message += '（已保存断点，排除问题后可继续分割）';
+    }
+    throw new Error(failures.length ? `${message}：${failures.join('；')}` : message);
+  }
+}
+
+// 后端错误为 { code, message, path?, row?, column?, byte? }，按错误码补充操作提示
+const ERROR_HINTS = {
+  output_exists: '可在“输出文件已存在时”中选择覆盖、跳过或自动重命名',
+  permission_denied: '请检查文件或目录的权限',
+  encoding: '请先将文件另存为UTF-8编码，或在“遇到格式错误的行时”中选择跳过',
+  field_count: '可勾选“补齐或截断字段数不一致的行”，或在“遇到格式错误的行时”中选择跳过或写入隔离文件',
+  column_not_found: '请检查“列改名”、“计算列”、“筛选条件”和“输出的列”中的列名或列序号',
+  invalid_expression: '表达式示例：concat(region, \'-\', substr(code, 1, 3))',
+  invalid_filter: '条件示例：status = \'paid\' and amount > 100',
+  header_mismatch: '替换的标题行需要与输入文件的列数一致',
+  checkpoint_not_found: '请重新开始分割'
+};
+function describeError(error) {
+  if (!error) {
+    return '未知错误';
+  }
+  const hint = ERROR_HINTS[error.code];
+  return hint ? `${error.message}，${hint}` : error.message;
+}
+
 // 显示/隐藏进度条
 function showProgress(show) {
   if (show) {